@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scoring::ScoringRules;
+
+// The language of the calls
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+    Es,
+    De,
+}
+
+// How an advantage is called
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdvantageStyle {
+    // "Advantage Federer"
+    #[default]
+    Name,
+    // "A-40" / "40-A", server first, with `ad_label` for the player ahead
+    Score,
+}
+
+// How calls are written, for display systems with their own conventions
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Notation {
+    pub locale: Locale,
+    pub advantage: AdvantageStyle,
+    // Shown for the player ahead with `advantage: "score"`, e.g. "Ad" for "Ad-40"
+    pub ad_label: String,
+}
+
+impl Default for Notation {
+    fn default() -> Self {
+        Notation { locale: Locale::En, advantage: AdvantageStyle::Name, ad_label: "A".to_string() }
+    }
+}
+
+// The words of a locale. Calls are put together the same way in each, e.g. "<game> <name>; <name> <leads>
+// <n> <games> <to> <m>, <ordinal> <set>".
+struct Words {
+    love: &'static str,
+    // After level points, e.g. "15-all"
+    all_points: &'static str,
+    all: &'static str,
+    deuce: &'static str,
+    advantage: &'static str,
+    deciding_point: &'static str,
+    game_call: &'static str,
+    game: &'static str,
+    games: &'static str,
+    leads: &'static str,
+    to: &'static str,
+    and: &'static str,
+    set: &'static str,
+    match_call: &'static str,
+    ordinals: [&'static str; 5],
+    // After the set number past the fifth
+    ordinal_suffix: &'static str,
+}
+
+impl Locale {
+    fn words(&self) -> Words {
+        match self {
+            Locale::En => Words {
+                love: "love", all_points: "-all", all: "all", deuce: "Deuce", advantage: "Advantage", deciding_point: "deciding point",
+                game_call: "Game", game: "game", games: "games", leads: "leads", to: "to", and: "and", set: "set", match_call: "match",
+                ordinals: ["first", "second", "third", "fourth", "fifth"], ordinal_suffix: "th",
+            },
+            Locale::Fr => Words {
+                love: "0", all_points: " partout", all: "partout", deuce: "Égalité", advantage: "Avantage", deciding_point: "point décisif",
+                game_call: "Jeu", game: "jeu", games: "jeux", leads: "mène", to: "à", and: "et", set: "set", match_call: "match",
+                ordinals: ["premier", "deuxième", "troisième", "quatrième", "cinquième"], ordinal_suffix: "e",
+            },
+            Locale::Es => Words {
+                love: "nada", all_points: " iguales", all: "iguales", deuce: "Iguales", advantage: "Ventaja", deciding_point: "punto decisivo",
+                game_call: "Juego", game: "juego", games: "juegos", leads: "gana", to: "a", and: "y", set: "set", match_call: "partido",
+                ordinals: ["primer", "segundo", "tercer", "cuarto", "quinto"], ordinal_suffix: "º",
+            },
+            Locale::De => Words {
+                love: "0", all_points: " beide", all: "beide", deuce: "Einstand", advantage: "Vorteil", deciding_point: "entscheidender Punkt",
+                game_call: "Spiel", game: "Spiel", games: "Spiele", leads: "führt", to: "zu", and: "und", set: "Satz", match_call: "Sieg",
+                ordinals: ["erster", "zweiter", "dritter", "vierter", "fünfter"], ordinal_suffix: ".",
+            },
+        }
+    }
+}
+
+impl Words {
+    fn ordinal(&self, set: usize) -> String {
+        match set.checked_sub(1).and_then(|index| self.ordinals.get(index)) {
+            Some(ordinal) => ordinal.to_string(),
+            None => format!("{}{}", set, self.ordinal_suffix),
+        }
+    }
+
+    fn love(&self, count: i32) -> String {
+        if count == 0 { self.love.to_string() } else { count.to_string() }
+    }
+
+    fn count_games(&self, count: i32) -> String {
+        format!("{} {}", count, if count == 1 { self.game } else { self.games })
+    }
+
+    // A game score as called, server first: "15-love", "30-all", "40-15"
+    fn points(&self, score: &str) -> String {
+        let word = |points: &str| if points == "0" { self.love.to_string() } else { points.to_string() };
+        match score.split_once('-') {
+            Some((server, receiver)) if server == receiver => format!("{}{}", word(server), self.all_points),
+            Some((server, receiver)) => format!("{}-{}", word(server), word(receiver)),
+            None => score.to_string(),
+        }
+    }
+}
+
+// Turns a match's point log, fed point by point from the start, into the chair umpire's calls: "30-love",
+// "Advantage Federer", "4-3 Nadal" in a tiebreak, "Game Nadal; Nadal leads 4 games to 3, first set"
+pub struct Announcer {
+    players: [String; 2],
+    rules: ScoringRules,
+    notation: Notation,
+    words: Words,
+    // The score before the next point, in player order
+    sets: [i32; 2],
+    games: [i32; 2],
+    // Games of each finished set, in player order
+    set_scores: Vec<[i32; 2]>,
+}
+
+impl Announcer {
+    pub fn new(player1: &str, player2: &str, rules: ScoringRules, notation: Notation) -> Self {
+        let words = notation.locale.words();
+        Announcer { players: [player1.to_string(), player2.to_string()], rules, notation, words, sets: [0, 0], games: [0, 0], set_scores: Vec::new() }
+    }
+
+    // The call after `point`, the next point of the log
+    pub fn call(&mut self, point: &HashMap<String, serde_json::Value>) -> String {
+        let text = |key: &str| point.get(key).and_then(|value| value.as_str()).unwrap_or("").to_string();
+        let server = if text("server") == self.players[0] { 0 } else { 1 };
+        let winner = if text("winner") == self.players[0] { 0 } else { 1 };
+        // The log's game and set scores are server first
+        let pair = |score: String| {
+            let (a, b) = score.split_once('-').unwrap_or(("0", "0"));
+            let pair = [a.parse().unwrap_or(0), b.parse().unwrap_or(0)];
+            if server == 0 { pair } else { [pair[1], pair[0]] }
+        };
+        let (games, sets) = (pair(text("game_score")), pair(text("set_score")));
+        let tiebreak = self.rules.tiebreak_at(self.sets, self.games).is_some();
+        let set = self.set_scores.len() + 1;
+        let words = &self.words;
+
+        let call = if sets != self.sets {
+            self.set_scores.push(games);
+            if sets[winner] >= self.rules.sets_to_win {
+                let scores: Vec<String> = self.set_scores.iter().map(|set| format!("{}-{}", set[winner], set[1 - winner])).collect();
+                format!("{}, {} {} {} {}, {}", words.game_call, words.set, words.and, words.match_call, self.players[winner], scores.join(" "))
+            } else {
+                format!(
+                    "{} {} {} {} {}, {} {} {}",
+                    words.game_call, words.and, words.ordinal(set), words.set, self.players[winner], words.count_games(games[winner]), words.to, words.love(games[1 - winner]),
+                )
+            }
+        } else if games != self.games {
+            let lead = if games[0] == games[1] {
+                format!("{} {}", words.count_games(games[0]), words.all)
+            } else {
+                let leader = if games[0] > games[1] { 0 } else { 1 };
+                format!("{} {} {} {} {}", self.players[leader], words.leads, words.count_games(games[leader]), words.to, words.love(games[1 - leader]))
+            };
+            format!("{} {}; {}, {} {}", words.game_call, self.players[winner], lead, words.ordinal(set), words.set)
+        } else if tiebreak {
+            // Tiebreak scores are called leader first, with the leader's name
+            let points = pair(text("point_score"));
+            if points[0] == points[1] {
+                format!("{} {}", points[0], words.all)
+            } else {
+                let leader = if points[0] > points[1] { 0 } else { 1 };
+                format!("{}-{} {}", points[leader], words.love(points[1 - leader]), self.players[leader])
+            }
+        } else {
+            let ad = &self.notation.ad_label;
+            match (text("point_score").as_str(), self.notation.advantage) {
+                ("Deuce", _) if self.rules.no_ad => format!("{}, {}", words.deuce, words.deciding_point),
+                ("Deuce", _) => words.deuce.to_string(),
+                ("Ad-In", AdvantageStyle::Name) => format!("{} {}", words.advantage, self.players[server]),
+                ("Ad-Out", AdvantageStyle::Name) => format!("{} {}", words.advantage, self.players[1 - server]),
+                ("Ad-In", AdvantageStyle::Score) => format!("{}-40", ad),
+                ("Ad-Out", AdvantageStyle::Score) => format!("40-{}", ad),
+                (score, _) => words.points(score),
+            }
+        };
+
+        self.games = if sets != self.sets { [0, 0] } else { games };
+        self.sets = sets;
+        call
+    }
+}
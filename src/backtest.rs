@@ -0,0 +1,102 @@
+use serde::Deserialize;
+
+use crate::{find_player, simulate_match_parallel, ModelConfig, Player, SimulationConfig};
+
+// A backtest file: the players taking part, and the fixtures to replay with their market odds
+#[derive(Deserialize)]
+struct BacktestFile {
+    players: Vec<Player>,
+    fixtures: Vec<Fixture>,
+    #[serde(default = "default_simulations")]
+    num_simulations: usize,
+    // Only back a player when simulated probability * odds - 1 exceeds this
+    #[serde(default)]
+    min_edge: f64,
+    #[serde(default)]
+    model: ModelConfig,
+}
+
+#[derive(Deserialize)]
+struct Fixture {
+    player1: String,
+    player2: String,
+    #[serde(default = "default_best_of")]
+    best_of: i32,
+    #[serde(default)]
+    grand_slam: bool,
+    // Decimal odds for player1 and player2
+    odds: [f64; 2],
+    winner: String,
+}
+
+fn default_simulations() -> usize {
+    2000
+}
+
+fn default_best_of() -> i32 {
+    3
+}
+
+// Simulates every fixture and backs the side with the largest edge over the market, one unit per bet
+pub fn run(path: &str) -> Result<(), String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    let file: BacktestFile = serde_json::from_str(&contents).map_err(|e| format!("invalid backtest file {}: {}", path, e))?;
+
+    let mut bets = 0;
+    let mut bets_won = 0;
+    let mut profit = 0.0;
+    let mut favourites_won = 0;
+
+    println!("Backtest over {} fixtures ({} simulations each):", file.fixtures.len(), file.num_simulations);
+    for fixture in &file.fixtures {
+        let player1 = find_player(&file.players, &fixture.player1)?.clone();
+        let player2 = find_player(&file.players, &fixture.player2)?.clone();
+        let winner = find_player(&file.players, &fixture.winner)?.name.clone();
+        if winner != player1.name && winner != player2.name {
+            return Err(format!("winner '{}' did not play in {} vs {}", winner, player1.name, player2.name));
+        }
+
+        let config = SimulationConfig {
+            player1,
+            player2,
+            best_of: fixture.best_of,
+            grand_slam: fixture.grand_slam,
+            model: file.model.clone(),
+            num_simulations: file.num_simulations,
+            log_interval: usize::MAX,
+            ..SimulationConfig::default()
+        };
+        let report = simulate_match_parallel(&config, &[], None);
+        let names = [&config.player1.name, &config.player2.name];
+        let probs = names.map(|name| report.win_probability(name));
+
+        let favourite = if probs[0] >= probs[1] { 0 } else { 1 };
+        if *names[favourite] == winner {
+            favourites_won += 1;
+        }
+
+        let edges = [probs[0] * fixture.odds[0] - 1.0, probs[1] * fixture.odds[1] - 1.0];
+        let pick = if edges[0] >= edges[1] { 0 } else { 1 };
+        let bet = if edges[pick] > file.min_edge {
+            bets += 1;
+            let won = *names[pick] == winner;
+            let result = if won { fixture.odds[pick] - 1.0 } else { -1.0 };
+            bets_won += won as i32;
+            profit += result;
+            format!("back {} @ {:.2} (edge {:+.3}): {:+.2}", names[pick], fixture.odds[pick], edges[pick], result)
+        } else {
+            "no bet".to_string()
+        };
+
+        println!(" {} vs {}: simulated {:.1}% / {:.1}%, winner {} - {}", names[0], names[1], probs[0] * 100.0, probs[1] * 100.0, winner, bet);
+    }
+
+    let fixtures = file.fixtures.len().max(1) as f64;
+    println!("\nSimulated favourite won: {:.2}% of fixtures", favourites_won as f64 / fixtures * 100.0);
+    println!("Bets placed: {}", bets);
+    if bets > 0 {
+        println!("Hit rate: {:.2}%", bets_won as f64 / bets as f64 * 100.0);
+        println!("Profit: {:+.2} units, ROI: {:+.2}%", profit, profit / bets as f64 * 100.0);
+    }
+    Ok(())
+}
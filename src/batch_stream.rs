@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread::JoinHandle;
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+use crate::sink::ResultSink;
+use crate::{config_from_args, flag_value, simulate_match_parallel, BatchResult, SimulationConfig, SimulationReport};
+
+// Batches held for the consumer before the workers wait, unless --buffer is given
+const DEFAULT_BUFFER: usize = 4;
+
+// Hands each finished batch to the stream. The send blocks the worker thread while the buffer is full, which
+// is the backpressure; once the stream is dropped the batches are discarded.
+struct ChannelSink {
+    sender: mpsc::Sender<BatchResult>,
+}
+
+impl ResultSink for ChannelSink {
+    fn on_batch(&self, batch: &BatchResult) {
+        let _ = self.sender.blocking_send(batch.clone());
+    }
+
+    fn on_complete(&self, _report: &SimulationReport) {}
+
+    fn wants_matches(&self) -> bool {
+        false
+    }
+}
+
+// A parallel simulation as a `Stream` of its batches in completion order, for async code that wants partial
+// results without blocking on the whole run. The run plays on its own thread on the rayon pool; at most
+// `buffer` finished batches wait for the consumer before the workers stop for it. Dropping the stream lets
+// the run finish without delivering the rest.
+pub(crate) struct BatchStream {
+    receiver: mpsc::Receiver<BatchResult>,
+    run: Option<JoinHandle<SimulationReport>>,
+}
+
+impl BatchStream {
+    pub fn start(config: SimulationConfig, buffer: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        let run = std::thread::spawn(move || simulate_match_parallel(&config, &[&ChannelSink { sender }], None));
+        BatchStream { receiver, run: Some(run) }
+    }
+
+    // The run's report, once the stream has ended; None before that
+    pub fn report(&mut self) -> Option<SimulationReport> {
+        if !self.receiver.is_closed() || !self.receiver.is_empty() {
+            return None;
+        }
+        self.run.take().map(|run| run.join().expect("the simulation thread panicked"))
+    }
+}
+
+impl Stream for BatchStream {
+    type Item = BatchResult;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<BatchResult>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+// batches [--config <config.json>] [--seed <n>] [--buffer <n>]
+// Runs the configured simulation through a BatchStream and prints a JSON line per batch as it arrives, with
+// the batch's match wins and the running totals, then the final match wins
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut config = config_from_args(args)?;
+    let buffer = match flag_value(args, "--buffer") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --buffer: {}", e))?,
+        None => DEFAULT_BUFFER,
+    };
+    // Nothing is written: the batches are the output
+    config.log_interval = usize::MAX;
+    config.match_log = false;
+    config.run_dir = Default::default();
+
+    let runtime = tokio::runtime::Builder::new_current_thread().build().map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        let mut stream = BatchStream::start(config, buffer);
+        let mut totals: HashMap<String, i32> = HashMap::new();
+        let mut batches = 0;
+        while let Some(batch) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            batches += 1;
+            for (player, wins) in &batch.match_wins {
+                *totals.entry(player.clone()).or_insert(0) += wins;
+            }
+            println!("{}", serde_json::json!({ "batch": batches, "match_wins": batch.match_wins, "shots": batch.total_shots, "total_match_wins": totals }));
+        }
+        let report = stream.report().ok_or("the batch stream ended before the run")?;
+        println!("{}", serde_json::json!({ "batches": batches, "match_wins": report.match_wins, "shots": report.total_shots }));
+        Ok(())
+    })
+}
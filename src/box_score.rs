@@ -0,0 +1,95 @@
+use comfy_table::Table;
+use serde::Serialize;
+
+use crate::{table, TennisMatch};
+
+// One player's line of a box score
+#[derive(Serialize)]
+pub struct PlayerBoxScore {
+    pub name: String,
+    pub aces: i32,
+    pub double_faults: i32,
+    // Points served, penalty points aside
+    pub serve_points: i32,
+    pub serve_points_won: i32,
+    pub first_serves_in: i32,
+    pub first_serve_points_won: i32,
+    pub second_serve_points: i32,
+    pub second_serve_points_won: i32,
+    pub break_points_saved: i32,
+    pub break_points_faced: i32,
+    // On the opponent's serve
+    pub break_points_won: i32,
+    pub break_point_chances: i32,
+    pub return_points_won: i32,
+    // Penalty points included
+    pub points_won: i32,
+    // Only with a return profile
+    pub return_errors: Option<i32>,
+    // Without a first serve profile no first serve misses, so the first and second serve counts say little
+    pub first_serve_modeled: bool,
+}
+
+// A match's stats as on a broadcast stat sheet, in player order. Winners and unforced errors are not modeled.
+#[derive(Serialize)]
+pub struct BoxScore {
+    pub players: [PlayerBoxScore; 2],
+    pub total_points: i32,
+}
+
+impl BoxScore {
+    pub fn from_match(match_sim: &TennisMatch) -> Self {
+        let players = [&match_sim.player1, &match_sim.player2];
+        let stat = |player: usize, key: &str| match_sim.match_stat(player, key);
+        let served = |player: usize| stat(player, "deuce_points_served") + stat(player, "ad_points_served");
+        let served_won = |player: usize| stat(player, "deuce_points_won") + stat(player, "ad_points_won");
+        let line = |player: usize| {
+            let opponent = 1 - player;
+            let return_points_won = served(opponent) - served_won(opponent);
+            PlayerBoxScore {
+                name: players[player].name.clone(),
+                aces: stat(player, "aces"),
+                double_faults: stat(player, "double_faults"),
+                serve_points: served(player),
+                serve_points_won: served_won(player),
+                first_serves_in: stat(player, "first_serves_in"),
+                first_serve_points_won: stat(player, "first_serve_points_won"),
+                second_serve_points: stat(player, "second_serve_points"),
+                second_serve_points_won: stat(player, "second_serve_points_won"),
+                break_points_saved: stat(player, "break_points_saved"),
+                break_points_faced: stat(player, "break_points_faced"),
+                break_points_won: stat(opponent, "break_points_faced") - stat(opponent, "break_points_saved"),
+                break_point_chances: stat(opponent, "break_points_faced"),
+                return_points_won,
+                points_won: served_won(player) + return_points_won + stat(opponent, "penalty_points"),
+                return_errors: players[player].return_profile.as_ref().map(|_| stat(player, "return_errors")),
+                first_serve_modeled: players[player].first_serve.is_some(),
+            }
+        };
+        BoxScore { players: [line(0), line(1)], total_points: match_sim.point_log.len() as i32 }
+    }
+
+    pub fn table(&self, plain: bool) -> Table {
+        let [a, b] = &self.players;
+        let mut output = table::new(&["", a.name.as_str(), b.name.as_str()], plain);
+        let share = |won: i32, of: i32| format!("{}/{} ({:.0}%)", won, of, won as f64 / of.max(1) as f64 * 100.0);
+        let mut row = |label: &str, cell: &dyn Fn(&PlayerBoxScore) -> String| {
+            output.add_row(vec![label.to_string(), cell(a), cell(b)]);
+        };
+        row("Aces", &|player| player.aces.to_string());
+        row("Double faults", &|player| player.double_faults.to_string());
+        let modeled = |cell: &dyn Fn(&PlayerBoxScore) -> String, player: &PlayerBoxScore| if player.first_serve_modeled { cell(player) } else { "-".to_string() };
+        row("1st serve %", &|player| modeled(&|player| share(player.first_serves_in, player.serve_points), player));
+        row("1st serve points won", &|player| modeled(&|player| share(player.first_serve_points_won, player.first_serves_in), player));
+        row("2nd serve points won", &|player| modeled(&|player| share(player.second_serve_points_won, player.second_serve_points), player));
+        row("Serve points won", &|player| share(player.serve_points_won, player.serve_points));
+        row("Break points saved", &|player| share(player.break_points_saved, player.break_points_faced));
+        row("Break points won", &|player| share(player.break_points_won, player.break_point_chances));
+        row("Return points won", &|player| share(player.return_points_won, a.serve_points + b.serve_points - player.serve_points));
+        if a.return_errors.is_some() || b.return_errors.is_some() {
+            row("Return errors", &|player| player.return_errors.map_or("-".to_string(), |errors| errors.to_string()));
+        }
+        row("Total points won", &|player| share(player.points_won, self.total_points));
+        output
+    }
+}
@@ -0,0 +1,309 @@
+use crate::scenario;
+use crate::surface::Surface;
+use crate::{unchecked_config_from_args, LogFormat, LogGranularity, MomentumDecay, Player, RunMetadata, SimulationConfig};
+
+// Measured on the default players: a match runs about 0.8 * best_of sets of about 68 points each, a logged
+// point takes about 125 bytes of CSV or 20 bytes of Parquet, and about 3 KB in memory before it is written. A
+// game takes about 6.5 points and a logged game about as much as a logged point.
+const SETS_PER_BEST_OF: f64 = 0.8;
+const POINTS_PER_SET: f64 = 68.0;
+const POINTS_PER_GAME: f64 = 6.5;
+const CSV_BYTES_PER_POINT: f64 = 125.0;
+const PARQUET_BYTES_PER_POINT: f64 = 20.0;
+const MEMORY_BYTES_PER_POINT: f64 = 3072.0;
+// Size of one match's row in the match log
+const MATCH_LOG_BYTES_PER_MATCH: f64 = 80.0;
+// Size of one match's excitement index in the checkpoint JSON
+const CHECKPOINT_BYTES_PER_MATCH: f64 = 100.0;
+
+// Problems that would make the run fail or the model meaningless, and settings that are legal but
+// probably not what was intended
+struct Findings {
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Findings {
+    fn probability(&mut self, label: &str, value: f64) {
+        if !(0.0..=1.0).contains(&value) {
+            self.errors.push(format!("{} is {}, outside [0, 1]", label, value));
+        }
+    }
+}
+
+fn check_player(findings: &mut Findings, player: &Player) {
+    let name = &player.name;
+    if name.trim().is_empty() {
+        findings.errors.push("a player has an empty name".to_string());
+    }
+    findings.probability(&format!("{}: serve_win_prob", name), player.serve_win_prob);
+    findings.probability(&format!("{}: ace_prob", name), player.ace_prob);
+    findings.probability(&format!("{}: double_fault_prob", name), player.double_fault_prob);
+    if player.ace_prob + player.double_fault_prob > 1.0 {
+        findings.errors.push(format!("{}: ace_prob + double_fault_prob is more than 1", name));
+    }
+    for (label, modifier) in [("tiebreak_serve_modifier", player.tiebreak_serve_modifier), ("deuce_court_modifier", player.deuce_court_modifier), ("ad_court_modifier", player.ad_court_modifier)] {
+        let adjusted = player.serve_win_prob + modifier;
+        if modifier != 0.0 && !(0.0..=1.0).contains(&adjusted) {
+            findings.warnings.push(format!("{}: serve_win_prob + {} is {:.3} and will be clamped to [0, 1]", name, label, adjusted));
+        }
+    }
+
+    if let Some(first_serve) = &player.first_serve {
+        findings.probability(&format!("{}: first_serve.in_prob", name), first_serve.in_prob);
+        if first_serve.in_prob <= 0.0 {
+            findings.errors.push(format!("{}: first_serve.in_prob is 0; no first serve would land", name));
+        }
+        if player.ace_prob > first_serve.in_prob || player.double_fault_prob > 1.0 - first_serve.in_prob {
+            findings.warnings.push(format!("{}: ace_prob or double_fault_prob can't be kept with first_serve.in_prob {}; the rate will be capped", name, first_serve.in_prob));
+        }
+    }
+
+    if let Some(placement) = &player.serve_placement {
+        for (direction, profile) in [("wide", &placement.wide), ("body", &placement.body), ("t", &placement.t)] {
+            if profile.share < 0.0 {
+                findings.errors.push(format!("{}: serve_placement.{}.share is negative", name, direction));
+            }
+            findings.probability(&format!("{}: serve_placement.{}.ace_prob", name, direction), profile.ace_prob);
+            findings.probability(&format!("{}: serve_placement.{}.serve_win_prob", name, direction), profile.serve_win_prob);
+        }
+        if placement.wide.share + placement.body.share + placement.t.share <= 0.0 {
+            findings.errors.push(format!("{}: serve_placement shares add up to zero", name));
+        }
+    }
+
+    if let Some(profile) = &player.return_profile {
+        findings.probability(&format!("{}: return_profile.error_prob", name), profile.error_prob);
+        findings.probability(&format!("{}: return_profile.deep_prob", name), profile.deep_prob);
+        if profile.aggressiveness <= -1.0 {
+            findings.errors.push(format!("{}: return_profile.aggressiveness must be above -1", name));
+        }
+    }
+}
+
+fn check_surface(findings: &mut Findings, surface: &Surface, config: &SimulationConfig) {
+    if surface.ace_factor < 0.0 {
+        findings.errors.push(format!("surface '{}': ace_factor is negative", surface.name));
+    }
+    for player in surface.player_modifiers.keys() {
+        if *player != config.player1.name && *player != config.player2.name {
+            findings.warnings.push(format!("surface '{}': player_modifiers names '{}', who is not playing", surface.name, player));
+        }
+    }
+}
+
+// Checks a config; with surfaces or scenarios, each of the configs actually simulated is checked instead
+fn check_config(config: &SimulationConfig) -> Findings {
+    let mut findings = Findings { errors: Vec::new(), warnings: Vec::new() };
+    let variants = match scenario::variants(config) {
+        Ok(variants) => variants,
+        Err(e) => {
+            findings.errors.push(e);
+            return findings;
+        }
+    };
+    if variants.is_empty() {
+        check_run(&mut findings, config);
+        return findings;
+    }
+
+    let mut dir_names: Vec<String> = Vec::new();
+    for (name, variant) in &variants {
+        if name.trim().is_empty() {
+            findings.errors.push("a surface or scenario has an empty name".to_string());
+        } else if dir_names.contains(&scenario::dir_name(name)) {
+            findings.errors.push(format!("'{}' would share its output directory with another surface or scenario", name));
+        }
+        dir_names.push(scenario::dir_name(name));
+
+        let mut variant_findings = Findings { errors: Vec::new(), warnings: Vec::new() };
+        check_run(&mut variant_findings, variant);
+        findings.errors.extend(variant_findings.errors.iter().map(|error| format!("'{}': {}", name, error)));
+        findings.warnings.extend(variant_findings.warnings.iter().map(|warning| format!("'{}': {}", name, warning)));
+    }
+    for surface in config.surfaces.iter().chain(config.scenarios.iter().filter_map(|scenario| scenario.surface.as_ref())) {
+        check_surface(&mut findings, surface, config);
+    }
+    findings
+}
+
+// The errors `check` would report for a config, for callers that change one on the fly
+pub fn errors(config: &SimulationConfig) -> Vec<String> {
+    check_config(config).errors
+}
+
+// Refuses a config with errors: they would fail part way through a run, or divide by a zero batch size before
+// it starts
+pub fn reject_errors(config: &SimulationConfig) -> Result<(), String> {
+    let errors = errors(config);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("invalid config:\n  {}", errors.join("\n  ")))
+    }
+}
+
+// The warnings `check` would report for a config, logged before a run
+pub fn warnings(config: &SimulationConfig) -> Vec<String> {
+    check_config(config).warnings
+}
+
+fn check_run(findings: &mut Findings, config: &SimulationConfig) {
+    check_player(findings, &config.player1);
+    check_player(findings, &config.player2);
+    if config.player1.name == config.player2.name {
+        findings.errors.push(format!("both players are named '{}'; stats and win counts are keyed by name", config.player1.name));
+    }
+
+    if config.best_of < 1 || config.best_of % 2 == 0 {
+        findings.errors.push(format!("best_of is {}; it must be a positive odd number of sets", config.best_of));
+    } else if config.best_of != 3 && config.best_of != 5 {
+        findings.warnings.push(format!("best_of is {}; professional matches are best of 3 or 5", config.best_of));
+    }
+    if let Some(rules) = &config.scoring {
+        findings.errors.extend(rules.errors());
+        if config.best_of != rules.best_of() {
+            findings.errors.push(format!("best_of is {} but scoring.sets_to_win {} makes it best of {}", config.best_of, rules.sets_to_win, rules.best_of()));
+        }
+    }
+    if !(1..=6).contains(&config.race_to_games) {
+        findings.errors.push(format!("race_to_games is {}; it must be between 1 and 6", config.race_to_games));
+    }
+
+    if config.num_simulations == 0 {
+        findings.errors.push("num_simulations is 0".to_string());
+    }
+    if config.batch_size == 0 {
+        findings.errors.push("batch_size is 0".to_string());
+    } else if !config.num_simulations.is_multiple_of(config.batch_size) {
+        findings.warnings.push(format!(
+            "num_simulations ({}) is not a multiple of batch_size ({}); only {} matches will be simulated",
+            config.num_simulations, config.batch_size, config.num_simulations / config.batch_size * config.batch_size,
+        ));
+    }
+    if config.max_workers == 0 {
+        findings.warnings.push("max_workers is 0; one worker per CPU will be used".to_string());
+    }
+    if config.log_interval == 0 {
+        findings.warnings.push("log_interval is 0; no points will be logged".to_string());
+    }
+
+    let momentum = &config.model.momentum;
+    if momentum.cap < 0.0 || momentum.ace_cap < 0.0 {
+        findings.errors.push("model.momentum caps must not be negative".to_string());
+    }
+    if let MomentumDecay::HalfLife(points) = momentum.decay {
+        if points <= 0.0 {
+            findings.warnings.push(format!("model.momentum.decay half_life is {}; streaks will never decay", points));
+        }
+    }
+    if !(0.0..1.0).contains(&config.model.lets.prob) {
+        findings.errors.push(format!("model.lets.prob is {}; it must be at least 0 and below 1", config.model.lets.prob));
+    }
+    let challenges = &config.model.challenges;
+    for (label, value) in [("wrong_call_prob", challenges.wrong_call_prob), ("challenge_wrong_prob", challenges.challenge_wrong_prob), ("challenge_right_prob", challenges.challenge_right_prob), ("replay_prob", challenges.replay_prob)] {
+        findings.probability(&format!("model.challenges.{}", label), value);
+    }
+    if challenges.per_set < 0 || challenges.tiebreak_extra < 0 {
+        findings.errors.push("model.challenges.per_set and tiebreak_extra must not be negative".to_string());
+    }
+    let conduct = &config.model.conduct;
+    for (label, value) in [("time_violation_prob", conduct.time_violation_prob), ("code_violation_prob", conduct.code_violation_prob), ("second_serve_fault_prob", conduct.second_serve_fault_prob)] {
+        findings.probability(&format!("model.conduct.{}", label), value);
+    }
+    findings.probability("model.weather.suspension_prob", config.model.weather.suspension_prob);
+    if config.model.weather.rest_points < 0 {
+        findings.errors.push(format!("model.weather.rest_points is {}; it must not be negative", config.model.weather.rest_points));
+    }
+    for (i, shock) in config.model.shocks.iter().enumerate() {
+        if shock.player != config.player1.name && shock.player != config.player2.name {
+            findings.errors.push(format!("model.shocks[{}].player '{}' is neither player", i, shock.player));
+        }
+        if shock.at_point == 0 {
+            findings.errors.push(format!("model.shocks[{}].at_point is 0; points are counted from 1", i));
+        }
+    }
+    if conduct.pressure_factor < 0.0 {
+        findings.errors.push(format!("model.conduct.pressure_factor is {}; it must not be negative", conduct.pressure_factor));
+    }
+    if config.prices.margin < 0.0 || config.prices.noise < 0.0 {
+        findings.errors.push("prices.margin and prices.noise must not be negative".to_string());
+    }
+
+}
+
+fn format_bytes(bytes: f64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, units[unit])
+}
+
+fn print_estimates(config: &SimulationConfig) {
+    let batch_size = config.batch_size.max(1);
+    let num_batches = config.num_simulations / batch_size;
+    let logged_batches = (0..num_batches).filter(|i| config.log_interval > 0 && ((i + 1) * batch_size).is_multiple_of(config.log_interval)).count();
+    let logged_matches = logged_batches * batch_size;
+    let points_per_match = (SETS_PER_BEST_OF * config.best_of.max(1) as f64 * POINTS_PER_SET).round();
+
+    let (format, bytes_per_point) = match config.log_format {
+        LogFormat::Csv => ("CSV", CSV_BYTES_PER_POINT),
+        LogFormat::Parquet => ("Parquet", PARQUET_BYTES_PER_POINT),
+    };
+    let (log, rows_per_match) = match config.log_granularity {
+        LogGranularity::Point => ("Point log", points_per_match),
+        LogGranularity::Game => ("Game log", (points_per_match / POINTS_PER_GAME).round()),
+    };
+    let log_bytes = logged_matches as f64 * rows_per_match * bytes_per_point;
+    let checkpoint_bytes = num_batches as f64 * batch_size as f64 * CHECKPOINT_BYTES_PER_MATCH;
+    // Every worker may hold the points of a logged batch at once, next to the checkpoint being written
+    let workers = config.max_workers.max(1).min(logged_batches);
+    let memory_bytes = workers as f64 * batch_size as f64 * rows_per_match * MEMORY_BYTES_PER_POINT + 2.0 * checkpoint_bytes;
+
+    println!("Estimates for {} matches (best of {}, about {} points per match):", num_batches * batch_size, config.best_of, points_per_match);
+    println!(" {}: {} of {} batches logged ({} matches), about {} of {}", log, logged_batches, num_batches, logged_matches, format_bytes(log_bytes), format);
+    // Parquet compresses match rows about as well as point rows
+    let match_log_bytes = if config.match_log { (num_batches * batch_size) as f64 * MATCH_LOG_BYTES_PER_MATCH * bytes_per_point / CSV_BYTES_PER_POINT } else { 0.0 };
+    if config.match_log {
+        println!(" Match log: {} matches, about {} of {}", num_batches * batch_size, format_bytes(match_log_bytes), format);
+    }
+    println!(" Checkpoint: about {}", format_bytes(checkpoint_bytes));
+    println!(" Disk per run: about {}", format_bytes(log_bytes + match_log_bytes + checkpoint_bytes));
+    println!(" Peak memory: about {}", format_bytes(memory_bytes));
+}
+
+// check [--config <config.json>] [--seed <n>]: validates the config and prints what a run with it would produce
+pub fn run(args: &[String]) -> Result<(), String> {
+    let config = unchecked_config_from_args(args)?;
+
+    let findings = check_config(&config);
+    for error in &findings.errors {
+        println!("error: {}", error);
+    }
+    for warning in &findings.warnings {
+        println!("warning: {}", warning);
+    }
+    if !findings.errors.is_empty() {
+        return Err(format!("config has {} error(s)", findings.errors.len()));
+    }
+    println!("Config OK ({} warning(s))\n", findings.warnings.len());
+
+    let variants = scenario::variants(&config)?;
+    if variants.is_empty() {
+        print_estimates(&config);
+    }
+    for (name, variant) in &variants {
+        // Variants run one after another, so disk adds up across them but peak memory doesn't
+        println!("'{}':", name);
+        print_estimates(variant);
+    }
+
+    let metadata = RunMetadata::new(&config);
+    println!("\nEffective configuration (config hash {}):", metadata.config_hash);
+    println!("{}", serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?);
+    Ok(())
+}
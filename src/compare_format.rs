@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::sink::ResultSink;
+use crate::{config_from_args, match_seed, simulate_match_parallel, BatchResult, Player, SimulationConfig, SimulationReport};
+
+// Records the winner of every match by match number, so the two formats can be compared match by match
+struct WinnerLog {
+    winners: Mutex<HashMap<u64, String>>,
+}
+
+impl ResultSink for WinnerLog {
+    fn on_batch(&self, _batch: &BatchResult) {}
+
+    fn on_complete(&self, _report: &SimulationReport) {}
+
+    fn on_match(&self, summary: &serde_json::Value, _points: &[HashMap<String, serde_json::Value>]) {
+        if let (Some(number), Some(winner)) = (summary["match"].as_u64(), summary["winner"].as_str()) {
+            self.winners.lock().unwrap().insert(number, winner.to_string());
+        }
+    }
+}
+
+// The run of `config` as best of `best_of` from `seed`, with nothing logged
+fn simulate_format(config: &SimulationConfig, best_of: i32, seed: u64) -> (SimulationReport, HashMap<u64, String>) {
+    let format_config = SimulationConfig {
+        best_of,
+        seed,
+        log_interval: usize::MAX,
+        match_log: false,
+        ..config.clone()
+    };
+    let winners = WinnerLog { winners: Mutex::new(HashMap::new()) };
+    let report = simulate_match_parallel(&format_config, &[&winners], None);
+    (report, winners.winners.into_inner().unwrap())
+}
+
+// compare-format [--config <config.json>] [--seed <n>] [--crn]: simulates the matchup as best of 3 and best of 5
+// and reports how much the longer format helps the stronger player. With --crn both formats replay the same
+// random numbers match by match, which makes the difference far more precise for the same number of matches.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let config = config_from_args(args)?;
+    // Custom scoring fixes the number of sets itself, so the formats could not differ
+    if config.scoring.is_some() {
+        return Err("compare-format plays standard best of 3 and best of 5 sets; remove `scoring` from the config to compare them".to_string());
+    }
+    let common_random_numbers = args.iter().any(|arg| arg == "--crn");
+    let seed_best_of_5 = if common_random_numbers { config.seed } else { match_seed(config.seed, 0) };
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(config.max_workers).build().map_err(|e| format!("could not start {} threads: {}", config.max_workers, e))?;
+    let (report3, winners3) = pool.install(|| simulate_format(&config, 3, config.seed));
+    let (report5, winners5) = pool.install(|| simulate_format(&config, 5, seed_best_of_5));
+
+    let players: [&Player; 2] = [&config.player1, &config.player2];
+    let num_matches = config.num_simulations / config.batch_size * config.batch_size;
+    let matches = num_matches.max(1) as f64;
+    println!(
+        "Format comparison: {} vs {}, {} matches per format (seed {}, {})",
+        players[0].name, players[1].name, num_matches, config.seed,
+        if common_random_numbers { "common random numbers" } else { "independent random numbers" },
+    );
+    println!("{:<10} {:>16} {:>16} {:>12}", "format", players[0].name, players[1].name, "shots/match");
+    for (label, report) in [("best of 3", &report3), ("best of 5", &report5)] {
+        println!(
+            "{:<10} {:>15.2}% {:>15.2}% {:>12.1}",
+            label,
+            report.win_probability(&players[0].name) * 100.0,
+            report.win_probability(&players[1].name) * 100.0,
+            report.total_shots as f64 / matches,
+        );
+    }
+
+    // The stronger player is the best-of-3 favourite
+    let stronger = if report3.win_probability(&players[0].name) >= report3.win_probability(&players[1].name) { &players[0].name } else { &players[1].name };
+    let p3 = report3.win_probability(stronger);
+    let p5 = report5.win_probability(stronger);
+    let difference = p5 - p3;
+    let standard_error = if common_random_numbers {
+        // Paired estimate from the matches the stronger player wins in one format but not the other
+        let mut gained = 0.0;
+        let mut lost = 0.0;
+        for (number, winner3) in &winners3 {
+            match (winner3 == stronger, winners5.get(number).is_some_and(|winner5| winner5 == stronger)) {
+                (false, true) => gained += 1.0,
+                (true, false) => lost += 1.0,
+                _ => {}
+            }
+        }
+        let same_winner = 1.0 - (gained + lost) / matches;
+        println!("\nSame winner in both formats in {:.2}% of paired matches", same_winner * 100.0);
+        (((gained + lost) / matches - difference.powi(2)) / matches).max(0.0).sqrt()
+    } else {
+        println!();
+        ((p3 * (1.0 - p3) + p5 * (1.0 - p5)) / matches).sqrt()
+    };
+
+    println!(
+        "Best of 5 changes {}'s win probability by {:+.2} points ({:.2}% to {:.2}%, {:+.1}% relative), standard error {:.2} points",
+        stronger, difference * 100.0, p3 * 100.0, p5 * 100.0, difference / p3.max(f64::EPSILON) * 100.0, standard_error * 100.0,
+    );
+    if difference.abs() > 2.0 * standard_error {
+        println!("The longer format {} the stronger player", if difference > 0.0 { "favours" } else { "hurts" });
+    } else {
+        println!("The difference is within two standard errors; run more matches to resolve it");
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::io::IsTerminal;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// How much the run prints: set by --quiet, -v and -vv
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    // Only the final match win probabilities, and errors
+    Quiet,
+    // The report tables, warnings and errors
+    Normal,
+    // Also progress roughly every tenth of the run and informational messages
+    Verbose,
+    // Also every batch as it completes
+    Debug,
+}
+
+impl Verbosity {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let quiet = args.iter().any(|arg| arg == "--quiet" || arg == "-q");
+        let verbosity = if args.iter().any(|arg| arg == "-vv") {
+            Verbosity::Debug
+        } else if args.iter().any(|arg| arg == "-v" || arg == "--verbose") {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        };
+        match (quiet, verbosity) {
+            (true, Verbosity::Normal) => Ok(Verbosity::Quiet),
+            (true, _) => Err("--quiet can't be combined with -v or -vv".to_string()),
+            (false, verbosity) => Ok(verbosity),
+        }
+    }
+
+    fn level(&self) -> LevelFilter {
+        match self {
+            Verbosity::Quiet => LevelFilter::Error,
+            Verbosity::Normal => LevelFilter::Warn,
+            Verbosity::Verbose => LevelFilter::Info,
+            Verbosity::Debug => LevelFilter::Debug,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Dim,
+}
+
+// `text` in `color` when `enabled`, as ANSI escape codes
+pub fn paint(text: &str, color: Color, enabled: bool) -> String {
+    if !enabled {
+        return text.to_string();
+    }
+    let code = match color {
+        Color::Red => "31",
+        Color::Green => "32",
+        Color::Yellow => "33",
+        Color::Dim => "2",
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+// Whether output to a terminal should be colored: not when it is piped, or when NO_COLOR is set
+pub fn colors(terminal: bool) -> bool {
+    terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+// Writes log records to stderr, their level colored when stderr is a terminal
+struct Console {
+    color: bool,
+}
+
+impl Log for Console {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let prefix = match record.level() {
+            Level::Error => paint("Error:", Color::Red, self.color),
+            Level::Warn => paint("Warning:", Color::Yellow, self.color),
+            Level::Info => return eprintln!("{}", record.args()),
+            Level::Debug | Level::Trace => paint("Debug:", Color::Dim, self.color),
+        };
+        eprintln!("{} {}", prefix, record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+// Routes the `log` macros to stderr at the level of `verbosity`; called once, before anything is logged
+pub fn init(verbosity: Verbosity) {
+    let console = Box::leak(Box::new(Console { color: colors(std::io::stderr().is_terminal()) }));
+    if log::set_logger(console).is_ok() {
+        log::set_max_level(verbosity.level());
+    }
+}
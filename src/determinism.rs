@@ -0,0 +1,74 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::sink::ResultSink;
+use crate::{config_from_args, flag_value, fnv1a, simulate_match_parallel, BatchResult, SimulationReport};
+
+// Matches played by each run of --verify-determinism unless --matches is given
+const DEFAULT_MATCHES: usize = 200;
+
+// Hashes each match's summary and point log as it finishes, keyed by match number, so the digest of a run does
+// not depend on the order its batches complete in
+#[derive(Default)]
+struct HashSink {
+    matches: Mutex<BTreeMap<u64, u64>>,
+}
+
+impl HashSink {
+    // One hash over the match hashes in match order and the run's totals
+    fn digest(&self, report: &SimulationReport) -> u64 {
+        let matches = self.matches.lock().unwrap();
+        let stats: BTreeMap<_, BTreeMap<_, _>> = report.stats.iter().map(|(player, stats)| (player, stats.iter().collect())).collect();
+        let totals = serde_json::json!({ "match_wins": report.match_wins, "stats": stats, "shots": report.total_shots });
+        let text = format!("{:?}{}", matches.iter().collect::<Vec<_>>(), totals);
+        fnv1a(text.bytes())
+    }
+}
+
+impl ResultSink for HashSink {
+    fn on_batch(&self, _batch: &BatchResult) {}
+
+    fn on_complete(&self, _report: &SimulationReport) {}
+
+    fn on_match(&self, summary: &serde_json::Value, points: &[HashMap<String, serde_json::Value>]) {
+        // serde_json objects are sorted by key; the point log's maps are sorted here
+        let points: Vec<BTreeMap<&String, &serde_json::Value>> = points.iter().map(|point| point.iter().collect()).collect();
+        let text = format!("{}{}", summary, serde_json::to_string(&points).unwrap());
+        self.matches.lock().unwrap().insert(summary["match"].as_u64().unwrap_or(0), fnv1a(text.bytes()));
+    }
+}
+
+// The digest of a run of `config` on a pool of `threads` threads
+fn run_digest(config: &crate::SimulationConfig, threads: usize) -> Result<u64, String> {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().map_err(|e| format!("could not start {} threads: {}", threads, e))?;
+    let sink = HashSink::default();
+    let report = pool.install(|| simulate_match_parallel(config, &[&sink], None));
+    Ok(sink.digest(&report))
+}
+
+// --verify-determinism [--config <config.json>] [--matches <n>] [--seed <n>]
+// Plays the same small seeded workload twice, on one thread and on `max_workers` threads, and compares hashes
+// of every match's summary and point log and of the totals. The hash can also be compared between machines.
+pub fn verify(args: &[String]) -> Result<(), String> {
+    let mut config = config_from_args(args)?;
+    config.num_simulations = match flag_value(args, "--matches") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --matches: {}", e))?,
+        None => DEFAULT_MATCHES,
+    };
+    config.num_simulations = (config.num_simulations - config.num_simulations % config.batch_size).max(config.batch_size);
+    config.log_interval = usize::MAX;
+    config.match_log = false;
+    config.run_dir = Default::default();
+
+    let threads = [1, config.max_workers.max(2)];
+    println!("Verifying determinism: {} matches (seed {}), on {} and {} threads", config.num_simulations, config.seed, threads[0], threads[1]);
+    let digests = [run_digest(&config, threads[0])?, run_digest(&config, threads[1])?];
+    for (threads, digest) in threads.iter().zip(digests) {
+        println!("  {} {}: {:016x}", threads, if *threads == 1 { "thread" } else { "threads" }, digest);
+    }
+    if digests[0] != digests[1] {
+        return Err("the two runs differ; the same seed did not reproduce the same matches".to_string());
+    }
+    println!("Deterministic: both runs produced identical output");
+    Ok(())
+}
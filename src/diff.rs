@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::run_dir::REPORT_JSON_FILE;
+use crate::significance;
+
+// |z| at or above this is reported as a meaningful difference (5% two-sided)
+const Z_THRESHOLD: f64 = 1.96;
+
+// The parts of a run's report.json the diff uses
+#[derive(Deserialize)]
+pub struct ReportFile {
+    metadata: serde_json::Value,
+    pub players: [String; 2],
+    best_of: i32,
+    pub matches: i32,
+    pub match_wins: HashMap<String, i32>,
+    total_shots: i32,
+    stats: HashMap<String, HashMap<String, i32>>,
+}
+
+impl ReportFile {
+    // Reads a report.json, or the report.json of a run directory
+    pub fn load(path: &str) -> Result<Self, String> {
+        let path = Path::new(path);
+        let path = if path.is_dir() { path.join(REPORT_JSON_FILE) } else { path.to_path_buf() };
+        let contents = std::fs::read_to_string(&path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents).map_err(|e| format!("invalid report {}: {}", path.display(), e))
+    }
+
+    fn stat(&self, player: usize, key: &str) -> i32 {
+        self.stats.get(&self.players[player]).and_then(|stats| stats.get(key)).copied().unwrap_or(0)
+    }
+}
+
+// How a row's value is estimated, which decides how its sampling error is worked out
+enum Measure {
+    // successes out of trials, e.g. matches won out of matches played
+    Proportion(i32, i32),
+    // A count per match, with the count treated as Poisson
+    PerMatch(i32, i32),
+    // Shown without a test: no variance can be derived from the totals
+    Untested(f64),
+}
+
+impl Measure {
+    fn value(&self) -> f64 {
+        match *self {
+            Measure::Proportion(successes, trials) => successes as f64 / trials.max(1) as f64,
+            Measure::PerMatch(total, matches) => total as f64 / matches.max(1) as f64,
+            Measure::Untested(value) => value,
+        }
+    }
+}
+
+// z statistic of the change from `a` to `b`, for two independent runs
+fn z_score(a: &Measure, b: &Measure) -> Option<f64> {
+    let standard_error = match (a, b) {
+        (Measure::Proportion(x1, n1), Measure::Proportion(x2, n2)) if *n1 > 0 && *n2 > 0 => {
+            return Some(significance::two_proportion_z_test(*x1 as u64, *n1 as u64, *x2 as u64, *n2 as u64).z);
+        }
+        (Measure::PerMatch(_, n1), Measure::PerMatch(_, n2)) if *n1 > 0 && *n2 > 0 => (a.value() / *n1 as f64 + b.value() / *n2 as f64).sqrt(),
+        _ => return None,
+    };
+    Some(if standard_error > 0.0 { (b.value() - a.value()) / standard_error } else { 0.0 })
+}
+
+fn rows(report: &ReportFile) -> Vec<(String, Measure)> {
+    let matches = report.matches;
+    let mut rows = vec![(
+        format!("{} win probability", report.players[0]),
+        Measure::Proportion(report.match_wins.get(&report.players[0]).copied().unwrap_or(0), matches),
+    )];
+    for player in 0..2 {
+        let name = &report.players[player];
+        rows.push((format!("{} aces/match", name), Measure::PerMatch(report.stat(player, "aces"), matches)));
+        rows.push((format!("{} double faults/match", name), Measure::PerMatch(report.stat(player, "double_faults"), matches)));
+        for (label, won, served) in [("deuce", "deuce_points_won", "deuce_points_served"), ("ad", "ad_points_won", "ad_points_served")] {
+            rows.push((format!("{} {} serve points won", name, label), Measure::Proportion(report.stat(player, won), report.stat(player, served))));
+        }
+        rows.push((format!("{} tiebreaks won", name), Measure::Proportion(report.stat(player, "tiebreaks_won"), report.stat(player, "tiebreaks_played"))));
+        for key in ["return_errors", "short_returns", "deep_returns"] {
+            rows.push((format!("{} {}/match", name, key.replace('_', " ")), Measure::PerMatch(report.stat(player, key), matches)));
+        }
+    }
+    rows.push(("shots/match".to_string(), Measure::Untested(report.total_shots as f64 / matches.max(1) as f64)));
+    rows
+}
+
+fn describe(report: &ReportFile) -> String {
+    format!(
+        "{} vs {}, best of {}, {} matches, seed {}, config hash {}",
+        report.players[0], report.players[1], report.best_of, report.matches, report.metadata["seed"], report.metadata["config_hash"].as_str().unwrap_or("?"),
+    )
+}
+
+// diff <report a> <report b>: compares two runs' report.json files (or run directories) row by row and marks
+// the differences too large to be sampling noise
+pub fn run(args: &[String]) -> Result<(), String> {
+    let [path_a, path_b] = [args.first(), args.get(1)].map(|arg| arg.filter(|arg| !arg.starts_with("--")));
+    let (Some(path_a), Some(path_b)) = (path_a, path_b) else {
+        return Err("usage: diff <report.json or run dir> <report.json or run dir>".to_string());
+    };
+    let a = ReportFile::load(path_a)?;
+    let b = ReportFile::load(path_b)?;
+
+    println!("A: {}", describe(&a));
+    println!("B: {}", describe(&b));
+    if a.players != b.players {
+        println!("Players differ; rows compare player1 with player1 and player2 with player2 and are labelled with A's names");
+    }
+    if a.metadata["seed"] == b.metadata["seed"] {
+        println!("Both runs used the same seed; the test assumes independent runs, so its z values are conservative for these");
+    }
+
+    let width = rows(&a).iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+    println!("\n{:<width$} {:>10} {:>10} {:>10} {:>7}", "", "A", "B", "change", "z");
+    let mut meaningful = 0;
+    for ((label, measure_a), (_, measure_b)) in rows(&a).into_iter().zip(rows(&b)) {
+        let is_proportion = matches!(measure_a, Measure::Proportion(..));
+        // Return stats are only counted when a return profile is set
+        if !is_proportion && measure_a.value() == 0.0 && measure_b.value() == 0.0 {
+            continue;
+        }
+        let format_value = |value: f64| if is_proportion { format!("{:.2}%", value * 100.0) } else { format!("{:.2}", value) };
+        let change = measure_b.value() - measure_a.value();
+        let change = if is_proportion { format!("{:+.2}pt", change * 100.0) } else { format!("{:+.2}", change) };
+        let (z, flag) = match z_score(&measure_a, &measure_b) {
+            Some(z) if z.abs() >= Z_THRESHOLD => {
+                meaningful += 1;
+                (format!("{:.2}", z), " *")
+            }
+            Some(z) => (format!("{:.2}", z), ""),
+            None => ("-".to_string(), ""),
+        };
+        println!("{:<width$} {:>10} {:>10} {:>10} {:>7}{}", label, format_value(measure_a.value()), format_value(measure_b.value()), change, z, flag);
+    }
+
+    println!("\n* |z| >= {}: unlikely to be sampling noise at the 5% level ({} rows)", Z_THRESHOLD, meaningful);
+    println!("Per-match counts are tested as Poisson rates; shots per match are not tested");
+    Ok(())
+}
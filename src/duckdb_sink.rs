@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use duckdb::types::Value;
+use duckdb::{appender_params_from_iter, Connection};
+
+use crate::sink::ResultSink;
+use crate::{point_log_columns, BatchResult, RunMetadata, SimulationConfig, SimulationReport, SET_STAT_KEYS};
+
+// Writes a run into three typed tables of a DuckDB database, tagged with the run directory's id so several
+// runs can share one file: `runs` (one row per run), `matches` (one row per match) and `points` (the point
+// log). Player-specific columns are named player1_*/player2_* so the schema is the same for every matchup.
+pub struct DuckDbSink {
+    connection: Mutex<Connection>,
+    run_id: String,
+    // A resumed run simulates again the matches of batches after its last checkpoint, which may already
+    // have rows; they are replaced rather than added twice
+    replace_matches: bool,
+    player1: String,
+    player2: String,
+    // Point log column -> (table column, SQL type)
+    point_columns: Vec<(String, String, &'static str)>,
+}
+
+impl DuckDbSink {
+    // Opens the database for run `run_id`, continuing its rows when the run is `resuming`
+    pub fn open(path: &str, config: &SimulationConfig, run_id: &str, resuming: bool) -> Result<Self, String> {
+        let connection = Connection::open(path).map_err(|e| format!("could not open {}: {}", path, e))?;
+
+        let point_columns: Vec<(String, String, &'static str)> = point_log_columns(&config.player1.name, &config.player2.name)
+            .into_iter()
+            .zip(point_log_columns("player1", "player2"))
+            .map(|(column, table_column)| {
+                let sql_type = match table_column.as_str() {
+                    "server" | "receiver" | "point_score" | "game_score" | "set_score" | "court" | "serve_direction" | "return_outcome" | "conduct" => "VARCHAR",
+                    "point_number" => "BIGINT",
+                    _ => "DOUBLE",
+                };
+                (column, table_column, sql_type)
+            })
+            .collect();
+
+        let point_schema: Vec<String> = point_columns.iter().map(|(_, name, sql_type)| format!("{} {}", name, sql_type)).collect();
+        let stat_schema: Vec<String> = ["player1", "player2"].iter()
+            .flat_map(|player| SET_STAT_KEYS.iter().map(move |key| format!("{}_{} INTEGER", player, key)))
+            .collect();
+        connection.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS runs (run_id VARCHAR, schema_version INTEGER, crate_version VARCHAR, config_hash VARCHAR, seed UBIGINT, \
+                 player1 VARCHAR, player2 VARCHAR, best_of INTEGER, grand_slam BOOLEAN, \
+                 matches BIGINT, player1_wins BIGINT, player2_wins BIGINT, total_shots BIGINT, execution_time_ms BIGINT);
+             CREATE TABLE IF NOT EXISTS matches (run_id VARCHAR, match_number BIGINT, seed UBIGINT, winner VARCHAR, player1_sets INTEGER, player2_sets INTEGER, \
+                 points INTEGER, shots INTEGER, cumulative_leverage DOUBLE, lead_changes INTEGER, winner_min_win_prob DOUBLE, {});
+             CREATE TABLE IF NOT EXISTS points (run_id VARCHAR, match_number BIGINT, {}, shots VARCHAR);",
+            stat_schema.join(", "),
+            point_schema.join(", "),
+        )).map_err(|e| format!("could not create tables in {}: {}", path, e))?;
+
+        let recorded: i64 = connection
+            .query_row("SELECT count(*) FROM runs WHERE run_id = ?", [run_id], |row| row.get(0))
+            .map_err(|e| format!("could not read the runs in {}: {}", path, e))?;
+        if recorded > 0 {
+            if !resuming {
+                return Err(format!("{} already has a run {}", path, run_id));
+            }
+            // The run was interrupted while writing here, and keeps its row
+            return Ok(DuckDbSink::new(connection, run_id, config, point_columns, true));
+        }
+        let metadata = RunMetadata::new(config);
+        connection.execute(
+            "INSERT INTO runs (run_id, schema_version, crate_version, config_hash, seed, player1, player2, best_of, grand_slam) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            duckdb::params![
+                run_id,
+                metadata.schema_version,
+                metadata.crate_version,
+                metadata.config_hash,
+                metadata.seed,
+                config.player1.name,
+                config.player2.name,
+                config.best_of,
+                config.grand_slam,
+            ],
+        ).map_err(|e| format!("could not record the run in {}: {}", path, e))?;
+        Ok(DuckDbSink::new(connection, run_id, config, point_columns, resuming))
+    }
+
+    fn new(connection: Connection, run_id: &str, config: &SimulationConfig, point_columns: Vec<(String, String, &'static str)>, replace_matches: bool) -> Self {
+        DuckDbSink {
+            connection: Mutex::new(connection),
+            run_id: run_id.to_string(),
+            replace_matches,
+            player1: config.player1.name.clone(),
+            player2: config.player2.name.clone(),
+            point_columns,
+        }
+    }
+
+    fn append_match(&self, summary: &serde_json::Value, points: &[HashMap<String, serde_json::Value>]) -> duckdb::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        let match_number = summary["match"].as_i64().unwrap_or(0);
+        if self.replace_matches {
+            for table in ["matches", "points"] {
+                connection.execute(&format!("DELETE FROM {} WHERE run_id = ? AND match_number = ?", table), duckdb::params![self.run_id, match_number])?;
+            }
+        }
+
+        let mut match_row = vec![
+            Value::Text(self.run_id.clone()),
+            Value::BigInt(match_number),
+            Value::UBigInt(summary["seed"].as_u64().unwrap_or(0)),
+            Value::Text(summary["winner"].as_str().unwrap_or("").to_string()),
+            Value::Int(summary["sets"][0].as_i64().unwrap_or(0) as i32),
+            Value::Int(summary["sets"][1].as_i64().unwrap_or(0) as i32),
+            Value::Int(summary["points"].as_i64().unwrap_or(0) as i32),
+            Value::Int(summary["shots"].as_i64().unwrap_or(0) as i32),
+            Value::Double(summary["cumulative_leverage"].as_f64().unwrap_or(0.0)),
+            Value::Int(summary["lead_changes"].as_i64().unwrap_or(0) as i32),
+            Value::Double(summary["winner_min_win_prob"].as_f64().unwrap_or(0.0)),
+        ];
+        for player in [&self.player1, &self.player2] {
+            for key in SET_STAT_KEYS {
+                match_row.push(Value::Int(summary["stats"][player.as_str()][key].as_i64().unwrap_or(0) as i32));
+            }
+        }
+        connection.appender("matches")?.append_row(appender_params_from_iter(match_row))?;
+
+        let mut appender = connection.appender("points")?;
+        for point in points {
+            let mut row = vec![Value::Text(self.run_id.clone()), Value::BigInt(match_number)];
+            for (column, _, sql_type) in &self.point_columns {
+                let value = &point[column];
+                row.push(match (*sql_type, value) {
+                    (_, serde_json::Value::Null) => Value::Null,
+                    ("VARCHAR", _) => Value::Text(value.as_str().unwrap_or("").to_string()),
+                    ("BIGINT", _) => Value::BigInt(value.as_i64().unwrap_or(0)),
+                    _ => Value::Double(value.as_f64().unwrap_or(0.0)),
+                });
+            }
+            row.push(Value::Text(point["shots"].to_string()));
+            appender.append_row(appender_params_from_iter(row))?;
+        }
+        Ok(())
+    }
+}
+
+impl ResultSink for DuckDbSink {
+    fn on_batch(&self, _batch: &BatchResult) {}
+
+    fn on_complete(&self, report: &SimulationReport) {
+        let wins = |player: &String| report.match_wins.get(player).copied().unwrap_or(0);
+        let result = self.connection.lock().unwrap().execute(
+            "UPDATE runs SET matches = ?, player1_wins = ?, player2_wins = ?, total_shots = ?, execution_time_ms = ? WHERE run_id = ?",
+            duckdb::params![
+                wins(&self.player1) + wins(&self.player2),
+                wins(&self.player1),
+                wins(&self.player2),
+                report.total_shots,
+                report.execution_time as i64,
+                self.run_id,
+            ],
+        );
+        if let Err(e) = result {
+            log::error!("duckdb: could not record the run totals: {}", e);
+        }
+    }
+
+    fn on_match(&self, summary: &serde_json::Value, points: &[HashMap<String, serde_json::Value>]) {
+        if let Err(e) = self.append_match(summary, points) {
+            log::error!("duckdb: could not write match {}: {}", summary["match"], e);
+        }
+    }
+}
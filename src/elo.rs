@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+// Elo rating updates, for players given an `elo` rating in the config
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EloConfig {
+    // Rating points at stake in a match: the winner gains K times the chance the ratings gave the loser
+    pub k_factor: f64,
+}
+
+impl Default for EloConfig {
+    fn default() -> Self {
+        EloConfig { k_factor: 32.0 }
+    }
+}
+
+// The chance of beating `opponent` that the ratings alone imply
+pub fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + libm::pow(10.0, (opponent - rating) / 400.0))
+}
+
+// The rating after a match scored 1 for a win and 0 for a loss
+pub fn update(rating: f64, opponent: f64, score: f64, k_factor: f64) -> f64 {
+    rating + k_factor * (score - expected_score(rating, opponent))
+}
+
+// The rating changes on winning and on losing, and their average weighted by the simulated win probability
+pub fn expected_change(rating: f64, opponent: f64, win_prob: f64, k_factor: f64) -> (f64, f64, f64) {
+    let on_win = update(rating, opponent, 1.0, k_factor) - rating;
+    let on_loss = update(rating, opponent, 0.0, k_factor) - rating;
+    (on_win, on_loss, win_prob * on_win + (1.0 - win_prob) * on_loss)
+}
@@ -0,0 +1,278 @@
+use crate::flag_value;
+use crate::scoring::ScoringRules;
+use crate::what_if::{parse_points, MatchState};
+
+// Exact probabilities that follow from the scoring rules alone, for a fixed chance of the server winning each
+// point, as opposed to the simulated ones
+
+// The server's chance of holding from `points` (server first, counted 0, 1, 2, 3 for 0, 15, 30, 40 and on past
+// 40 at deuce), winning each point with probability `p`. With `no_ad` the point at 40-40 decides the game.
+pub fn hold_probability(p: f64, points: [i32; 2], no_ad: bool) -> f64 {
+    let [server, returner] = points;
+    let q = 1.0 - p;
+    if no_ad {
+        // A finished tiebreak can leave both sides on 4 points or more; the one ahead has won
+        if server >= 4 && server > returner {
+            return 1.0;
+        }
+        if returner >= 4 && returner > server {
+            return 0.0;
+        }
+    } else {
+        if server >= 4 && server - returner >= 2 {
+            return 1.0;
+        }
+        if returner >= 4 && returner - server >= 2 {
+            return 0.0;
+        }
+        if server >= 3 && returner >= 3 {
+            // From deuce the server needs two points in a row before the returner does
+            let deuce = p * p / (p * p + q * q);
+            return match server - returner {
+                0 => deuce,
+                1 => p + q * deuce,
+                _ => p * deuce,
+            };
+        }
+    }
+    p * hold_probability(p, [server + 1, returner], no_ad) + q * hold_probability(p, [server, returner + 1], no_ad)
+}
+
+// A player's service games: the chance of winning each serve point, and whether games are played no-ad
+#[derive(Clone, Copy)]
+pub struct HoldParams {
+    pub serve_point_prob: f64,
+    pub no_ad: bool,
+}
+
+impl HoldParams {
+    fn hold(&self) -> f64 {
+        hold_probability(self.serve_point_prob, [0, 0], self.no_ad)
+    }
+}
+
+// How a set at 6-6 is decided
+#[derive(Clone, Copy)]
+pub enum TiebreakRule {
+    // A tiebreak to this many points, won by two
+    To(i32),
+    // No tiebreak: games go on until a player leads by two
+    Advantage,
+}
+
+// Player1's chance of winning a tiebreak to `target` from `points` (player order), with `first_server` (0 for
+// player1) having served its first point. Servers change after the first point and then every two points.
+pub fn tiebreak_win_probability(player1: f64, player2: f64, first_server: usize, points: [i32; 2], target: i32) -> f64 {
+    // Player1's chance of winning the point after `played` points
+    let point = |played: i32| {
+        let server = if ((played + 1) / 2) % 2 == 0 { first_server } else { 1 - first_server };
+        if server == 0 { player1 } else { 1.0 - player2 }
+    };
+    // From a tie at target - 1 or more, each player serves one of the next two points
+    let both = player1 * (1.0 - player2);
+    let tie = both / (both + (1.0 - player1) * player2);
+
+    let [a, b] = points;
+    if a.min(b) >= target - 1 {
+        return match a - b {
+            0 => tie,
+            1 => point(a + b) + (1.0 - point(a + b)) * tie,
+            -1 => point(a + b) * tie,
+            lead => if lead > 0 { 1.0 } else { 0.0 },
+        };
+    }
+    if a.max(b) >= target {
+        return if a > b { 1.0 } else { 0.0 };
+    }
+    // Filled backwards from the end of the tiebreak; a tie at target - 1 all stands for everything after it
+    let size = (target + 1) as usize;
+    let mut table = vec![vec![0.0; size]; size];
+    for x in (0..=target).rev() {
+        for y in (0..=target).rev() {
+            table[x as usize][y as usize] = if x.min(y) >= target - 1 {
+                tie
+            } else if x >= target {
+                1.0
+            } else if y >= target {
+                0.0
+            } else {
+                point(x + y) * table[x as usize + 1][y as usize] + (1.0 - point(x + y)) * table[x as usize][y as usize + 1]
+            };
+        }
+    }
+    table[a as usize][b as usize]
+}
+
+// Player1's chance of winning the set from `games` (player order) with `server` (0 for player1) serving the
+// next game, each player holding according to their own parameters
+pub fn set_win_probability(games: [i32; 2], server: usize, player1: HoldParams, player2: HoldParams, tiebreak: TiebreakRule) -> f64 {
+    let won = |games: i32, other: i32| (games >= 6 && games - other >= 2) || (matches!(tiebreak, TiebreakRule::To(_)) && games == 7 && other == 6);
+    let [a, b] = games;
+    if won(a, b) {
+        return 1.0;
+    }
+    if won(b, a) {
+        return 0.0;
+    }
+    match tiebreak {
+        TiebreakRule::To(target) if a == 6 && b == 6 => tiebreak_win_probability(player1.serve_point_prob, player2.serve_point_prob, server, [0, 0], target),
+        TiebreakRule::Advantage if a == b && a >= 5 => {
+            // Each player serves one of the next two games
+            let (hold1, hold2) = (player1.hold(), player2.hold());
+            let both = hold1 * (1.0 - hold2);
+            both / (both + (1.0 - hold1) * hold2)
+        }
+        _ => {
+            let game = if server == 0 { player1.hold() } else { 1.0 - player2.hold() };
+            game * set_win_probability([a + 1, b], 1 - server, player1, player2, tiebreak) + (1.0 - game) * set_win_probability([a, b + 1], 1 - server, player1, player2, tiebreak)
+        }
+    }
+}
+
+fn probability(args: &[String], flag: &str) -> Result<f64, String> {
+    let value = flag_value(args, flag).ok_or_else(|| format!("missing {}", flag))?;
+    let p: f64 = value.parse().map_err(|e| format!("invalid {}: {}", flag, e))?;
+    if !(0.0..=1.0).contains(&p) {
+        return Err(format!("{} must be between 0 and 1", flag));
+    }
+    Ok(p)
+}
+
+// exact game --p <point win prob> [--points <server first, e.g. 30-40>] [--no-ad]
+// exact set --p1 <point win prob> --p2 <point win prob> [--games 4-3] [--server p1|p2] [--tiebreak 7|10|none] [--no-ad]
+// Prints the exact probability of the server holding from a game score, or of player1 winning a set from a game score
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("game") => {
+            let p = probability(args, "--p")?;
+            let no_ad = args.iter().any(|arg| arg == "--no-ad");
+            let score = flag_value(args, "--points").unwrap_or("0-0");
+            let points = parse_points(score, false)?;
+            if no_ad && points.iter().any(|points| *points > 3) {
+                return Err(format!("{} can't occur without advantage scoring", score));
+            }
+            let scoring = if no_ad { "no-ad" } else { "advantage scoring" };
+            println!("Hold probability from {} (server first, {}) winning {:.4} of serve points: {:.6}", score, scoring, p, hold_probability(p, points, no_ad));
+            Ok(())
+        }
+        Some("set") => {
+            let no_ad = args.iter().any(|arg| arg == "--no-ad");
+            let player1 = HoldParams { serve_point_prob: probability(args, "--p1")?, no_ad };
+            let player2 = HoldParams { serve_point_prob: probability(args, "--p2")?, no_ad };
+            let server = match flag_value(args, "--server") {
+                Some("p1") | None => 0,
+                Some("p2") => 1,
+                Some(other) => return Err(format!("invalid --server '{}'; use p1 or p2", other)),
+            };
+            let tiebreak = match flag_value(args, "--tiebreak") {
+                Some("7") | None => TiebreakRule::To(7),
+                Some("10") => TiebreakRule::To(10),
+                Some("none") => TiebreakRule::Advantage,
+                Some(other) => return Err(format!("invalid --tiebreak '{}'; use 7, 10 or none", other)),
+            };
+            let score = flag_value(args, "--games").unwrap_or("0-0");
+            let games = MatchState::parse(score, None, server, &ScoringRules::default())?.games;
+            let [a, b] = games;
+            let over = (a.max(b) >= 6 && (a - b).abs() >= 2) || (matches!(tiebreak, TiebreakRule::To(_)) && a.max(b) > 6);
+            if score.split_whitespace().count() > 1 || over {
+                return Err(format!("{} is not a set in progress", score));
+            }
+            let prob = set_win_probability(games, server, player1, player2, tiebreak);
+            println!(
+                "Player1 holds {:.4} and player2 {:.4} of service games; from {} with {} serving, player1 wins the set with probability {:.6}",
+                player1.hold(), player2.hold(), score, if server == 0 { "player1" } else { "player2" }, prob,
+            );
+            Ok(())
+        }
+        _ => Err("usage: exact game --p <point win prob> [--points 30-40] [--no-ad], or exact set --p1 <point win prob> --p2 <point win prob> [--games 4-3] [--server p1|p2] [--tiebreak 7|10|none] [--no-ad]".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{match_seed, PressureCurve, Shock, SimulationConfig, TennisMatch};
+
+    const EPSILON: f64 = 1e-12;
+
+    #[test]
+    fn an_even_point_is_an_even_game() {
+        for no_ad in [false, true] {
+            assert!((hold_probability(0.5, [0, 0], no_ad) - 0.5).abs() < EPSILON);
+            assert!((hold_probability(0.5, [2, 2], no_ad) - 0.5).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn deuce_needs_two_points_in_a_row() {
+        for p in [0.3, 0.55, 0.65, 0.9] {
+            let q = 1.0 - p;
+            let deuce = p * p / (p * p + q * q);
+            // Every tie from 40-40 on is deuce again
+            for tie in 3..6 {
+                assert!((hold_probability(p, [tie, tie], false) - deuce).abs() < EPSILON);
+            }
+            assert!((hold_probability(p, [4, 3], false) - (p + q * deuce)).abs() < EPSILON);
+            assert!((hold_probability(p, [3, 4], false) - p * deuce).abs() < EPSILON);
+            // No-ad plays a single deciding point instead
+            assert!((hold_probability(p, [3, 3], true) - p).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn a_tiebreak_at_six_all_is_decided_like_deuce() {
+        let (player1, player2) = (0.68, 0.61);
+        let both = player1 * (1.0 - player2);
+        let tie = both / (both + (1.0 - player1) * player2);
+        for first_server in [0, 1] {
+            for points in [[6, 6], [9, 9]] {
+                assert!((tiebreak_win_probability(player1, player2, first_server, points, 7) - tie).abs() < EPSILON);
+            }
+            // A tiebreak to ten reaches the same tie at 9-9
+            assert!((tiebreak_win_probability(player1, player2, first_server, [9, 9], 10) - tie).abs() < EPSILON);
+        }
+        // With even serving both players are level at every tie
+        assert!((tiebreak_win_probability(0.6, 0.6, 0, [6, 6], 7) - 0.5).abs() < EPSILON);
+
+        let hold = |serve_point_prob| HoldParams { serve_point_prob, no_ad: false };
+        let set = set_win_probability([6, 6], 0, hold(player1), hold(player2), TiebreakRule::To(7));
+        assert!((set - tiebreak_win_probability(player1, player2, 0, [0, 0], 7)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn simulated_holds_match_the_exact_ones() {
+        // A fixed seed, so the sample and the check against it are the same on every run
+        let mut config = SimulationConfig { seed: 42, ..SimulationConfig::default() };
+        // Without momentum, pressure or aces (whose chance leans on the lead and on the last point) a player wins
+        // every serve point with the same chance, whatever the score
+        config.model.momentum.strength = 0.0;
+        config.model.momentum.ace_strength = 0.0;
+        config.model.pressure = PressureCurve::default();
+        config.model.shocks = [&config.player1, &config.player2]
+            .map(|player| Shock { player: player.name.clone(), at_point: 1, serve_win_prob: 0.0, ace_prob: -1.0, points: None })
+            .to_vec();
+        let rules = config.scoring_rules();
+        let mut match_sim = TennisMatch::new(config.player1.clone(), config.player2.clone(), rules, config.model.clone(), match_seed(config.seed, 1));
+        // [server]: service games played and held, tiebreaks aside
+        let mut games = [[0u64; 2]; 2];
+        let mut situations = crate::situations::ServeSituations::default();
+        for n in 1..=400 {
+            match_sim.reset(match_seed(config.seed, n));
+            match_sim.play_match();
+            situations.merge(&match_sim.serve_situations);
+            for game in match_sim.games_played.iter().filter(|game| !game.tiebreak) {
+                games[game.server][0] += 1;
+                games[game.server][1] += (game.winner == game.server) as u64;
+            }
+        }
+        for (server, [played, held]) in games.into_iter().enumerate() {
+            // The server's share of serve points won outside tiebreaks
+            let (points, won, _) = situations.rates(server, 0);
+            let (tiebreak_points, tiebreak_won, _) = situations.rates(server, 3);
+            let p = (points as f64 * won - tiebreak_points as f64 * tiebreak_won) / (points - tiebreak_points) as f64;
+            let simulated = held as f64 / played as f64;
+            let exact = hold_probability(p, [0, 0], false);
+            assert!((simulated - exact).abs() < 0.02, "server {}: simulated hold {:.4}, exact {:.4} at p = {:.4}", server, simulated, exact, p);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::sink::redis_sink;
+use crate::what_if::{self, parse_points, MatchState};
+use crate::{config_from_args, find_player, flag_value, SimulationConfig};
+
+// What a live score payload says about the match
+pub enum LiveScore {
+    InPlay(MatchState),
+    Finished { winner: String },
+}
+
+// Turns the body of a live score response into a match state. Implement this for a provider's format and
+// pass it to `FeedClient::new`.
+pub trait ScoreParser: Send + Sync {
+    fn parse(&self, payload: &str, config: &SimulationConfig) -> Result<LiveScore, String>;
+}
+
+// The format understood out of the box, in player order:
+// {"sets": [1, 0], "games": [4, 4], "points": "30-40", "server": "Nadal"}, or {"winner": "Nadal"} once it's over
+pub struct JsonScoreParser;
+
+#[derive(Deserialize)]
+struct JsonScore {
+    #[serde(default)]
+    sets: [i32; 2],
+    #[serde(default)]
+    games: [i32; 2],
+    #[serde(default)]
+    points: Option<String>,
+    server: Option<String>,
+    winner: Option<String>,
+}
+
+impl ScoreParser for JsonScoreParser {
+    fn parse(&self, payload: &str, config: &SimulationConfig) -> Result<LiveScore, String> {
+        let score: JsonScore = serde_json::from_str(payload).map_err(|e| format!("invalid score payload: {}", e))?;
+        let players = [config.player1.clone(), config.player2.clone()];
+        if let Some(winner) = score.winner {
+            return Ok(LiveScore::Finished { winner: find_player(&players, &winner)?.name.clone() });
+        }
+        let server = score.server.ok_or("score payload has no server")?;
+        let server = if find_player(&players, &server)?.name == players[0].name { 0 } else { 1 };
+        let points = match &score.points {
+            Some(points) => parse_points(points, config.scoring_rules().tiebreak_at(score.sets, score.games).is_some())?,
+            None => [0, 0],
+        };
+        Ok(LiveScore::InPlay(MatchState { sets: score.sets, games: score.games, points, server }))
+    }
+}
+
+// Polls a live score endpoint over HTTP and hands each distinct score to the caller
+pub struct FeedClient {
+    http: reqwest::Client,
+    url: String,
+    interval: Duration,
+    parser: Box<dyn ScoreParser>,
+}
+
+impl FeedClient {
+    pub fn new(url: &str, interval: Duration, parser: Box<dyn ScoreParser>) -> Self {
+        FeedClient { http: reqwest::Client::new(), url: url.to_string(), interval, parser }
+    }
+
+    async fn poll(&self, config: &SimulationConfig) -> Result<LiveScore, String> {
+        let response = self.http.get(&self.url).send().await.map_err(|e| format!("could not fetch {}: {}", self.url, e))?;
+        let response = response.error_for_status().map_err(|e| format!("could not fetch {}: {}", self.url, e))?;
+        let payload = response.text().await.map_err(|e| format!("could not read the response of {}: {}", self.url, e))?;
+        self.parser.parse(&payload, config)
+    }
+
+    // Polls until the payload reports a winner, calling `on_score` each time the score changes, and returns the
+    // winner. A failed poll or an unreadable payload is reported and retried at the next interval.
+    pub async fn follow(&self, config: &SimulationConfig, mut on_score: impl FnMut(&MatchState)) -> String {
+        let mut last = None;
+        loop {
+            match self.poll(config).await {
+                Ok(LiveScore::Finished { winner }) => return winner,
+                Ok(LiveScore::InPlay(state)) if last.as_ref() != Some(&state) => match state.validate(&config.scoring_rules()) {
+                    Ok(()) => {
+                        on_score(&state);
+                        last = Some(state);
+                    }
+                    Err(e) => log::warn!("Skipping score from {}: {}", self.url, e),
+                },
+                Ok(LiveScore::InPlay(_)) => {}
+                Err(e) => log::error!("{}", e),
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+// feed <url> [--config <config.json>] [--interval-ms <ms>] [--simulations <n>] [--match-id <id>] [--redis <url>]
+// Follows a real match through a live score endpoint and re-simulates it from every new score, printing and
+// publishing the updated win probabilities
+pub fn run(args: &[String]) -> Result<(), String> {
+    let url = args.first().filter(|arg| !arg.starts_with("--")).ok_or("usage: feed <url> [--config <config.json>] [--interval-ms <ms>] [--simulations <n>] [--redis <url>]")?;
+    let config = config_from_args(args)?;
+    let interval = match flag_value(args, "--interval-ms") {
+        Some(value) => Duration::from_millis(value.parse().map_err(|e| format!("invalid --interval-ms: {}", e))?),
+        None => Duration::from_secs(5),
+    };
+    let simulations = match flag_value(args, "--simulations") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --simulations: {}", e))?,
+        None => 5000,
+    };
+    let match_id = flag_value(args, "--match-id").unwrap_or("feed").to_string();
+    let sinks = match flag_value(args, "--redis") {
+        Some(url) => vec![redis_sink(url)?],
+        None => Vec::new(),
+    };
+
+    let client = FeedClient::new(url, interval, Box::new(JsonScoreParser));
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|e| e.to_string())?;
+    let players = [&config.player1.name, &config.player2.name];
+    println!("Following {} vs {} from {}", players[0], players[1], url);
+    let winner = runtime.block_on(client.follow(&config, |state| {
+        let playouts = what_if::play_out(&config, state, simulations);
+        let prob = playouts.share(playouts.matches_won, simulations);
+        println!(" {} ({} serving) - {} {:.1}%", state.describe(&config.scoring_rules()), players[state.server], players[0], prob * 100.0);
+        let mut update: HashMap<String, serde_json::Value> = HashMap::new();
+        update.insert("state".to_string(), serde_json::json!(state));
+        update.insert("server".to_string(), serde_json::Value::from(players[state.server].as_str()));
+        update.insert(format!("{}_match_win_prob", players[0]), serde_json::Value::from(prob));
+        update.insert(format!("{}_match_win_prob", players[1]), serde_json::Value::from(1.0 - prob));
+        // The near-term markets from the same playouts, the game and set being those in play
+        let game_prob = playouts.share(playouts.games_won, simulations);
+        let set_prob = playouts.share(playouts.sets_won, simulations);
+        update.insert(format!("{}_game_win_prob", players[0]), serde_json::Value::from(game_prob));
+        update.insert(format!("{}_game_win_prob", players[1]), serde_json::Value::from(1.0 - game_prob));
+        update.insert(format!("{}_set_win_prob", players[0]), serde_json::Value::from(set_prob));
+        update.insert(format!("{}_set_win_prob", players[1]), serde_json::Value::from(1.0 - set_prob));
+        update.insert("deuce_prob".to_string(), serde_json::Value::from(playouts.share(playouts.games_to_deuce, simulations)));
+        update.insert("set_tiebreak_prob".to_string(), serde_json::Value::from(playouts.share(playouts.set_tiebreaks, simulations)));
+        update.insert("next_set_tiebreak_prob".to_string(), serde_json::Value::from(playouts.share(playouts.next_set_tiebreaks, simulations)));
+        for sink in &sinks {
+            sink.on_point(&match_id, &update);
+        }
+    }));
+
+    println!("{} wins", winner);
+    for sink in &sinks {
+        sink.on_match_complete(&match_id, &winner);
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::announce::Announcer;
+use crate::box_score::BoxScore;
+use crate::run_dir::CONFIG_FILE;
+use crate::timeline::MatchTimeline;
+use crate::{flag_value, match_seed, odds, scenario, SimulationConfig, TennisMatch, SET_STAT_KEYS};
+
+// Levels of the win probability chart, lowest first
+const CHART_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+// Widest the chart gets; longer matches are sampled
+const CHART_WIDTH: usize = 80;
+
+// inspect --run <dir> --match <k> [--variant <surface or scenario>] [--game <set>:<game>] [--plain]
+// Replays match k of a run from its per-match seed, with the same random draws as in the run, and prints it
+// point by point, then its box score and how player1's win probability moved. With --game only that game is
+// printed. The full point log, shots included, is written next to the run.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let usage = "usage: inspect --run <run dir> --match <match number> [--variant <surface or scenario>] [--game <set>:<game>]";
+    let run_dir = flag_value(args, "--run").ok_or(usage)?;
+    let match_number: usize = flag_value(args, "--match").ok_or(usage)?.parse().map_err(|e| format!("invalid --match: {}", e))?;
+    let config = SimulationConfig::from_file(&Path::new(run_dir).join(CONFIG_FILE).to_string_lossy())?;
+
+    let variants = scenario::variants(&config)?;
+    let config = match (flag_value(args, "--variant"), variants.is_empty()) {
+        (None, true) => config,
+        (Some(name), false) => variants.into_iter()
+            .find(|(variant, _)| variant == name || scenario::dir_name(variant) == name)
+            .map(|(_, variant)| variant)
+            .ok_or_else(|| format!("the run has no surface or scenario named '{}'", name))?,
+        (None, false) => {
+            let names: Vec<String> = variants.iter().map(|(name, _)| format!("'{}'", name)).collect();
+            return Err(format!("the run compares several configs; pick one with --variant ({})", names.join(", ")));
+        }
+        (Some(_), true) => return Err("the run has no surfaces or scenarios to pick from".to_string()),
+    };
+    if match_number == 0 || match_number > config.num_simulations {
+        return Err(format!("the run has matches 1 to {}", config.num_simulations));
+    }
+
+    // The same steps as `simulate_batch`, so the price series draws the same numbers too
+    let seed = match_seed(config.seed, match_number);
+    let mut match_sim = TennisMatch::new(config.player1.clone(), config.player2.clone(), config.scoring_rules(), config.model.clone(), seed);
+    let winner = match_sim.play_match();
+    odds::add_price_series(&mut match_sim.point_log, &config.player1, &config.player2, &config.prices, &mut match_sim.rng);
+
+    let timeline = MatchTimeline::from_match(&match_sim);
+    if let Some(game) = flag_value(args, "--game") {
+        return print_game(&timeline, game);
+    }
+
+    println!("Match {} of {} vs {} (master seed {}, match seed {})", match_number, config.player1.name, config.player2.name, config.seed, seed);
+    if let Some(toss) = &match_sim.toss {
+        println!("{} won the toss and chose to {}; {} serves first", toss.winner, toss.election.as_str(), toss.first_server);
+    }
+    let p1_prob = format!("{}_match_win_prob", config.player1.name);
+    let mut announcer = Announcer::new(&config.player1.name, &config.player2.name, config.scoring_rules(), config.notation.clone());
+    for point in &match_sim.point_log {
+        println!("{}", commentary(point, &announcer.call(point), &config.player1.name, &p1_prob));
+    }
+
+    println!("\n{} wins, sets {}-{}, {} points, {} shots", winner.name, match_sim.score.sets[0], match_sim.score.sets[1], match_sim.point_log.len(), match_sim.total_shots);
+    println!("{}", BoxScore::from_match(&match_sim).table(args.iter().any(|arg| arg == "--plain")));
+    for (i, player) in [&config.player1.name, &config.player2.name].into_iter().enumerate() {
+        let stats: Vec<String> = SET_STAT_KEYS.iter()
+            .map(|key| (key, match_sim.match_stat(i, key)))
+            .filter(|(_, value)| *value != 0)
+            .map(|(key, value)| format!("{} {}", key, value))
+            .collect();
+        println!(" {}: {}", player, stats.join(", "));
+    }
+    println!("{}'s match win probability: {}", config.player1.name, chart(&timeline.win_prob_series()));
+
+    let log_dir = Path::new(run_dir).join("inspect");
+    fs::create_dir_all(&log_dir).map_err(|e| format!("could not create {}: {}", log_dir.display(), e))?;
+    let log_path = log_dir.join(format!("match_{}.json", match_number));
+    let log = serde_json::to_string_pretty(&match_sim.point_log).map_err(|e| e.to_string())?;
+    fs::write(&log_path, log).map_err(|e| format!("could not write {}: {}", log_path.display(), e))?;
+    println!("Point log written to '{}'", log_path.display());
+    Ok(())
+}
+
+// One line per point: how it was won, the umpire's call after it and player1's match win probability
+fn commentary(point: &HashMap<String, serde_json::Value>, call: &str, player1: &str, p1_prob: &str) -> String {
+    let text = |key: &str| point.get(key).and_then(|value| value.as_str()).unwrap_or("").to_string();
+    let (server, winner) = (text("server"), text("winner"));
+    let shots = point["shots"].as_array().cloned().unwrap_or_default();
+    let outcome = |shot: &str| shots.iter().find(|entry| entry["shot"] == shot).and_then(|entry| entry["outcome"].as_str().map(str::to_string));
+
+    let mut description = if shots.is_empty() {
+        format!("penalty point to {}", winner)
+    } else {
+        let direction = shots.iter().find(|entry| entry["shot"] == "serve").and_then(|entry| entry["direction"].as_str()).map_or(String::new(), |direction| format!(" {}", direction));
+        let lets = shots.iter().filter(|entry| entry["shot"] == "let").count();
+        let lets = if lets > 0 { format!(" after {} let(s)", lets) } else { String::new() };
+        let how = match (outcome("serve").as_deref(), outcome("return").as_deref()) {
+            (Some("ace"), _) => "ace".to_string(),
+            (Some("double_fault"), _) => "double fault".to_string(),
+            (_, Some("error")) => format!("return error, point {}", winner),
+            (_, Some(depth)) => format!("{} return, point {}", depth, winner),
+            _ => format!("point {}", winner),
+        };
+        format!("{} serves{} from the {} court{}: {}", server, direction, text("court"), lets, how)
+    };
+    let conduct = text("conduct");
+    if !conduct.is_empty() {
+        description.push_str(&format!(" [{}]", conduct.replace(';', ", ")));
+    }
+    format!(
+        "{:>4}. {} | {} | {} {:.1}%",
+        point["point_number"].as_u64().unwrap_or(0), description, call, player1, point[p1_prob].as_f64().unwrap_or(0.5) * 100.0,
+    )
+}
+
+// One game of the timeline, given as "<set>:<game>": who won each point, what happened and the score after it
+fn print_game(timeline: &MatchTimeline, game: &str) -> Result<(), String> {
+    let invalid = || format!("invalid --game '{}'; give the set and the game within it, e.g. 2:5", game);
+    let (set, number) = game.split_once(':').ok_or_else(invalid)?;
+    let (set, number): (usize, usize) = (set.parse().map_err(|_| invalid())?, number.parse().map_err(|_| invalid())?);
+    let points = timeline.game(set, number);
+    let Some(last) = points.last() else {
+        return Err(format!("the match has no game {} in set {}", number, set));
+    };
+    let names = &timeline.players;
+    println!("Set {}, game {}, {} serving:", set, number, names[points[0].server]);
+    for point in &points {
+        println!("{:>3}. {} ({})", point.point, names[point.winner], point.score.points);
+    }
+    let events: Vec<String> = timeline.events_in_game(set, number).iter().map(|event| format!("{} {} (point {})", names[event.player], event.kind, event.point_number)).collect();
+    println!("Events: {}", if events.is_empty() { "none".to_string() } else { events.join(", ") });
+    if let Some(score) = timeline.score_after(set, number, last.point) {
+        println!("Score after: sets {}-{}, games {}-{} ({} first)", score.sets[0], score.sets[1], score.games[0], score.games[1], names[0]);
+    }
+    Ok(())
+}
+
+// Player1's win probability after each point as a line of block characters, sampled down to CHART_WIDTH
+fn chart(series: &[f64]) -> String {
+    let step = series.len().div_ceil(CHART_WIDTH).max(1);
+    series.iter().step_by(step).map(|prob| CHART_LEVELS[((prob.clamp(0.0, 1.0) * CHART_LEVELS.len() as f64) as usize).min(CHART_LEVELS.len() - 1)]).collect()
+}
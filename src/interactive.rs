@@ -0,0 +1,83 @@
+use std::io::{BufRead, Write};
+
+use crate::what_if::{self, MatchState};
+use crate::{config_from_args, find_player, flag_value};
+
+const KEYS: &str = "s = server wins, r = receiver wins, a = ace, d = double fault, u = undo, q = quit";
+
+// interactive [--config <config.json>] --server <player> [--score <sets and games>] [--points <points>] [--simulations <n>]
+// Scores a match point by point from the keyboard and prints both players' win probabilities after each
+// point, simulated from the new score
+pub fn run(args: &[String]) -> Result<(), String> {
+    let config = config_from_args(args)?;
+    let players = [config.player1.name.clone(), config.player2.name.clone()];
+    let server = flag_value(args, "--server").ok_or("usage: interactive [--config <config.json>] --server <player> [--score \"6-4 2-1\"] [--points 15-0]")?;
+    let server = if find_player(&[config.player1.clone(), config.player2.clone()], server)?.name == players[0] { 0 } else { 1 };
+    let simulations = match flag_value(args, "--simulations") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --simulations: {}", e))?,
+        None => 2000,
+    };
+    let rules = config.scoring_rules();
+    let state = MatchState::parse(flag_value(args, "--score").unwrap_or("0-0"), flag_value(args, "--points"), server, &rules)?;
+    state.validate(&rules)?;
+
+    // Each entry is a score together with the aces and double faults entered so far
+    let mut history = vec![(state, [0, 0], [0, 0])];
+    println!("{} vs {}, best of {} ({})", players[0], players[1], config.best_of, KEYS);
+    let print_state = |state: &MatchState| {
+        let prob = what_if::win_probability(&config, state, simulations);
+        println!("{} ({} serving): {} {:.1}%, {} {:.1}%", state.describe(&rules), players[state.server], players[0], prob * 100.0, players[1], (1.0 - prob) * 100.0);
+    };
+    print_state(&history[0].0);
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        let Some(line) = lines.next() else { break };
+        let line = line.map_err(|e| e.to_string())?;
+        let (state, mut aces, mut double_faults) = history.last().cloned().unwrap();
+        let winner = match line.trim() {
+            "s" => state.server,
+            "r" => 1 - state.server,
+            "a" => {
+                aces[state.server] += 1;
+                state.server
+            }
+            "d" => {
+                double_faults[state.server] += 1;
+                1 - state.server
+            }
+            "u" => {
+                if history.len() > 1 {
+                    history.pop();
+                }
+                print_state(&history.last().unwrap().0);
+                continue;
+            }
+            "q" => break,
+            "" => continue,
+            other => {
+                println!("Unknown key '{}' ({})", other, KEYS);
+                continue;
+            }
+        };
+
+        let (next, match_winner) = state.after_point(winner, &rules);
+        if let Some(match_winner) = match_winner {
+            println!("Game, set and match {} (sets {}-{})", players[match_winner], next.sets[0], next.sets[1]);
+            history.push((next, aces, double_faults));
+            break;
+        }
+        print_state(&next);
+        history.push((next, aces, double_faults));
+    }
+
+    let (_, aces, double_faults) = history.last().unwrap();
+    println!("{} points entered", history.len() - 1);
+    for player in 0..2 {
+        println!(" {}: {} aces, {} double faults", players[player], aces[player], double_faults[player]);
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::announce::Announcer;
+use crate::sink::redis_sink;
+use crate::{check, flag_value, match_seed, odds, SimulationConfig, TennisMatch};
+
+// live [--config <config.json>] [--matches <n>] [--pace-ms <ms>] [--redis <url>]
+// Plays matches one at a time and reveals them point by point, pausing between points, so
+// subscribers follow them as if they were being played
+pub fn run(args: &[String]) -> Result<(), String> {
+    let config = match flag_value(args, "--config") {
+        Some(path) => SimulationConfig::from_file(path)?,
+        None => SimulationConfig::default(),
+    };
+    check::reject_errors(&config)?;
+    let matches = match flag_value(args, "--matches") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --matches: {}", e))?,
+        None => 1,
+    };
+    let pace = match flag_value(args, "--pace-ms") {
+        Some(value) => Duration::from_millis(value.parse().map_err(|e| format!("invalid --pace-ms: {}", e))?),
+        None => Duration::from_secs(1),
+    };
+
+    let sinks = match flag_value(args, "--redis") {
+        Some(url) => vec![redis_sink(url)?],
+        None => Vec::new(),
+    };
+
+    let run_id = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_millis()).unwrap_or(0);
+    let p1_prob_column = format!("{}_match_win_prob", config.player1.name);
+    for n in 1..=matches {
+        let match_id = format!("{}-{}", run_id, n);
+        let seed = match_seed(config.seed, n);
+        let mut match_sim = TennisMatch::new(config.player1.clone(), config.player2.clone(), config.scoring_rules(), config.model.clone(), seed);
+        let winner = match_sim.play_match();
+        odds::add_price_series(&mut match_sim.point_log, &config.player1, &config.player2, &config.prices, &mut match_sim.rng);
+
+        println!("Match {}: {} vs {} (seed {})", match_id, config.player1.name, config.player2.name, seed);
+        let mut announcer = Announcer::new(&config.player1.name, &config.player2.name, config.scoring_rules(), config.notation.clone());
+        for point in &match_sim.point_log {
+            thread::sleep(pace);
+            let call = announcer.call(point);
+            println!(" {} ({} serving) - {} {:.1}%",
+                call,
+                point["server"].as_str().unwrap_or(""),
+                config.player1.name,
+                point[&p1_prob_column].as_f64().unwrap_or(0.5) * 100.0);
+            let mut point = point.clone();
+            point.insert("call".to_string(), serde_json::Value::String(call));
+            for sink in &sinks {
+                sink.on_point(&match_id, &point);
+            }
+        }
+
+        println!("{} wins\n", winner.name);
+        for sink in &sinks {
+            sink.on_match_complete(&match_id, &winner.name);
+        }
+    }
+    Ok(())
+}
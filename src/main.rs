@@ -1,122 +1,983 @@
-use std::collections::HashMap;
-use serde_json;
-use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::fs::OpenOptions;
-use std::io::Write;
-use std::sync::{Arc, Mutex};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::time::Instant;
 use rayon::prelude::*;
-
-#[derive(Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+use run_dir::{Checkpoint, LogPolicy, RunDirectory};
+use scoring::GameState;
+use outliers::OutlierSink;
+use perspective::{Perspective, ProbabilityAudit, WinProbability};
+use results::StoreSink;
+use sink::{ProgressSink, ResultSink, StreamMode, StreamSink};
+use stats::{Scope, StatsTracker};
+
+mod announce;
+mod backtest;
+mod box_score;
+mod check;
+mod compare_format;
+mod console;
+mod determinism;
+mod diff;
+mod elo;
+mod exact;
+mod inspect;
+mod interactive;
+mod live;
+mod markov;
+mod metrics;
+mod momentum;
+mod odds;
+mod outliers;
+mod partial;
+mod perspective;
+mod repl;
+mod results;
+mod run_dir;
+mod sanity;
+mod scaling;
+mod scenario;
+mod scoring;
+mod serve_first;
+mod significance;
+mod sink;
+mod situations;
+mod stats;
+mod surface;
+mod table;
+mod team;
+mod tiebreak;
+mod timeline;
+mod upset;
+mod what_if;
+#[cfg(feature = "parquet")]
+mod parquet_log;
+#[cfg(feature = "redis")]
+mod redis_sink;
+#[cfg(feature = "object_store")]
+mod upload;
+#[cfg(feature = "duckdb")]
+mod duckdb_sink;
+#[cfg(feature = "postgres")]
+mod postgres_sink;
+#[cfg(feature = "feed")]
+mod feed;
+#[cfg(feature = "async")]
+mod batch_stream;
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
 struct Player {
     name: String,
     serve_win_prob: f64,
     ace_prob: f64,
     double_fault_prob: f64,
+    // Added to serve_win_prob while a tiebreak is in progress
+    #[serde(default)]
+    tiebreak_serve_modifier: f64,
+    // Added to serve_win_prob when serving from the deuce / ad court
+    #[serde(default)]
+    deuce_court_modifier: f64,
+    #[serde(default)]
+    ad_court_modifier: f64,
+    // When set, each serve picks a direction first and uses that direction's probabilities
+    #[serde(default)]
+    serve_placement: Option<ServePlacement>,
+    // When set, returns against this player's opponent are played out as error / short / deep
+    #[serde(default)]
+    return_profile: Option<ReturnProfile>,
+    // When set, points start with a first serve that can miss, followed by a second serve
+    #[serde(default)]
+    first_serve: Option<FirstServe>,
+    // What the player elects on winning the pre-match coin toss
+    #[serde(default)]
+    toss_election: TossElection,
+    // Elo rating, for the expected rating change in the report
+    #[serde(default)]
+    elo: Option<f64>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TossElection {
+    #[default]
+    Serve,
+    Receive,
+    // Leaves the choice to the opponent, who can't defer it back and serves if their own election is to defer
+    Defer,
+}
+
+impl TossElection {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TossElection::Serve => "serve",
+            TossElection::Receive => "receive",
+            TossElection::Defer => "defer",
+        }
+    }
+}
+
+// The coin toss of a match, for its summary
+struct Toss {
+    winner: String,
+    election: TossElection,
+    first_server: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ReturnOutcome {
+    Error,
+    Short,
+    Deep,
+}
+
+impl ReturnOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReturnOutcome::Error => "error",
+            ReturnOutcome::Short => "short",
+            ReturnOutcome::Deep => "deep",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+struct ReturnProfile {
+    // Chance of missing a serve that is neither an ace nor a double fault
+    error_prob: f64,
+    // Chance that a return which lands is deep
+    deep_prob: f64,
+    // Scales both error_prob and deep_prob by (1 + aggressiveness): more risk, more depth
+    #[serde(default)]
+    aggressiveness: f64,
+}
+
+impl ReturnProfile {
+    fn sample_outcome(&self, rng: &mut impl Rng) -> ReturnOutcome {
+        let scale = 1.0 + self.aggressiveness;
+        if rng.gen::<f64>() < (self.error_prob * scale).clamp(0.0, 1.0) {
+            ReturnOutcome::Error
+        } else if rng.gen::<f64>() < (self.deep_prob * scale).clamp(0.0, 1.0) {
+            ReturnOutcome::Deep
+        } else {
+            ReturnOutcome::Short
+        }
+    }
+}
+
+// Aces come off first serves that land and double faults off second serves, at rates that keep ace_prob and
+// double_fault_prob per point
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+struct FirstServe {
+    // Chance of a first serve landing in
+    in_prob: f64,
+    // Taken off the server's rally win probability after a second serve
+    #[serde(default)]
+    second_serve_penalty: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ServeDirection {
+    Wide,
+    Body,
+    T,
+}
+
+impl ServeDirection {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ServeDirection::Wide => "wide",
+            ServeDirection::Body => "body",
+            ServeDirection::T => "t",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+struct PlacementProfile {
+    // Relative frequency of this direction; shares are normalized over the three directions
+    share: f64,
+    ace_prob: f64,
+    serve_win_prob: f64,
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+struct ServePlacement {
+    wide: PlacementProfile,
+    body: PlacementProfile,
+    t: PlacementProfile,
+}
+
+impl ServePlacement {
+    fn profile(&self, direction: ServeDirection) -> &PlacementProfile {
+        match direction {
+            ServeDirection::Wide => &self.wide,
+            ServeDirection::Body => &self.body,
+            ServeDirection::T => &self.t,
+        }
+    }
+
+    fn sample_direction(&self, rng: &mut impl Rng) -> ServeDirection {
+        let total = self.wide.share + self.body.share + self.t.share;
+        let draw = rng.gen::<f64>() * total;
+        if draw < self.wide.share {
+            ServeDirection::Wide
+        } else if draw < self.wide.share + self.body.share {
+            ServeDirection::Body
+        } else {
+            ServeDirection::T
+        }
+    }
+
+    // Share-weighted average over directions, for estimates made before the direction is known
+    fn expected(&self, value: impl Fn(&PlacementProfile) -> f64) -> f64 {
+        let total = self.wide.share + self.body.share + self.t.share;
+        [&self.wide, &self.body, &self.t].iter().map(|profile| profile.share * value(profile)).sum::<f64>() / total
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Court {
+    Deuce,
+    Ad,
+}
+
+impl Court {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Court::Deuce => "deuce",
+            Court::Ad => "ad",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum MomentumDecay {
+    // The streak carries over for the whole match
+    Never,
+    ResetEachGame,
+    ResetEachSet,
+    // The streak is never reset but halves in weight every `n` points
+    HalfLife(f64),
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct MomentumConfig {
+    // Serve/return point probability shift per point in the current streak
+    strength: f64,
+    cap: f64,
+    // Ace probability shift per point in the server's current streak
+    ace_strength: f64,
+    ace_cap: f64,
+    decay: MomentumDecay,
+}
+
+impl Default for MomentumConfig {
+    fn default() -> Self {
+        MomentumConfig {
+            strength: 0.01,
+            cap: 0.05,
+            ace_strength: 0.005,
+            ace_cap: 0.02,
+            decay: MomentumDecay::ResetEachGame,
+        }
+    }
+}
+
+impl MomentumConfig {
+    // Weight kept by the existing streak each time another point is added to it
+    fn streak_retention(&self) -> f64 {
+        match self.decay {
+            MomentumDecay::HalfLife(points) if points > 0.0 => libm::pow(0.5, 1.0 / points),
+            _ => 1.0,
+        }
+    }
+}
+
+// Serve probability modifiers by game situation, applied to the server of the next point
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct PressureCurve {
+    // Keyed by the server-first point score as logged ("0-30", "30-40", "Deuce", "Ad-Out", ...)
+    points: HashMap<String, f64>,
+    serving_for_set: f64,
+    // Applied on top of serving_for_set
+    serving_for_match: f64,
+}
+
+impl PressureCurve {
+    // The point modifiers by game state (see markov::STATES); a score that is not one is never reached
+    fn by_state(&self) -> [f64; markov::STATES.len()] {
+        let mut modifiers = [0.0; markov::STATES.len()];
+        for (state, label) in markov::STATES.iter().enumerate() {
+            modifiers[state] = self.points.get(*label).copied().unwrap_or(0.0);
+        }
+        modifiers
+    }
+}
+
+// How the depth of a landed return shifts the server's chance of winning the rally
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct ReturnModel {
+    short_return_bonus: f64,
+    deep_return_penalty: f64,
+}
+
+impl Default for ReturnModel {
+    fn default() -> Self {
+        ReturnModel {
+            short_return_bonus: 0.08,
+            deep_return_penalty: 0.08,
+        }
+    }
+}
+
+// Serves that clip the net and land in: replayed as lets, or played on in no-let formats
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct LetModel {
+    // Chance of a let on each serve; 0 turns the model off
+    prob: f64,
+    no_let: bool,
+}
+
+// Wrong calls ending a rally and the players' challenges of them
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct ChallengeModel {
+    // Chance that the call ending a rally is wrong; 0 turns the model off
+    wrong_call_prob: f64,
+    // Unsuccessful challenges allowed per player per set, and extra ones once a tiebreak starts
+    per_set: i32,
+    tiebreak_extra: i32,
+    // Chance that the player a call went against challenges it, when the call is wrong / right
+    challenge_wrong_prob: f64,
+    challenge_right_prob: f64,
+    // Players only challenge on points at least this important (see `point_importance`)
+    min_leverage: f64,
+    // Share of overturned calls where the rally is replayed rather than the point awarded to the challenger
+    replay_prob: f64,
+}
+
+impl Default for ChallengeModel {
+    fn default() -> Self {
+        ChallengeModel {
+            wrong_call_prob: 0.0,
+            per_set: 3,
+            tiebreak_extra: 1,
+            challenge_wrong_prob: 0.7,
+            challenge_right_prob: 0.05,
+            min_leverage: 0.0,
+            replay_prob: 0.3,
+        }
+    }
+}
+
+enum CallReview {
+    Replayed,
+    Awarded,
+}
+
+// Time violations on the serve clock and code violations (racket abuse, audible obscenities, ...)
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct ConductModel {
+    // Chance per point that the server breaks the serve clock; 0 turns time violations off
+    time_violation_prob: f64,
+    // Chance that the loser of a point commits a code violation after it; 0 turns code violations off
+    code_violation_prob: f64,
+    // Both chances are scaled by 1 + pressure_factor * the point's pressure index
+    pressure_factor: f64,
+    // Chance that the second serve a server is left with after a time violation fault is also a fault
+    second_serve_fault_prob: f64,
+}
+
+impl Default for ConductModel {
+    fn default() -> Self {
+        ConductModel {
+            time_violation_prob: 0.0,
+            code_violation_prob: 0.0,
+            pressure_factor: 10.0,
+            second_serve_fault_prob: 0.1,
+        }
+    }
+}
+
+// Rain suspending outdoor matches between games, and how play resumes
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct WeatherModel {
+    // Chance of rain suspending play after each game that doesn't end a set; 0 turns the model off
+    suspension_prob: f64,
+    // Indoor matches, or courts with a roof, are never suspended
+    indoor: bool,
+    // Clears any momentum when play resumes, as at the start of a set
+    reset_momentum: bool,
+    // Added to the server's serve_win_prob for the first `rest_points` points after play resumes
+    resume_serve_modifier: f64,
+    rest_points: i32,
+}
+
+impl Default for WeatherModel {
+    fn default() -> Self {
+        WeatherModel {
+            suspension_prob: 0.0,
+            indoor: false,
+            reset_momentum: true,
+            resume_serve_modifier: -0.02,
+            rest_points: 8,
+        }
+    }
+}
+
+// A sudden change to a player's serve from a given point of the match, such as an injury or a bout of nerves,
+// for stress-testing in-play prices, which don't know about it
+#[derive(Clone, Deserialize, Serialize)]
+struct Shock {
+    player: String,
+    // The point of the match the shock takes effect at, counted from 1
+    at_point: usize,
+    // Added to the player's serve_win_prob and ace_prob while the shock lasts
+    serve_win_prob: f64,
+    #[serde(default)]
+    ace_prob: f64,
+    // Points of the match the shock lasts; to the end of the match when unset
+    #[serde(default)]
+    points: Option<usize>,
+}
+
+impl Shock {
+    fn is_active(&self, server: &str, point_number: usize) -> bool {
+        self.player == server && point_number >= self.at_point && self.points.is_none_or(|points| point_number < self.at_point + points)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Penalty {
+    Point,
+    Game,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct ModelConfig {
+    momentum: MomentumConfig,
+    pressure: PressureCurve,
+    returns: ReturnModel,
+    lets: LetModel,
+    challenges: ChallengeModel,
+    conduct: ConductModel,
+    weather: WeatherModel,
+    shocks: Vec<Shock>,
+}
+
+// Per-player counters, kept by game, set and match in `stats`, each credited to the player it happened to at the
+// time, e.g. the server of the point
+const SET_STAT_KEYS: [&str; 32] = [
+    "aces",
+    "double_faults",
+    "tiebreaks_played",
+    "tiebreaks_won",
+    "deuce_points_served",
+    "deuce_points_won",
+    "ad_points_served",
+    "ad_points_won",
+    // Counted for the server: points off a first serve that landed and off a second serve (double faults
+    // included), and how many of them were won
+    "first_serves_in",
+    "first_serve_points_won",
+    "second_serve_points",
+    "second_serve_points_won",
+    // Counted for the server; tiebreaks are not service games
+    "break_points_faced",
+    "break_points_saved",
+    "service_games",
+    "service_games_held",
+    // Counted for the returning player
+    "return_errors",
+    "short_returns",
+    "deep_returns",
+    // Counted for the player the call went against
+    "challenges",
+    "challenges_won",
+    "uncorrected_calls",
+    // Counted for the offending player; penalty_points are the points they conceded to point and game penalties
+    "time_violations",
+    "code_violations",
+    "penalty_points",
+    // Counted for both players
+    "rain_suspensions",
+    // Counted for the set winner: sets won to love (6-0), to 1 (6-1), and without losing a point
+    "bagels",
+    "breadsticks",
+    "golden_sets",
+    // Counted for both players, except deciding_sets_won
+    "deciding_sets_played",
+    "deciding_sets_won",
+    "deciding_set_tiebreaks",
+];
+
+// One finished game of a match; a tiebreak counts as a game served by its first server
+#[derive(Clone, Copy)]
+struct GameRecord {
+    // Sets finished before the game
+    set: usize,
+    // Indices into (player1, player2)
+    server: usize,
+    winner: usize,
+    tiebreak: bool,
+    // Entries of the point log, penalty points included
+    points: usize,
+}
+
+// How often each player won the in-play markets tracked per match, in player order
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct MarketCounts {
+    // Matches in which player1 or player2 broke serve first, or nobody was broken
+    first_break: [i32; 3],
+    // Per set number: sets in which player1 or player2 was first to `race_to_games` games
+    race: Vec<[i32; 2]>,
+    // Matches in which player1 or player2 served more aces, or both the same number
+    more_aces: [i32; 3],
+    // Per player: matches by number of aces served
+    ace_counts: [Vec<i32>; 2],
+    // Matches by their games in every set, player1 first, e.g. "6-4 4-6 7-6"
+    score_paths: HashMap<String, i32>,
+}
+
+impl MarketCounts {
+    fn add_match(&mut self, games: &[GameRecord], aces: [i32; 2], race_to_games: i32) {
+        self.more_aces[match aces[0].cmp(&aces[1]) {
+            std::cmp::Ordering::Greater => 0,
+            std::cmp::Ordering::Less => 1,
+            std::cmp::Ordering::Equal => 2,
+        }] += 1;
+        for (counts, aces) in self.ace_counts.iter_mut().zip(aces) {
+            if counts.len() <= aces as usize {
+                counts.resize(aces as usize + 1, 0);
+            }
+            counts[aces as usize] += 1;
+        }
+
+        let first_break = games.iter().find(|game| !game.tiebreak && game.winner != game.server).map_or(2, |game| game.winner);
+        self.first_break[first_break] += 1;
+
+        let mut set_games = Vec::<[i32; 2]>::new();
+        for game in games {
+            if set_games.len() <= game.set {
+                set_games.resize(game.set + 1, [0, 0]);
+            }
+            let set = &mut set_games[game.set];
+            set[game.winner] += 1;
+            if set[game.winner] == race_to_games && set[1 - game.winner] < race_to_games {
+                if self.race.len() <= game.set {
+                    self.race.resize(game.set + 1, [0, 0]);
+                }
+                self.race[game.set][game.winner] += 1;
+            }
+        }
+        let path: Vec<String> = set_games.iter().map(|games| format!("{}-{}", games[0], games[1])).collect();
+        *self.score_paths.entry(path.join(" ")).or_insert(0) += 1;
+    }
+
+    fn merge(&mut self, other: &MarketCounts) {
+        for (total, count) in self.first_break.iter_mut().zip(other.first_break) {
+            *total += count;
+        }
+        if self.race.len() < other.race.len() {
+            self.race.resize(other.race.len(), [0, 0]);
+        }
+        for (total, counts) in self.race.iter_mut().zip(&other.race) {
+            total[0] += counts[0];
+            total[1] += counts[1];
+        }
+        for (total, count) in self.more_aces.iter_mut().zip(other.more_aces) {
+            *total += count;
+        }
+        for (path, count) in &other.score_paths {
+            *self.score_paths.entry(path.clone()).or_insert(0) += count;
+        }
+        for (totals, counts) in self.ace_counts.iter_mut().zip(&other.ace_counts) {
+            if totals.len() < counts.len() {
+                totals.resize(counts.len(), 0);
+            }
+            for (total, count) in totals.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+    }
+}
+
+// Per-match drama measures, based on player1's logged match win probability
+#[derive(Clone, Deserialize, Serialize)]
+struct ExcitementIndex {
+    // Sum over all points of how much the point could swing the match win probability
+    cumulative_leverage: f64,
+    // Times the favourite (match win probability above 0.5) switched players
+    lead_changes: i32,
+    // Lowest match win probability the eventual winner had at any point
+    winner_min_win_prob: f64,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+struct CalibrationBucket {
+    count: u64,
+    wins: u64,
+    prob_sum: f64,
+}
+
+// Accuracy of one family of win probability estimates against realized outcomes
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct ForecastScore {
+    count: u64,
+    brier_sum: f64,
+    log_loss_sum: f64,
+    // Predictions bucketed by decile: [0, 0.1), [0.1, 0.2), ..., [0.9, 1.0]
+    buckets: [CalibrationBucket; 10],
+}
+
+impl ForecastScore {
+    fn add(&mut self, prob: f64, outcome: bool) {
+        let target = if outcome { 1.0 } else { 0.0 };
+        let clipped = prob.clamp(1e-15, 1.0 - 1e-15);
+        self.count += 1;
+        self.brier_sum += (prob - target).powi(2);
+        self.log_loss_sum -= libm::log(if outcome { clipped } else { 1.0 - clipped });
+
+        let bucket = &mut self.buckets[((prob * 10.0) as usize).min(9)];
+        bucket.count += 1;
+        bucket.wins += outcome as u64;
+        bucket.prob_sum += prob;
+    }
+
+    fn merge(&mut self, other: &ForecastScore) {
+        self.count += other.count;
+        self.brier_sum += other.brier_sum;
+        self.log_loss_sum += other.log_loss_sum;
+        for (bucket, other_bucket) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            bucket.count += other_bucket.count;
+            bucket.wins += other_bucket.wins;
+            bucket.prob_sum += other_bucket.prob_sum;
+        }
+    }
+
+    fn brier_score(&self) -> f64 {
+        self.brier_sum / self.count.max(1) as f64
+    }
+
+    fn log_loss(&self) -> f64 {
+        self.log_loss_sum / self.count.max(1) as f64
+    }
+}
+
+// Estimates scored against outcomes, in the order they are reported
+const FORECAST_MODELS: [&str; 4] = ["next_point_win_prob", "game_win_prob", "set_win_prob", "match_win_prob"];
+
+// The score of a match in play, in player order (0 = player1), with the server and whether a tiebreak is being
+// played. It is Copy and free of the logging state, so the point-by-point engine reads and updates plain integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct MatchScore {
+    sets: [i32; 2],
+    games: [i32; 2],
+    // Points of the current game, or of the tiebreak
+    points: [i32; 2],
+    server: usize,
+    tiebreak: bool,
 }
 
 struct TennisMatch {
     player1: Player,
     player2: Player,
-    best_of: i32,
-    grand_slam: bool,
+    rules: scoring::ScoringRules,
+    model: ModelConfig,
+    // model.pressure.points by markov::state, so a point's modifier is looked up without formatting its score
+    pressure_points: [f64; markov::STATES.len()],
     server: Option<Player>,
     receiver: Option<Player>,
-    score: HashMap<String, Vec<i32>>,
-    set_history: Vec<HashMap<String, HashMap<String, i32>>>,
+    score: MatchScore,
     total_shots: i32,
     point_log: Vec<HashMap<String, serde_json::Value>>,
-    stats: HashMap<String, HashMap<String, i32>>,
+    stats: StatsTracker,
     last_point_winner: Option<Player>,
     consecutive_points: i32,
+    // Length of the current winning streak after momentum decay is applied
+    momentum_streak: f64,
     last_point_ace: bool,
-    is_tiebreak: bool,
+    last_point_double_fault: bool,
+    last_point_court: Court,
+    // Direction of the serve currently being played, if the server has a placement model
+    serve_direction: Option<ServeDirection>,
+    last_serve_direction: Option<ServeDirection>,
+    last_return_outcome: Option<ReturnOutcome>,
+    // "replayed" or "played" for each let served before the last point's serve
+    last_point_lets: Vec<&'static str>,
+    // Unsuccessful challenges each player has left in the set
+    challenges_left: [i32; 2],
+    // Time and code violations of each player in the match, which decide the penalty for the next one
+    time_violations: [i32; 2],
+    code_violations: [i32; 2],
+    // Penalty still to be applied against a player (0 = player1): a game penalty lasts until the game is over
+    pending_penalty: Option<(usize, Penalty)>,
+    // Violations and penalties of the last point, as "player:violation:penalty" or "player:penalty"
+    last_point_conduct: Vec<String>,
+    // The last point was awarded for a penalty rather than played
+    last_point_penalty: bool,
+    // Points still to be played under the resume modifier after a rain suspension
+    resume_points_left: i32,
+    // Points won by each player (0 = player1) in the current set
+    set_points_won: [i32; 2],
+    games_played: Vec<GameRecord>,
+    // Moves between the score states of the match's service games, point by point
+    score_transitions: markov::ScoreTransitions,
+    serve_situations: situations::ServeSituations,
+    // Index (0 = player1) of the player serving first in each set played; a match resumed mid-set counts the
+    // server at the resumed score
+    set_first_servers: Vec<usize>,
+    // Whether the point being played is on a second serve
+    on_second_serve: bool,
     tiebreak_points: i32,
     tiebreak_server: Option<Player>,
+    cumulative_leverage: f64,
+    // Leverage of the last point and that leverage scaled by how close the match was before it
+    last_point_leverage: f64,
+    last_point_pressure: f64,
+    lead_changes: i32,
+    // Index (0 = player1) of the player last favoured to win the match
+    match_leader: Option<usize>,
+    min_match_win_prob: [f64; 2],
+    // Player1's estimates made before each point, waiting for the point/game/set/match to finish
+    pending_forecasts: HashMap<String, Vec<f64>>,
+    forecast_scores: HashMap<String, ForecastScore>,
+    toss: Option<Toss>,
+    // Seeded per match (see `match_seed`), so a run can be reproduced from its master seed
+    rng: StdRng,
 }
 
 impl TennisMatch {
-    fn new(player1: Player, player2: Player, best_of: i32, grand_slam: bool) -> Self {
-        let mut score = HashMap::new();
-        score.insert("sets".to_string(), vec![0, 0]);
-        score.insert("games".to_string(), vec![0, 0]);
-        score.insert("points".to_string(), vec![0, 0]);
-
-        let mut stats = HashMap::new();
-        stats.insert(player1.name.clone(), HashMap::new());
-        stats.insert(player2.name.clone(), HashMap::new());
-        stats.get_mut(&player1.name).unwrap().insert("aces".to_string(), 0);
-        stats.get_mut(&player1.name).unwrap().insert("double_faults".to_string(), 0);
-        stats.get_mut(&player2.name).unwrap().insert("aces".to_string(), 0);
-        stats.get_mut(&player2.name).unwrap().insert("double_faults".to_string(), 0);
+    fn new(player1: Player, player2: Player, rules: scoring::ScoringRules, model: ModelConfig, seed: u64) -> Self {
+        let challenges_left = [model.challenges.per_set; 2];
+        let pressure_points = model.pressure.by_state();
 
         TennisMatch {
             player1,
             player2,
-            best_of,
-            grand_slam,
+            rules,
+            model,
+            pressure_points,
             server: None,
             receiver: None,
-            score,
-            set_history: Vec::new(),
+            score: MatchScore::default(),
             total_shots: 0,
             point_log: Vec::new(),
-            stats,
+            stats: StatsTracker::default(),
             last_point_winner: None,
             consecutive_points: 0,
+            momentum_streak: 0.0,
             last_point_ace: false,
-            is_tiebreak: false,
+            last_point_double_fault: false,
+            last_point_court: Court::Deuce,
+            serve_direction: None,
+            last_serve_direction: None,
+            last_return_outcome: None,
+            last_point_lets: Vec::new(),
+            challenges_left,
+            time_violations: [0, 0],
+            code_violations: [0, 0],
+            pending_penalty: None,
+            last_point_conduct: Vec::new(),
+            last_point_penalty: false,
+            resume_points_left: 0,
+            set_points_won: [0, 0],
+            games_played: Vec::new(),
+            score_transitions: markov::ScoreTransitions::default(),
+            serve_situations: situations::ServeSituations::default(),
+            set_first_servers: Vec::new(),
+            on_second_serve: false,
             tiebreak_points: 0,
             tiebreak_server: None,
+            cumulative_leverage: 0.0,
+            last_point_leverage: 0.0,
+            last_point_pressure: 0.0,
+            lead_changes: 0,
+            match_leader: None,
+            min_match_win_prob: [0.5, 0.5],
+            pending_forecasts: HashMap::new(),
+            forecast_scores: HashMap::new(),
+            toss: None,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // Readies the match to be played again from the start with `seed`, as `new` would, but keeps the capacity of
+    // its point log, game records and stat maps. Every field is named, so a new field cannot be left out.
+    fn reset(&mut self, seed: u64) {
+        let TennisMatch {
+            player1: _,
+            player2: _,
+            rules: _,
+            model,
+            pressure_points: _,
+            server,
+            receiver,
+            score,
+            total_shots,
+            point_log,
+            stats,
+            last_point_winner,
+            consecutive_points,
+            momentum_streak,
+            last_point_ace,
+            last_point_double_fault,
+            last_point_court,
+            serve_direction,
+            last_serve_direction,
+            last_return_outcome,
+            last_point_lets,
+            challenges_left,
+            time_violations,
+            code_violations,
+            pending_penalty,
+            last_point_conduct,
+            last_point_penalty,
+            resume_points_left,
+            set_points_won,
+            games_played,
+            score_transitions,
+            serve_situations,
+            set_first_servers,
+            on_second_serve,
+            tiebreak_points,
+            tiebreak_server,
+            cumulative_leverage,
+            last_point_leverage,
+            last_point_pressure,
+            lead_changes,
+            match_leader,
+            min_match_win_prob,
+            pending_forecasts,
+            forecast_scores,
+            toss,
+            rng,
+        } = self;
+        *server = None;
+        *receiver = None;
+        *score = MatchScore::default();
+        *total_shots = 0;
+        point_log.clear();
+        stats.clear();
+        *last_point_winner = None;
+        *consecutive_points = 0;
+        *momentum_streak = 0.0;
+        *last_point_ace = false;
+        *last_point_double_fault = false;
+        *last_point_court = Court::Deuce;
+        *serve_direction = None;
+        *last_serve_direction = None;
+        *last_return_outcome = None;
+        last_point_lets.clear();
+        *challenges_left = [model.challenges.per_set; 2];
+        *time_violations = [0, 0];
+        *code_violations = [0, 0];
+        *pending_penalty = None;
+        last_point_conduct.clear();
+        *last_point_penalty = false;
+        *resume_points_left = 0;
+        *set_points_won = [0, 0];
+        games_played.clear();
+        score_transitions.clear();
+        serve_situations.clear();
+        set_first_servers.clear();
+        *on_second_serve = false;
+        *tiebreak_points = 0;
+        *tiebreak_server = None;
+        *cumulative_leverage = 0.0;
+        *last_point_leverage = 0.0;
+        *last_point_pressure = 0.0;
+        *lead_changes = 0;
+        *match_leader = None;
+        *min_match_win_prob = [0.5, 0.5];
+        pending_forecasts.clear();
+        forecast_scores.clear();
+        *toss = None;
+        *rng = StdRng::seed_from_u64(seed);
+    }
+
+    // A stat of player `player` (0 = player1) over the match so far
+    fn match_stat(&self, player: usize, key: &str) -> i32 {
+        self.stats.get(Scope::Match, player, key)
+    }
+
+    fn record_forecasts(&mut self) {
+        let forecasts = [
+            ("next_point_win_prob", self.calculate_next_point_win_probability()),
+            ("game_win_prob", self.calculate_game_win_probability()),
+            ("set_win_prob", self.calculate_set_win_probability()),
+            ("match_win_prob", self.calculate_match_win_probability()),
+        ];
+        for (model, prob) in forecasts {
+            self.pending_forecasts.entry(model.to_string()).or_default().push(prob.player(0));
+        }
+    }
+
+    fn resolve_forecasts(&mut self, model: &str, player1_won: bool) {
+        let score = self.forecast_scores.entry(model.to_string()).or_default();
+        for prob in self.pending_forecasts.entry(model.to_string()).or_default().drain(..) {
+            score.add(prob, player1_won);
         }
     }
 
     fn switch_server(&mut self) {
         std::mem::swap(&mut self.server, &mut self.receiver);
+        self.score.server = 1 - self.score.server;
     }
 
-    fn is_final_set(&self) -> bool {
-        self.score["sets"].iter().sum::<i32>() == self.best_of - 1
+    // Puts `server` (0 = player1) on serve
+    fn set_server(&mut self, server: usize) {
+        let players = [&self.player1, &self.player2];
+        self.server = Some(players[server].clone());
+        self.receiver = Some(players[1 - server].clone());
+        self.score.server = server;
+    }
+
+    // Sets won by each player (0 = player1)
+    fn sets_won(&self) -> [i32; 2] {
+        self.score.sets
     }
 
     fn is_set_over(&self) -> bool {
-        if !self.is_tiebreak {
-            self.score["games"].iter().max().unwrap() >= &6 && (self.score["games"][0] - self.score["games"][1]).abs() >= 2
+        if !self.score.tiebreak {
+            let games = &self.score.games;
+            self.rules.set_won(games[0].max(games[1]), games[0].min(games[1]))
         } else {
-            if self.grand_slam && self.is_final_set() {
-                self.score["points"].iter().max().unwrap() >= &10 && (self.score["points"][0] - self.score["points"][1]).abs() >= 2
-            } else {
-                self.score["points"].iter().max().unwrap() >= &7 && (self.score["points"][0] - self.score["points"][1]).abs() >= 2
-            }
+            let target = self.rules.tiebreak_points(self.sets_won());
+            self.score.points.iter().max().unwrap() >= &target && (self.score.points[0] - self.score.points[1]).abs() >= 2
         }
     }
 
+    // The point score as logged and called, server first; what it means for the game comes from game_state
     fn format_point_score(&self) -> String {
-        if !self.is_tiebreak {
-            let server_points = self.score["points"][if self.server.as_ref().unwrap().name == self.player1.name { 0 } else { 1 }];
-            let receiver_points = self.score["points"][if self.server.as_ref().unwrap().name == self.player1.name { 1 } else { 0 }];
-            if server_points == receiver_points && server_points >= 3 {
-                "Deuce".to_string()
-            } else if server_points.max(receiver_points) >= 4 {
-                if (server_points - receiver_points).abs() == 1 {
-                    if server_points > receiver_points { "Ad-In".to_string() } else { "Ad-Out".to_string() }
-                } else if (server_points - receiver_points).abs() >= 2 {
-                    "GAME".to_string()
-                } else {
-                    format!("{}-{}", self.point_to_tennis_score(server_points), self.point_to_tennis_score(receiver_points))
-                }
-            } else {
-                format!("{}-{}", self.point_to_tennis_score(server_points), self.point_to_tennis_score(receiver_points))
-            }
-        } else {
-            let server_points = self.score["points"][if self.server.as_ref().unwrap().name == self.player1.name { 0 } else { 1 }];
-            let receiver_points = self.score["points"][if self.server.as_ref().unwrap().name == self.player1.name { 1 } else { 0 }];
-            format!("{}-{}", server_points, receiver_points)
+        let [server_points, receiver_points] = Perspective::Server.orient(self.score.server, self.score.points);
+        if self.score.tiebreak {
+            return format!("{}-{}", server_points, receiver_points);
+        }
+        match self.rules.game_state([server_points, receiver_points]) {
+            GameState::Won(_) => "GAME".to_string(),
+            GameState::Deuce => "Deuce".to_string(),
+            GameState::Advantage(0) => "Ad-In".to_string(),
+            GameState::Advantage(_) => "Ad-Out".to_string(),
+            GameState::InProgress => format!("{}-{}", self.point_to_tennis_score(server_points), self.point_to_tennis_score(receiver_points)),
         }
     }
 
     fn point_to_tennis_score(&self, points: i32) -> String {
-        if self.is_tiebreak {
+        if self.score.tiebreak {
             points.to_string()
         } else {
             match points {
@@ -130,47 +991,52 @@ impl TennisMatch {
     }
 
     fn format_game_score(&self) -> String {
-        let server_games = self.score["games"][if self.server.as_ref().unwrap().name == self.player1.name { 0 } else { 1 }];
-        let receiver_games = self.score["games"][if self.server.as_ref().unwrap().name == self.player1.name { 1 } else { 0 }];
+        let server_games = self.score.games[self.score.server];
+        let receiver_games = self.score.games[1 - self.score.server];
         format!("{}-{}", server_games, receiver_games)
     }
 
     fn format_set_score(&self) -> String {
-        let server_sets = self.score["sets"][if self.server.as_ref().unwrap().name == self.player1.name { 0 } else { 1 }];
-        let receiver_sets = self.score["sets"][if self.server.as_ref().unwrap().name == self.player1.name { 1 } else { 0 }];
+        let server_sets = self.score.sets[self.score.server];
+        let receiver_sets = self.score.sets[1 - self.score.server];
         format!("{}-{}", server_sets, receiver_sets)
     }
 
-    fn log_point(&mut self) -> (bool, bool) {
+    fn log_point(&mut self, winner: &Player) -> (bool, bool) {
         let point_score = self.format_point_score();
         let mut game_over = false;
         let mut set_over = false;
 
-        if self.is_tiebreak {
+        if self.score.tiebreak {
             if self.is_set_over() {
                 set_over = true;
                 game_over = true;
-                let winning_player_index = if self.score["points"][0] > self.score["points"][1] { 0 } else { 1 };
-                self.score.get_mut("games").unwrap()[winning_player_index] += 1;
-                self.score.get_mut("sets").unwrap()[winning_player_index] += 1;
-                self.is_tiebreak = false;
+                let winning_player_index = if self.score.points[0] > self.score.points[1] { 0 } else { 1 };
+                self.score.games[winning_player_index] += 1;
+                self.score.sets[winning_player_index] += 1;
+                self.score.tiebreak = false;
+                self.resolve_forecasts("game_win_prob", winning_player_index == 0);
+                self.resolve_forecasts("set_win_prob", winning_player_index == 0);
+
+                for player in 0..2 {
+                    self.stats.add(player, "tiebreaks_played", 1);
+                }
+                self.stats.add(winning_player_index, "tiebreaks_won", 1);
             }
         } else {
-            if point_score == "GAME" {
+            if let GameState::Won(winning_player_index) = self.rules.game_state(self.score.points) {
                 game_over = true;
-                let winning_player_index = if self.score["points"][0] > self.score["points"][1] { 0 } else { 1 };
-                self.score.get_mut("games").unwrap()[winning_player_index] += 1;
+                self.score.games[winning_player_index] += 1;
+                self.resolve_forecasts("game_win_prob", winning_player_index == 0);
             }
 
             if self.is_set_over() {
                 set_over = true;
-                let winning_player_index = if self.score["games"][0] > self.score["games"][1] { 0 } else { 1 };
-                self.score.get_mut("sets").unwrap()[winning_player_index] += 1;
-            } else if self.score["games"][0] == 6 && self.score["games"][1] == 6 {
-                self.is_tiebreak = true;
-                self.score.insert("points".to_string(), vec![0, 0]);
-                self.tiebreak_server = self.server.clone();
-                self.tiebreak_points = 0;
+                let winning_player_index = if self.score.games[0] > self.score.games[1] { 0 } else { 1 };
+                self.score.sets[winning_player_index] += 1;
+                self.resolve_forecasts("set_win_prob", winning_player_index == 0);
+            } else if self.rules.tiebreak_at(self.sets_won(), [self.score.games[0], self.score.games[1]]).is_some() {
+                self.start_tiebreak();
             }
         }
 
@@ -178,23 +1044,40 @@ impl TennisMatch {
         let set_score = self.format_set_score();
 
         // Calculate probabilities
-        let match_win_prob1 = self.calculate_match_win_probability(&self.player1);
-        let match_win_prob2 = self.calculate_match_win_probability(&self.player2);
-        let set_win_prob1 = self.calculate_set_win_probability(&self.player1);
-        let set_win_prob2 = self.calculate_set_win_probability(&self.player2);
-        let game_win_prob1 = self.calculate_game_win_probability(&self.player1);
-        let game_win_prob2 = self.calculate_game_win_probability(&self.player2);
-        let next_point_prob1 = self.calculate_next_point_win_probability(&self.player1);
-        let next_point_prob2 = self.calculate_next_point_win_probability(&self.player2);
+        let match_win_prob = self.calculate_match_win_probability();
+        let (match_win_prob1, match_win_prob2) = (match_win_prob.player(0), match_win_prob.player(1));
+        let set_win_prob = self.calculate_set_win_probability();
+        let (set_win_prob1, set_win_prob2) = (set_win_prob.player(0), set_win_prob.player(1));
+        let game_win_prob = self.calculate_game_win_probability();
+        let (game_win_prob1, game_win_prob2) = (game_win_prob.player(0), game_win_prob.player(1));
+        let next_point_prob = self.calculate_next_point_win_probability();
+        let (next_point_prob1, next_point_prob2) = (next_point_prob.player(0), next_point_prob.player(1));
         let ace_prob = self.calculate_ace_probability();
+        let serve_prob = self.serve_win_prob();
         let tiebreak_prob = self.calculate_tiebreak_probability();
 
+        self.min_match_win_prob[0] = self.min_match_win_prob[0].min(match_win_prob1);
+        self.min_match_win_prob[1] = self.min_match_win_prob[1].min(match_win_prob2);
+        let leader = if match_win_prob1 > 0.5 { Some(0) } else if match_win_prob1 < 0.5 { Some(1) } else { None };
+        if leader.is_some() {
+            if self.match_leader.is_some() && leader != self.match_leader {
+                self.lead_changes += 1;
+            }
+            self.match_leader = leader;
+        }
+
         let mut point_info = HashMap::new();
         point_info.insert("server".to_string(), serde_json::Value::String(self.server.as_ref().unwrap().name.clone()));
         point_info.insert("receiver".to_string(), serde_json::Value::String(self.receiver.as_ref().unwrap().name.clone()));
+        point_info.insert("winner".to_string(), serde_json::Value::String(winner.name.clone()));
         point_info.insert("point_score".to_string(), serde_json::Value::String(point_score));
         point_info.insert("game_score".to_string(), serde_json::Value::String(game_score));
         point_info.insert("set_score".to_string(), serde_json::Value::String(set_score));
+        point_info.insert("court".to_string(), serde_json::Value::String(self.last_point_court.as_str().to_string()));
+        point_info.insert("serve_direction".to_string(), serde_json::Value::String(self.last_serve_direction.map_or("", |direction| direction.as_str()).to_string()));
+        point_info.insert("return_outcome".to_string(), serde_json::Value::String(self.last_return_outcome.map_or("", |outcome| outcome.as_str()).to_string()));
+        point_info.insert("conduct".to_string(), serde_json::Value::String(self.last_point_conduct.join(";")));
+        point_info.insert("shots".to_string(), self.last_point_shots());
         point_info.insert(format!("{}_match_win_prob", self.player1.name), serde_json::Value::Number(serde_json::Number::from_f64(match_win_prob1).unwrap()));
         point_info.insert(format!("{}_match_win_prob", self.player2.name), serde_json::Value::Number(serde_json::Number::from_f64(match_win_prob2).unwrap()));
         point_info.insert(format!("{}_set_win_prob", self.player1.name), serde_json::Value::Number(serde_json::Number::from_f64(set_win_prob1).unwrap()));
@@ -204,204 +1087,638 @@ impl TennisMatch {
         point_info.insert(format!("{}_next_point_win_prob", self.player1.name), serde_json::Value::Number(serde_json::Number::from_f64(next_point_prob1).unwrap()));
         point_info.insert(format!("{}_next_point_win_prob", self.player2.name), serde_json::Value::Number(serde_json::Number::from_f64(next_point_prob2).unwrap()));
         point_info.insert("next_serve_ace_prob".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(ace_prob).unwrap()));
+        point_info.insert("next_serve_win_prob".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(serve_prob).unwrap()));
         point_info.insert("tiebreak_prob".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(tiebreak_prob).unwrap()));
+        point_info.insert("point_number".to_string(), serde_json::Value::from(self.point_log.len() + 1));
+        point_info.insert("leverage".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(self.last_point_leverage).unwrap()));
+        point_info.insert("pressure_index".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(self.last_point_pressure).unwrap()));
 
         self.point_log.push(point_info);
 
         (game_over, set_over)
     }
 
+    fn start_tiebreak(&mut self) {
+        self.score.tiebreak = true;
+        for challenges_left in &mut self.challenges_left {
+            *challenges_left += self.model.challenges.tiebreak_extra;
+        }
+        self.score.points = [0, 0];
+        self.tiebreak_points = 0;
+    }
+
+    // The shots simulated for the last point, in order: any lets, the serve, then the return if the return
+    // model ran. A let played on is the serve that follows it. Penalty points have no shots.
+    fn last_point_shots(&self) -> serde_json::Value {
+        if self.last_point_penalty {
+            return serde_json::Value::Array(Vec::new());
+        }
+        let serve_outcome = if self.last_point_ace {
+            "ace"
+        } else if self.last_point_double_fault {
+            "double_fault"
+        } else {
+            "in"
+        };
+        let mut shots: Vec<serde_json::Value> = self.last_point_lets.iter().map(|outcome| serde_json::json!({
+            "shot": "let",
+            "direction": null,
+            "outcome": outcome,
+        })).collect();
+        shots.push(serde_json::json!({
+            "shot": "serve",
+            "direction": self.last_serve_direction.map(|direction| direction.as_str()),
+            "outcome": serve_outcome,
+        }));
+        if let Some(outcome) = self.last_return_outcome {
+            shots.push(serde_json::json!({
+                "shot": "return",
+                "direction": null,
+                "outcome": outcome.as_str(),
+            }));
+        }
+        serde_json::Value::Array(shots)
+    }
+
     fn play_point(&mut self) -> Player {
+        self.last_point_conduct.clear();
+        self.last_point_penalty = self.pending_penalty.is_some();
+        let leverage = self.point_importance();
+        let closeness = 1.0 - (2.0 * self.calculate_match_win_probability().player(0) - 1.0).abs();
+        self.cumulative_leverage += leverage;
+        self.last_point_leverage = leverage;
+        self.last_point_pressure = leverage * closeness;
+        if let Some((offender, penalty)) = self.pending_penalty {
+            return self.award_penalty_point(offender, penalty);
+        }
         self.total_shots += 1;
-        let mut rng = rand::thread_rng();
-        let ace_prob = self.calculate_ace_probability();
+        self.record_forecasts();
+        self.serve_direction = self.server.as_ref().unwrap().serve_placement.as_ref().map(|placement| placement.sample_direction(&mut self.rng));
+        let mut ace_prob = self.calculate_ace_probability();
+
+        // Drawn only when lets are modelled, so runs without them keep their random streams
+        self.last_point_lets.clear();
+        let let_prob = self.model.lets.prob.clamp(0.0, 0.99);
+        while let_prob > 0.0 && self.rng.gen::<f64>() < let_prob {
+            if self.model.lets.no_let {
+                self.last_point_lets.push("played");
+                break;
+            }
+            // The serve is taken again
+            self.total_shots += 1;
+            self.last_point_lets.push("replayed");
+        }
 
         let server_name = self.server.as_ref().unwrap().name.clone();
-        let is_server_player1 = server_name == self.player1.name;
+        let is_server_player1 = self.score.server == 0;
+        let court = self.current_court();
+
+        // After a warning, each time violation costs the server their first serve
+        let mut second_serve = false;
+        let time_violation_prob = self.model.conduct.time_violation_prob;
+        if time_violation_prob > 0.0 && self.rng.gen::<f64>() < self.conduct_prob(time_violation_prob) {
+            let server_index = if is_server_player1 { 0 } else { 1 };
+            self.time_violations[server_index] += 1;
+            second_serve = self.time_violations[server_index] > 1;
+            self.stats.add(server_index, "time_violations", 1);
+            self.last_point_conduct.push(format!("{}:time_violation:{}", server_name, if second_serve { "fault" } else { "warning" }));
+        }
+        let mut double_fault_prob = if second_serve { self.model.conduct.second_serve_fault_prob } else { self.server.as_ref().unwrap().double_fault_prob };
+        // Drawn only for servers with a first serve profile, so runs without one keep their random streams
+        if let (Some(first_serve), false) = (&self.server.as_ref().unwrap().first_serve, second_serve) {
+            let in_prob = first_serve.in_prob.clamp(0.01, 1.0);
+            if self.rng.gen::<f64>() < in_prob {
+                ace_prob = (ace_prob / in_prob).min(1.0);
+                double_fault_prob = 0.0;
+            } else {
+                second_serve = true;
+                double_fault_prob = (double_fault_prob / (1.0 - in_prob)).min(1.0);
+            }
+        }
+        self.on_second_serve = second_serve;
+        let server_index = if is_server_player1 { 0 } else { 1 };
+        let break_point = !self.score.tiebreak && self.rules.game_won(self.score.points[1 - server_index] + 1, self.score.points[server_index]);
 
-        let (winner, is_ace, is_double_fault) = if rng.gen::<f64>() < ace_prob {
+        let mut return_outcome = None;
+        let (mut winner, is_ace, is_double_fault) = if !second_serve && self.rng.gen::<f64>() < ace_prob {
             (self.server.as_ref().unwrap().clone(), true, false)
-        } else if rng.gen::<f64>() < self.server.as_ref().unwrap().double_fault_prob {
+        } else if self.rng.gen::<f64>() < double_fault_prob {
             (self.receiver.as_ref().unwrap().clone(), false, true)
-        } else if rng.gen::<f64>() < self.server.as_ref().unwrap().serve_win_prob {
-            (self.server.as_ref().unwrap().clone(), false, false)
         } else {
-            (self.receiver.as_ref().unwrap().clone(), false, false)
+            let (winner, outcome) = self.play_rally();
+            return_outcome = outcome;
+            (winner, false, false)
         };
+        // Only calls ending a rally are reviewed; aces and double faults stand
+        if !is_ace && !is_double_fault {
+            while let Some(review) = self.review_call(&winner, leverage) {
+                match review {
+                    CallReview::Replayed => {
+                        self.total_shots += 1;
+                        (winner, return_outcome) = self.play_rally();
+                    }
+                    CallReview::Awarded => {
+                        winner = if winner.name == self.player1.name { self.player2.clone() } else { self.player1.clone() };
+                        break;
+                    }
+                }
+            }
+        }
 
         // Update stats
         if is_ace {
-            *self.stats.get_mut(&server_name).unwrap().entry("aces".to_string()).or_insert(0) += 1;
+            self.stats.add(server_index, "aces", 1);
         }
         if is_double_fault {
-            *self.stats.get_mut(&server_name).unwrap().entry("double_faults".to_string()).or_insert(0) += 1;
+            self.stats.add(server_index, "double_faults", 1);
+        }
+        if let Some(outcome) = return_outcome {
+            let key = match outcome {
+                ReturnOutcome::Error => "return_errors",
+                ReturnOutcome::Short => "short_returns",
+                ReturnOutcome::Deep => "deep_returns",
+            };
+            self.stats.add(1 - server_index, key, 1);
+        }
+
+        let (served_key, won_key) = match court {
+            Court::Deuce => ("deuce_points_served", "deuce_points_won"),
+            Court::Ad => ("ad_points_served", "ad_points_won"),
+        };
+        let server_won = winner.name == server_name;
+        // A double fault missed the first serve too
+        let (serve_key, serve_won_key) = if second_serve || is_double_fault { ("second_serve_points", "second_serve_points_won") } else { ("first_serves_in", "first_serve_points_won") };
+        for (key, counted) in [(served_key, true), (won_key, server_won), (serve_key, true), (serve_won_key, server_won), ("break_points_faced", break_point), ("break_points_saved", break_point && server_won)] {
+            self.stats.add(server_index, key, counted as i32);
         }
+        let game_point = !self.score.tiebreak && self.rules.game_won(self.score.points[server_index] + 1, self.score.points[1 - server_index]);
+        let deciding_set = self.rules.is_final_set(self.sets_won());
+        self.serve_situations.add(server_index, [break_point, game_point, self.score.tiebreak, deciding_set], server_won, is_ace);
 
         // Update score
         if winner.name == server_name {
-            self.score.get_mut("points").unwrap()[if is_server_player1 { 0 } else { 1 }] += 1;
+            self.score.points[if is_server_player1 { 0 } else { 1 }] += 1;
         } else {
-            self.score.get_mut("points").unwrap()[if is_server_player1 { 1 } else { 0 }] += 1;
+            self.score.points[if is_server_player1 { 1 } else { 0 }] += 1;
         }
 
+        self.resolve_forecasts("next_point_win_prob", winner.name == self.player1.name);
         self.last_point_ace = is_ace;
+        self.last_point_double_fault = is_double_fault;
+        self.last_point_court = court;
+        self.last_serve_direction = self.serve_direction.take();
+        self.last_return_outcome = return_outcome;
 
         if Some(&winner) == self.last_point_winner.as_ref() {
             self.consecutive_points += 1;
+            self.momentum_streak = self.momentum_streak * self.model.momentum.streak_retention() + 1.0;
         } else {
             self.consecutive_points = 1;
+            self.momentum_streak = 1.0;
         }
         self.last_point_winner = Some(winner.clone());
+        self.resume_points_left = (self.resume_points_left - 1).max(0);
+
+        let code_violation_prob = self.model.conduct.code_violation_prob;
+        if code_violation_prob > 0.0 && self.rng.gen::<f64>() < self.conduct_prob(code_violation_prob) {
+            self.code_violation(if winner.name == self.player1.name { 1 } else { 0 });
+        }
 
-        if self.is_tiebreak {
+        self.rotate_tiebreak_server();
+        winner
+    }
+
+    fn rotate_tiebreak_server(&mut self) {
+        if self.score.tiebreak {
             self.tiebreak_points += 1;
             if self.tiebreak_points % 2 == 1 {
                 self.switch_server();
             }
         }
+    }
+
+    // A conduct chance raised on pressure points
+    fn conduct_prob(&self, prob: f64) -> f64 {
+        (prob * (1.0 + self.model.conduct.pressure_factor * self.last_point_pressure)).clamp(0.0, 1.0)
+    }
+
+    // Code violations escalate through the match: a warning, then a point penalty, then a game penalty for each
+    // further one. Penalties are applied from the next point on.
+    fn code_violation(&mut self, offender: usize) {
+        self.code_violations[offender] += 1;
+        let (penalty, label) = match self.code_violations[offender] {
+            1 => (None, "warning"),
+            2 => (Some(Penalty::Point), "point_penalty"),
+            _ => (Some(Penalty::Game), "game_penalty"),
+        };
+        self.pending_penalty = penalty.map(|penalty| (offender, penalty));
+        let name = if offender == 0 { self.player1.name.clone() } else { self.player2.name.clone() };
+        self.stats.add(offender, "code_violations", 1);
+        self.last_point_conduct.push(format!("{}:code_violation:{}", name, label));
+    }
+
+    // A point awarded to the offender's opponent without being played. A game penalty keeps awarding points
+    // until the game, or the tiebreak, is over.
+    fn award_penalty_point(&mut self, offender: usize, penalty: Penalty) -> Player {
+        let winner = if offender == 0 { self.player2.clone() } else { self.player1.clone() };
+        let (_, games_after) = self.sets_and_games_after_point(1 - offender);
+        if penalty == Penalty::Point || games_after != self.score.games {
+            self.pending_penalty = None;
+        }
+        let offender_name = if offender == 0 { self.player1.name.clone() } else { self.player2.name.clone() };
+        self.stats.add(offender, "penalty_points", 1);
+        self.last_point_conduct.push(format!("{}:{}", offender_name, if penalty == Penalty::Point { "point_penalty" } else { "game_penalty" }));
 
+        self.last_point_ace = false;
+        self.last_point_double_fault = false;
+        self.last_point_court = self.current_court();
+        self.last_serve_direction = None;
+        self.last_return_outcome = None;
+        self.last_point_lets.clear();
+        self.score.points[1 - offender] += 1;
+        self.rotate_tiebreak_server();
         winner
     }
 
+    // Plays out a serve that lands in: the return, if the receiver has a return profile, then the rally
+    fn play_rally(&mut self) -> (Player, Option<ReturnOutcome>) {
+        let return_outcome = self.receiver.as_ref().unwrap().return_profile.as_ref().map(|profile| profile.sample_outcome(&mut self.rng));
+        let rally_adjustment = match return_outcome {
+            Some(ReturnOutcome::Short) => self.model.returns.short_return_bonus,
+            Some(ReturnOutcome::Deep) => -self.model.returns.deep_return_penalty,
+            _ => 0.0,
+        };
+        let server = self.server.as_ref().unwrap();
+        let second_serve_penalty = match &server.first_serve {
+            Some(first_serve) if self.on_second_serve => first_serve.second_serve_penalty,
+            _ => 0.0,
+        };
+        if return_outcome == Some(ReturnOutcome::Error) || self.rng.gen::<f64>() < self.serve_win_prob() + rally_adjustment - second_serve_penalty {
+            (self.server.as_ref().unwrap().clone(), return_outcome)
+        } else {
+            (self.receiver.as_ref().unwrap().clone(), return_outcome)
+        }
+    }
+
+    // The call ending the rally may be wrong. The player it went against may challenge it while they have
+    // challenges left; a wrong call is overturned and the rally replayed or the point awarded to them, and a
+    // right one costs them a challenge.
+    fn review_call(&mut self, winner: &Player, leverage: f64) -> Option<CallReview> {
+        let model = self.model.challenges.clone();
+        if model.wrong_call_prob <= 0.0 {
+            return None;
+        }
+        let wrong_call = self.rng.gen::<f64>() < model.wrong_call_prob;
+        let challenger_index = if winner.name == self.player1.name { 1 } else { 0 };
+        let challenge_prob = if wrong_call { model.challenge_wrong_prob } else { model.challenge_right_prob };
+        let challenged = self.challenges_left[challenger_index] > 0 && leverage >= model.min_leverage && self.rng.gen::<f64>() < challenge_prob;
+
+        if !challenged {
+            if wrong_call {
+                self.stats.add(challenger_index, "uncorrected_calls", 1);
+            }
+            return None;
+        }
+        self.stats.add(challenger_index, "challenges", 1);
+        if !wrong_call {
+            self.challenges_left[challenger_index] -= 1;
+            return None;
+        }
+        self.stats.add(challenger_index, "challenges_won", 1);
+        Some(if self.rng.gen::<f64>() < model.replay_prob { CallReview::Replayed } else { CallReview::Awarded })
+    }
+
     fn play_game(&mut self) -> (Player, bool) {
-        if !self.is_tiebreak {
-            self.score.insert("points".to_string(), vec![0, 0]);
+        if self.model.momentum.decay == MomentumDecay::ResetEachGame {
+            self.reset_momentum();
         }
-        self.last_point_winner = None;
-        self.consecutive_points = 0;
         self.last_point_ace = false;
-        self.stats.get_mut(&self.server.as_ref().unwrap().name).unwrap().insert("aces".to_string(), 0);
-        self.stats.get_mut(&self.server.as_ref().unwrap().name).unwrap().insert("double_faults".to_string(), 0);
+        let set = self.score.sets.iter().sum::<i32>() as usize;
+        let server = self.score.server;
+        let tiebreak = self.score.tiebreak;
+        let first_point = self.point_log.len();
 
         loop {
+            let before = markov::state(&self.rules, Perspective::Server.orient(server, self.score.points));
             let winner = self.play_point();
-            let (game_over, set_over) = self.log_point();
+            let winner_index = if winner.name == self.player1.name { 0 } else { 1 };
+            self.set_points_won[winner_index] += 1;
+            let (game_over, set_over) = self.log_point(&winner);
+            if !tiebreak {
+                // A game that ends the set may already have moved the score on to a tiebreak
+                let after = match game_over {
+                    true if winner_index == server => markov::HOLD,
+                    true => markov::BREAK,
+                    false => markov::state(&self.rules, Perspective::Server.orient(server, self.score.points)),
+                };
+                self.score_transitions.add(server, before, after);
+            }
             if game_over || set_over {
-                if !set_over && !self.is_tiebreak {
+                if !tiebreak {
+                    self.stats.add(server, "service_games", 1);
+                    self.stats.add(server, "service_games_held", (winner_index == server) as i32);
+                }
+                self.stats.end_game();
+                self.games_played.push(GameRecord { set, server, winner: winner_index, tiebreak, points: self.point_log.len() - first_point });
+                // Service alternates after every game, into a tiebreak too, which the new server serves first
+                if !set_over {
                     self.switch_server();
+                    self.score.points = [0, 0];
+                    if self.score.tiebreak {
+                        self.tiebreak_server = self.server.clone();
+                    }
                 }
                 return (winner, set_over);
             }
         }
     }
 
+    fn reset_momentum(&mut self) {
+        self.last_point_winner = None;
+        self.consecutive_points = 0;
+        self.momentum_streak = 0.0;
+    }
+
     fn play_set(&mut self) -> Player {
-        let mut set_stats = HashMap::new();
-        set_stats.insert(self.player1.name.clone(), HashMap::new());
-        set_stats.insert(self.player2.name.clone(), HashMap::new());
+
+        if self.model.momentum.decay == MomentumDecay::ResetEachSet {
+            self.reset_momentum();
+        }
+        self.challenges_left = [self.model.challenges.per_set; 2];
+        self.set_points_won = [0, 0];
+        self.set_first_servers.push(self.score.server);
+        // A set played as a tiebreak alone starts with it
+        if !self.score.tiebreak && self.rules.tiebreak_at(self.sets_won(), [self.score.games[0], self.score.games[1]]).is_some() {
+            self.start_tiebreak();
+            self.tiebreak_server = self.server.clone();
+        }
 
         loop {
             let (winner, set_over) = self.play_game();
+            if !set_over {
+                self.check_weather();
+            }
             if set_over {
-                for player_name in [&self.player1.name, &self.player2.name].iter() {
-                    let aces = *self.stats.get(*player_name).unwrap().get("aces").unwrap_or(&0);
-                    let double_faults = *self.stats.get(*player_name).unwrap().get("double_faults").unwrap_or(&0);
-                    
-                    set_stats.get_mut(*player_name).unwrap().insert("aces".to_string(), aces);
-                    set_stats.get_mut(*player_name).unwrap().insert("double_faults".to_string(), double_faults);
-                    
-                    self.stats.get_mut(*player_name).unwrap().insert("aces".to_string(), 0);
-                    self.stats.get_mut(*player_name).unwrap().insert("double_faults".to_string(), 0);
-                }
-                self.set_history.push(set_stats);
-                self.score.insert("games".to_string(), vec![0, 0]);
-                self.score.insert("points".to_string(), vec![0, 0]);
-                self.is_tiebreak = false;
+                self.record_set_result(&winner);
+                self.stats.end_set();
+                self.score.games = [0, 0];
+                self.score.points = [0, 0];
+                self.score.tiebreak = false;
                 self.tiebreak_points = 0;
-                self.switch_server();
+                // After a tiebreak the player who received first in it serves first in the next set, whoever
+                // served its last point; otherwise service alternates as after any game
+                match self.tiebreak_server.take() {
+                    Some(tiebreak_server) if self.games_played.last().is_some_and(|game| game.tiebreak) => {
+                        self.set_server(if tiebreak_server.name == self.player1.name { 1 } else { 0 });
+                    }
+                    _ => self.switch_server(),
+                }
                 return winner;
             }
         }
     }
 
-    fn play_match(&mut self) -> Player {
-        let mut rng = rand::thread_rng();
-        self.server = Some(if rng.gen::<bool>() { self.player1.clone() } else { self.player2.clone() });
-        self.receiver = Some(if self.server.as_ref().unwrap().name == self.player1.name { self.player2.clone() } else { self.player1.clone() });
-
-        while self.score["sets"].iter().max().unwrap() < &((self.best_of / 2) + 1) {
-            let _set_winner = self.play_set();
+    // Credits the set winner with a bagel (to love), breadstick (to 1) or golden set (no point lost), and counts
+    // deciding sets and their tiebreaks
+    fn record_set_result(&mut self, winner: &Player) {
+        let winner_index = if winner.name == self.player1.name { 0 } else { 1 };
+        let loser_games = self.score.games[1 - winner_index];
+        // The sets already include this one
+        if self.score.sets.iter().sum::<i32>() == self.rules.best_of() {
+            let tiebreak = self.stats.get(Scope::Set, 0, "tiebreaks_played") > 0;
+            for player in 0..2 {
+                self.stats.add(player, "deciding_sets_played", 1);
+                self.stats.add(player, "deciding_set_tiebreaks", tiebreak as i32);
+            }
+            self.stats.add(winner_index, "deciding_sets_won", 1);
+        }
+        if self.score.games[winner_index] == self.rules.games_per_set && loser_games <= 1 {
+            self.stats.add(winner_index, if loser_games == 0 { "bagels" } else { "breadsticks" }, 1);
+        }
+        if self.set_points_won[1 - winner_index] == 0 {
+            self.stats.add(winner_index, "golden_sets", 1);
         }
-
-        if self.score["sets"][0] > self.score["sets"][1] { self.player1.clone() } else { self.player2.clone() }
     }
 
-    fn calculate_match_win_probability(&self, player: &Player) -> f64 {
- 
-        let player_sets = self.score["sets"][if player.name == self.player1.name { 0 } else { 1 }];
-        let opponent_sets = self.score["sets"][if player.name == self.player1.name { 1 } else { 0 }];
-        let player_games = self.score["games"][if player.name == self.player1.name { 0 } else { 1 }];
-        let opponent_games = self.score["games"][if player.name == self.player1.name { 1 } else { 0 }];
+    // Rain may suspend play at the end of a game. The match resumes where it stopped, optionally without
+    // momentum and with the servers finding their rhythm again over the first points.
+    fn check_weather(&mut self) {
+        let weather = &self.model.weather;
+        if weather.indoor || weather.suspension_prob <= 0.0 || self.rng.gen::<f64>() >= weather.suspension_prob {
+            return;
+        }
+        if weather.reset_momentum {
+            self.reset_momentum();
+        }
+        self.resume_points_left = self.model.weather.rest_points;
+        for player in 0..2 {
+            self.stats.add(player, "rain_suspensions", 1);
+        }
+    }
+
+    // Tosses for the first serve: the winner elects to serve or receive, or defers the election to the opponent
+    fn coin_toss(&mut self) -> Player {
+        let (winner, loser) = if self.rng.gen::<bool>() { (&self.player1, &self.player2) } else { (&self.player2, &self.player1) };
+        let first_server = match (winner.toss_election, loser.toss_election) {
+            (TossElection::Serve, _) | (TossElection::Defer, TossElection::Receive) => winner.clone(),
+            (TossElection::Receive, _) | (TossElection::Defer, _) => loser.clone(),
+        };
+        self.toss = Some(Toss { winner: winner.name.clone(), election: winner.toss_election, first_server: first_server.name.clone() });
+        first_server
+    }
+
+    fn play_match(&mut self) -> Player {
+        let first_server = if self.coin_toss().name == self.player1.name { 0 } else { 1 };
+        self.set_server(first_server);
+        self.play_on()
+    }
 
-        let base_prob = 0.5 + (player_sets - opponent_sets) as f64 * 0.1;
-        let game_adjustment = (player_games - opponent_games) as f64 * 0.01;
-        (base_prob + game_adjustment).max(0.0).min(1.0)
+    // Puts the match at a given score, to be played out from there with `play_on`
+    fn resume_from(&mut self, state: &what_if::MatchState) {
+        self.score.sets = state.sets;
+        self.score.games = state.games;
+        self.score.points = state.points;
+        self.set_server(state.server);
+        let players = [self.player1.clone(), self.player2.clone()];
+        self.score.tiebreak = state.is_tiebreak(&self.rules);
+        self.tiebreak_points = if self.score.tiebreak { state.points.iter().sum() } else { 0 };
+        // The tiebreak's first server, worked back from the server after the points played
+        let first_server = if ((self.tiebreak_points + 1) / 2) % 2 == 0 { state.server } else { 1 - state.server };
+        self.tiebreak_server = self.score.tiebreak.then(|| players[first_server].clone());
     }
 
-    fn calculate_set_win_probability(&self, player: &Player) -> f64 {
-        let player_games = self.score["games"][if player.name == self.player1.name { 0 } else { 1 }];
-        let opponent_games = self.score["games"][if player.name == self.player1.name { 1 } else { 0 }];
+    // Plays the match out from its current score
+    fn play_on(&mut self) -> Player {
+        while self.score.sets.iter().max().unwrap() < &self.rules.sets_to_win {
+            let _set_winner = self.play_set();
+        }
 
-        let base_prob = 0.5 + (player_games - opponent_games) as f64 * 0.05;
-        base_prob.max(0.0).min(1.0)
+        let winner = if self.score.sets[0] > self.score.sets[1] { self.player1.clone() } else { self.player2.clone() };
+        self.resolve_forecasts("match_win_prob", winner.name == self.player1.name);
+        winner
     }
 
-    fn calculate_game_win_probability(&self, player: &Player) -> f64 {
-        let is_server = self.server.as_ref().unwrap().name == player.name;
-        let player_points = self.score["points"][if is_server { 0 } else { 1 }];
-        let opponent_points = self.score["points"][if is_server { 1 } else { 0 }];
+    fn calculate_match_win_probability(&self) -> WinProbability {
+        WinProbability::new(Perspective::Player(0), self.score.server, Self::match_win_probability_from(self.score.sets, self.score.games))
+    }
 
-        let base_prob = if is_server { self.server.as_ref().unwrap().serve_win_prob } else { 1.0 - self.server.as_ref().unwrap().serve_win_prob };
-        let point_adjustment = (player_points - opponent_points) as f64 * 0.05;
-        (base_prob + point_adjustment).max(0.0).min(1.0)
+    // Player1's match win probability for the given set and game counts
+    fn match_win_probability_from(sets: [i32; 2], games: [i32; 2]) -> f64 {
+        let base_prob = 0.5 + (sets[0] - sets[1]) as f64 * 0.1;
+        let game_adjustment = (games[0] - games[1]) as f64 * 0.01;
+        (base_prob + game_adjustment).clamp(0.0, 1.0)
     }
 
-    fn calculate_next_point_win_probability(&self, player: &Player) -> f64 {
-        let base_prob = if self.server.as_ref().unwrap().name == player.name {
-            self.server.as_ref().unwrap().serve_win_prob
-        } else {
-            1.0 - self.server.as_ref().unwrap().serve_win_prob
-        };
+    // Set and game counts after the next point is won by the player at `winner_index`
+    fn sets_and_games_after_point(&self, winner_index: usize) -> ([i32; 2], [i32; 2]) {
+        let loser_index = 1 - winner_index;
+        let mut sets = [self.score.sets[0], self.score.sets[1]];
+        let mut games = [self.score.games[0], self.score.games[1]];
+        let mut points = [self.score.points[0], self.score.points[1]];
+        points[winner_index] += 1;
+        let point_lead = points[winner_index] - points[loser_index];
+
+        if self.score.tiebreak {
+            let target = self.rules.tiebreak_points(sets);
+            if points[winner_index] >= target && point_lead >= 2 {
+                games[winner_index] += 1;
+                sets[winner_index] += 1;
+            }
+        } else if self.rules.game_state(points) == GameState::Won(winner_index) {
+            games[winner_index] += 1;
+            if self.rules.set_won(games[winner_index], games[loser_index]) {
+                sets[winner_index] += 1;
+            }
+        }
+        (sets, games)
+    }
 
-        let score_diff = self.score["points"][0] - self.score["points"][1];
-        let score_adjustment = if player.name == self.server.as_ref().unwrap().name {
-            0.02 * score_diff as f64
+    // Swing in player1's match win probability between winning and losing the next point
+    fn point_importance(&self) -> f64 {
+        let (sets_if_won, games_if_won) = self.sets_and_games_after_point(0);
+        let (sets_if_lost, games_if_lost) = self.sets_and_games_after_point(1);
+        (Self::match_win_probability_from(sets_if_won, games_if_won) - Self::match_win_probability_from(sets_if_lost, games_if_lost)).abs()
+    }
+
+    fn excitement(&self, winner: &Player) -> ExcitementIndex {
+        let winner_index = if winner.name == self.player1.name { 0 } else { 1 };
+        ExcitementIndex {
+            cumulative_leverage: self.cumulative_leverage,
+            lead_changes: self.lead_changes,
+            winner_min_win_prob: self.min_match_win_prob[winner_index],
+        }
+    }
+
+    fn calculate_set_win_probability(&self) -> WinProbability {
+        WinProbability::new(Perspective::Player(0), self.score.server, Self::set_win_probability_from(self.score.games))
+    }
+
+    // Player1's set win probability for the given game counts
+    fn set_win_probability_from(games: [i32; 2]) -> f64 {
+        0.5 + (games[0] - games[1]) as f64 * 0.05
+    }
+
+    // The server's chance of holding from the point score, winning each point with the serve model's chance at
+    // this score; a tiebreak is forecast by its next point alone
+    fn calculate_game_win_probability(&self) -> WinProbability {
+        let server = self.score.server;
+        let prob = if self.score.tiebreak {
+            self.serve_win_prob()
         } else {
-            -0.02 * score_diff as f64
+            exact::hold_probability(self.serve_win_prob(), Perspective::Server.orient(server, self.score.points), self.rules.no_ad)
         };
+        WinProbability::new(Perspective::Server, server, prob)
+    }
+
+    // Worked out for the server, whose lead in the game and aces and double faults in it move the serve model
+    fn calculate_next_point_win_probability(&self) -> WinProbability {
+        let server = self.score.server;
+        let [server_points, receiver_points] = Perspective::Server.orient(server, self.score.points);
+        let score_adjustment = 0.02 * (server_points - receiver_points) as f64;
 
-        let momentum_adjustment = if Some(player) == self.last_point_winner.as_ref() {
-            (0.01 * self.consecutive_points as f64).min(0.05)
+        let momentum = &self.model.momentum;
+        let momentum_adjustment = if self.last_point_winner.as_ref() == self.server.as_ref() {
+            (momentum.strength * self.momentum_streak).min(momentum.cap)
         } else if self.last_point_winner.is_some() {
-            -(0.01 * self.consecutive_points as f64).min(0.05)
+            -(momentum.strength * self.momentum_streak).min(momentum.cap)
         } else {
             0.0
         };
 
-        let recent_ace_adjustment = if self.stats[&player.name]["aces"] > 0 { 0.03 } else { 0.0 };
-        let recent_df_adjustment = if self.stats[&player.name]["double_faults"] > 0 { -0.03 } else { 0.0 };
+        let recent_ace_adjustment = if self.stats.get(Scope::Game, server, "aces") > 0 { 0.03 } else { 0.0 };
+        let recent_df_adjustment = if self.stats.get(Scope::Game, server, "double_faults") > 0 { -0.03 } else { 0.0 };
+
+        WinProbability::new(Perspective::Server, server, self.serve_win_prob() + score_adjustment + momentum_adjustment + recent_ace_adjustment + recent_df_adjustment)
+    }
+
+    fn serve_win_prob(&self) -> f64 {
+        let server = self.server.as_ref().unwrap();
+        let tiebreak_adjustment = if self.score.tiebreak { server.tiebreak_serve_modifier } else { 0.0 };
+        let court_adjustment = match self.current_court() {
+            Court::Deuce => server.deuce_court_modifier,
+            Court::Ad => server.ad_court_modifier,
+        };
+        let resume_adjustment = if self.resume_points_left > 0 { self.model.weather.resume_serve_modifier } else { 0.0 };
+        let shock_adjustment = self.shock_adjustment(|shock| shock.serve_win_prob);
+        (self.base_serve_prob(|profile| profile.serve_win_prob, server.serve_win_prob) + tiebreak_adjustment + court_adjustment + resume_adjustment + shock_adjustment + self.pressure_adjustment()).clamp(0.0, 1.0)
+    }
+
+    // The sum of `value` over the shocks to the server active on the coming point
+    fn shock_adjustment(&self, value: impl Fn(&Shock) -> f64) -> f64 {
+        let server = &self.server.as_ref().unwrap().name;
+        self.model.shocks.iter().filter(|shock| shock.is_active(server, self.point_log.len() + 1)).map(value).sum()
+    }
+
+    // The server's base probability, taken from the placement model when the server has one
+    fn base_serve_prob(&self, value: impl Fn(&PlacementProfile) -> f64, default: f64) -> f64 {
+        match (&self.server.as_ref().unwrap().serve_placement, self.serve_direction) {
+            (Some(placement), Some(direction)) => value(placement.profile(direction)),
+            (Some(placement), None) => placement.expected(value),
+            (None, _) => default,
+        }
+    }
+
+    // Points alternate courts starting from the deuce side, in games and tiebreaks alike
+    fn current_court(&self) -> Court {
+        if self.score.points.iter().sum::<i32>() % 2 == 0 { Court::Deuce } else { Court::Ad }
+    }
 
-        (base_prob + score_adjustment + momentum_adjustment + recent_ace_adjustment + recent_df_adjustment).max(0.0).min(1.0)
+    fn pressure_adjustment(&self) -> f64 {
+        if self.score.tiebreak {
+            return 0.0;
+        }
+        let pressure = &self.model.pressure;
+        let mut adjustment = self.pressure_points[markov::state(&self.rules, Perspective::Server.orient(self.score.server, self.score.points))];
+
+        let [server_games, receiver_games] = Perspective::Server.orient(self.score.server, self.score.games);
+        if server_games >= self.rules.games_per_set - 1 && server_games > receiver_games {
+            adjustment += pressure.serving_for_set;
+            if Perspective::Server.orient(self.score.server, self.score.sets)[0] == self.rules.sets_to_win - 1 {
+                adjustment += pressure.serving_for_match;
+            }
+        }
+        adjustment
     }
 
     fn calculate_ace_probability(&self) -> f64 {
-        let base_prob = self.server.as_ref().unwrap().ace_prob;
-        let score_diff = self.score["points"][0] - self.score["points"][1];
-        let score_adjustment = 0.01 * score_diff as f64;
+        let base_prob = self.base_serve_prob(|profile| profile.ace_prob, self.server.as_ref().unwrap().ace_prob);
+        let [server_points, receiver_points] = Perspective::Server.orient(self.score.server, self.score.points);
+        let score_adjustment = 0.01 * (server_points - receiver_points) as f64;
 
+        let momentum = &self.model.momentum;
         let momentum_adjustment = if Some(self.server.as_ref().unwrap()) == self.last_point_winner.as_ref() {
-            (0.005 * self.consecutive_points as f64).min(0.02)
+            (momentum.ace_strength * self.momentum_streak).min(momentum.ace_cap)
         } else {
             0.0
         };
 
         let recent_ace_adjustment = if self.last_point_ace { 0.02 } else { 0.0 };
 
-        (base_prob + score_adjustment + momentum_adjustment + recent_ace_adjustment).max(0.0).min(0.3)
+        (base_prob + score_adjustment + momentum_adjustment + recent_ace_adjustment + self.shock_adjustment(|shock| shock.ace_prob)).clamp(0.0, 0.3)
     }
 
     fn calculate_tiebreak_probability(&self) -> f64 {
-        let games_sum: i32 = self.score["games"].iter().sum();
+        let games_sum: i32 = self.score.games.iter().sum();
         match games_sum {
             0..=9 => 0.1,
             10 => 0.2,
@@ -434,185 +1751,1459 @@ fn simulate_single_match(player1: Player, player2: Player, best_of: i32, grand_s
 }
 */
 
-fn simulate_batch(player1: Player, player2: Player, best_of: i32, grand_slam: bool, batch_size: usize, save_logs: bool, filename: &str) -> (HashMap<String, i32>, i32, HashMap<String, i32>, HashMap<String, i32>) {
-    let mut match_wins = HashMap::new();
-    match_wins.insert(player1.name.clone(), 0);
-    match_wins.insert(player2.name.clone(), 0);
-    let mut total_shots = 0;
-    let mut all_point_logs = Vec::new();
-    let mut total_aces = HashMap::new();
-    total_aces.insert(player1.name.clone(), 0);
-    total_aces.insert(player2.name.clone(), 0);
-    let mut total_double_faults = HashMap::new();
-    total_double_faults.insert(player1.name.clone(), 0);
-    total_double_faults.insert(player2.name.clone(), 0);
-
-    for _ in 0..batch_size {
-        let mut match_sim = TennisMatch::new(player1.clone(), player2.clone(), best_of, grand_slam);
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum LogFormat {
+    Csv,
+    // One Parquet file per logged batch, with the shots of each point kept as a nested list
+    Parquet,
+}
+
+// How finely the log records a match: a row per point, or a row per game, about six times fewer
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum LogGranularity {
+    #[default]
+    Point,
+    Game,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct SimulationConfig {
+    player1: Player,
+    player2: Player,
+    best_of: i32,
+    grand_slam: bool,
+    // Custom scoring rules; when set they decide the format, best_of is taken from them and grand_slam is ignored
+    scoring: Option<scoring::ScoringRules>,
+    // How umpire calls are written in inspect and live output
+    notation: announce::Notation,
+    model: ModelConfig,
+    num_simulations: usize,
+    max_workers: usize,
+    batch_size: usize,
+    log_interval: usize,
+    log_format: LogFormat,
+    log_granularity: LogGranularity,
+    // Write a row per match of every batch, in `log_format`, whether or not its points are logged
+    match_log: bool,
+    // Per-match metrics (see `metrics::METRICS`) to collect and summarize
+    metrics: Vec<String>,
+    prices: odds::PriceSeriesConfig,
+    // The race-to-N-games market: the games a player has to reach first in a set
+    race_to_games: i32,
+    // How many of the most likely set-by-set scores the report lists
+    score_paths: usize,
+    elo: elo::EloConfig,
+    // Master seed every match's random stream is derived from; random unless set
+    seed: u64,
+    // When set, the matchup is simulated once per surface, or per scenario, and the runs are compared
+    surfaces: Vec<surface::Surface>,
+    scenarios: Vec<scenario::Scenario>,
+    // Where the CSV point log and match summary are written instead of the run directory, e.g. to collect
+    // several runs in one file (see `LogPolicy`); a relative path is taken from the working directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    point_log_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_log_path: Option<PathBuf>,
+    // Directory the point log and calibration curves are written to; set per run, not from the config file
+    #[serde(skip)]
+    run_dir: PathBuf,
+    // Report tables without borders, for piping; set per run by --plain
+    #[serde(skip)]
+    plain: bool,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            player1: Player {
+                name: "Federer".to_string(),
+                serve_win_prob: 0.65,
+                ace_prob: 0.10,
+                double_fault_prob: 0.05,
+                tiebreak_serve_modifier: 0.0,
+                deuce_court_modifier: 0.0,
+                ad_court_modifier: 0.0,
+                serve_placement: None,
+                return_profile: None,
+                first_serve: None,
+                toss_election: TossElection::Serve,
+                elo: None,
+            },
+            player2: Player {
+                name: "Nadal".to_string(),
+                serve_win_prob: 0.62,
+                ace_prob: 0.08,
+                double_fault_prob: 0.04,
+                tiebreak_serve_modifier: 0.0,
+                deuce_court_modifier: 0.0,
+                ad_court_modifier: 0.0,
+                serve_placement: None,
+                return_profile: None,
+                first_serve: None,
+                toss_election: TossElection::Serve,
+                elo: None,
+            },
+            best_of: 5,
+            grand_slam: true,
+            scoring: None,
+            notation: announce::Notation::default(),
+            model: ModelConfig::default(),
+            num_simulations: 10000,
+            max_workers: 10,
+            batch_size: 10,
+            log_interval: 10000,
+            log_format: LogFormat::Csv,
+            log_granularity: LogGranularity::Point,
+            match_log: false,
+            metrics: Vec::new(),
+            prices: odds::PriceSeriesConfig::default(),
+            race_to_games: 3,
+            score_paths: 10,
+            elo: elo::EloConfig::default(),
+            seed: rand::random(),
+            surfaces: Vec::new(),
+            scenarios: Vec::new(),
+            point_log_path: None,
+            match_log_path: None,
+            run_dir: PathBuf::new(),
+            plain: false,
+        }
+    }
+}
+
+impl SimulationConfig {
+    // Any field missing from the file keeps its default value
+    fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        let mut config: SimulationConfig = serde_json::from_str(&contents).map_err(|e| format!("invalid config {}: {}", path, e))?;
+        config.apply_scoring()?;
+        if let Some(error) = metrics::unknown(&config.metrics) {
+            return Err(error);
+        }
+        if config.log_format == LogFormat::Parquet && !cfg!(feature = "parquet") {
+            return Err("log_format \"parquet\" requires building with --features parquet".to_string());
+        }
+        Ok(config)
+    }
+
+    // Where the run's CSV point log is written
+    fn point_log_file(&self) -> PathBuf {
+        self.point_log_path.clone().unwrap_or_else(|| self.run_dir.join(CSV_POINT_LOG))
+    }
+
+    // Where the run's CSV match summary is written
+    fn match_log_file(&self) -> PathBuf {
+        self.match_log_path.clone().unwrap_or_else(|| self.run_dir.join(CSV_MATCH_LOG))
+    }
+
+    // Rejects custom scoring rules that contradict themselves, and makes best_of agree with them
+    fn apply_scoring(&mut self) -> Result<(), String> {
+        if let Some(rules) = &self.scoring {
+            let errors = rules.errors();
+            if !errors.is_empty() {
+                return Err(errors.join("; "));
+            }
+            self.best_of = rules.best_of();
+        }
+        Ok(())
+    }
+
+    fn scoring_rules(&self) -> scoring::ScoringRules {
+        self.scoring.clone().unwrap_or_else(|| scoring::ScoringRules::standard(self.best_of, self.grand_slam))
+    }
+}
+
+// Bumped whenever a column, table or file layout of the outputs changes incompatibly
+const OUTPUT_SCHEMA_VERSION: u32 = 10;
+
+// Identifies the run an output artifact came from, so pipelines can detect incompatible
+// changes and reproduce the run
+struct RunMetadata {
+    schema_version: u32,
+    crate_version: &'static str,
+    // FNV-1a hash of the configuration without its seed, as 16 hex digits
+    config_hash: String,
+    seed: u64,
+    // FNV-1a hash of the configuration with its seed and of the crate version: runs with the same run hash
+    // produce the same results
+    run_hash: String,
+}
+
+impl RunMetadata {
+    fn new(config: &SimulationConfig) -> Self {
+        let crate_version = env!("CARGO_PKG_VERSION");
+        let mut value = serde_json::to_value(config).unwrap();
+        // Where the logs go has no bearing on the results
+        for key in ["point_log_path", "match_log_path"] {
+            value.as_object_mut().unwrap().remove(key);
+        }
+        // serde_json objects are sorted by key, so the text is stable across runs
+        let run_hash = fnv1a(format!("{} {}", value, crate_version).bytes());
+        value.as_object_mut().unwrap().remove("seed");
+        let hash = fnv1a(value.to_string().bytes());
+        RunMetadata {
+            schema_version: OUTPUT_SCHEMA_VERSION,
+            crate_version,
+            config_hash: format!("{:016x}", hash),
+            seed: config.seed,
+            run_hash: format!("{:016x}", run_hash),
+        }
+    }
+
+    fn pairs(&self) -> [(&'static str, String); 5] {
+        [
+            ("schema_version", self.schema_version.to_string()),
+            ("crate_version", self.crate_version.to_string()),
+            ("config_hash", self.config_hash.clone()),
+            ("seed", self.seed.to_string()),
+            ("run_hash", self.run_hash.clone()),
+        ]
+    }
+
+    // `# key=value ...` line put before the header of CSV artifacts
+    fn csv_comment(&self) -> String {
+        let pairs: Vec<String> = self.pairs().iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        format!("# {}", pairs.join(" "))
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "schema_version": self.schema_version,
+            "crate_version": self.crate_version,
+            "config_hash": self.config_hash,
+            "seed": self.seed,
+            "run_hash": self.run_hash,
+        })
+    }
+}
+
+// FNV-1a hash of a run's counts, as 16 hex digits: match wins, shots, stats and markets, which a run reproduces
+// exactly whatever its thread count or interruptions. The floating-point forecast sums are left out, since
+// they are merged in completion order.
+fn results_hash(report: &SimulationReport) -> String {
+    let counts = serde_json::json!({
+        "match_wins": report.match_wins,
+        "total_shots": report.total_shots,
+        "stats": report.stats,
+        "markets": report.markets,
+    });
+    format!("{:016x}", fnv1a(counts.to_string().bytes()))
+}
+
+// Seed of match `match_number` of a run: a SplitMix64 step over the master seed and the match number,
+// so each match has its own stream whichever batch or thread plays it
+fn match_seed(master_seed: u64, match_number: usize) -> u64 {
+    let mut z = master_seed.wrapping_add((match_number as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Lowercase letters and digits of a player name, with any run of other characters turned into one space,
+// so "Djokovic, N." and "djokovic n" compare equal
+fn normalize_name(name: &str) -> String {
+    let mapped: String = name.chars().map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' }).collect();
+    mapped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Levenshtein distance in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + (a_char != *b_char) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Edit distance from a query to a normalized player name, or to any single word of it
+fn name_distance(candidate: &str, query: &str) -> usize {
+    candidate.split(' ').map(|word| edit_distance(word, query)).fold(edit_distance(candidate, query), usize::min)
+}
+
+// Resolves a player by exact name, then case- and punctuation-insensitively, then by a part of the name
+// ("Djokovic" for "Djokovic, N.") and finally allowing a typo or two. A unique match is used with a note
+// on stderr; several matches, or none, are an error naming the closest players.
+fn find_player<'a>(players: &'a [Player], name: &str) -> Result<&'a Player, String> {
+    if let Some(player) = players.iter().find(|player| player.name == name) {
+        return Ok(player);
+    }
+
+    let query = normalize_name(name);
+    let normalized: Vec<(String, &Player)> = players.iter().map(|player| (normalize_name(&player.name), player)).collect();
+    let max_typos = (query.chars().count() / 4).max(1);
+    let tiers: [Vec<&Player>; 3] = [
+        normalized.iter().filter(|(candidate, _)| *candidate == query).map(|(_, player)| *player).collect(),
+        normalized.iter().filter(|(candidate, _)| !query.is_empty() && candidate.contains(&query)).map(|(_, player)| *player).collect(),
+        normalized.iter().filter(|(candidate, _)| name_distance(candidate, &query) <= max_typos).map(|(_, player)| *player).collect(),
+    ];
+    for matches in tiers {
+        match matches.as_slice() {
+            [] => continue,
+            [player] => {
+                log::warn!("player '{}' resolved to '{}'", name, player.name);
+                return Ok(player);
+            }
+            _ => {
+                let names: Vec<String> = matches.iter().map(|player| format!("'{}'", player.name)).collect();
+                return Err(format!("player '{}' is ambiguous; did you mean {}?", name, names.join(" or ")));
+            }
+        }
+    }
+
+    let mut closest: Vec<(usize, &Player)> = normalized.iter().map(|(candidate, player)| (name_distance(candidate, &query), *player)).collect();
+    closest.sort_by_key(|(distance, _)| *distance);
+    let suggestions: Vec<String> = closest.iter()
+        .take(3)
+        .filter(|(distance, _)| *distance <= query.chars().count() / 2)
+        .map(|(_, player)| format!("'{}'", player.name))
+        .collect();
+    if suggestions.is_empty() {
+        Err(format!("unknown player '{}'", name))
+    } else {
+        Err(format!("unknown player '{}'; did you mean {}?", name, suggestions.join(" or ")))
+    }
+}
+
+fn load_players(path: &str) -> Result<Vec<Player>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("invalid players file {}: {}", path, e))
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct BatchResult {
+    match_wins: HashMap<String, i32>,
+    total_shots: i32,
+    // Player name -> stat name (see SET_STAT_KEYS) -> total over all matches
+    stats: HashMap<String, HashMap<String, i32>>,
+    excitement: Vec<ExcitementIndex>,
+    forecast_scores: HashMap<String, ForecastScore>,
+    #[serde(default)]
+    markets: MarketCounts,
+    #[serde(default)]
+    transitions: markov::ScoreTransitions,
+    #[serde(default)]
+    situations: situations::ServeSituations,
+    // Metric name -> its value in each match
+    #[serde(default)]
+    metrics: HashMap<String, Vec<f64>>,
+    // Points of the batch with their prices, or its games with `log_granularity: "game"`, only kept when the
+    // batch is to be logged
+    #[serde(skip)]
+    point_log: Vec<HashMap<String, serde_json::Value>>,
+    // A row per match of the batch with `match_log` set
+    #[serde(skip)]
+    match_log: Vec<HashMap<String, serde_json::Value>>,
+}
+
+impl BatchResult {
+    fn new(player1: &Player, player2: &Player) -> Self {
+        let mut match_wins = HashMap::new();
+        match_wins.insert(player1.name.clone(), 0);
+        match_wins.insert(player2.name.clone(), 0);
+
+        let mut stats = HashMap::new();
+        for player in [player1, player2] {
+            let player_stats: HashMap<String, i32> = SET_STAT_KEYS.iter().map(|key| (key.to_string(), 0)).collect();
+            stats.insert(player.name.clone(), player_stats);
+        }
+
+        BatchResult {
+            match_wins,
+            total_shots: 0,
+            stats,
+            excitement: Vec::new(),
+            forecast_scores: HashMap::new(),
+            markets: MarketCounts::default(),
+            transitions: markov::ScoreTransitions::default(),
+            situations: situations::ServeSituations::default(),
+            metrics: HashMap::new(),
+            point_log: Vec::new(),
+            match_log: Vec::new(),
+        }
+    }
+
+    fn merge(&mut self, other: BatchResult) {
+        for (player, wins) in other.match_wins {
+            *self.match_wins.entry(player).or_insert(0) += wins;
+        }
+        self.total_shots += other.total_shots;
+        for (player, player_stats) in other.stats {
+            let totals = self.stats.entry(player).or_default();
+            for (key, value) in player_stats {
+                *totals.entry(key).or_insert(0) += value;
+            }
+        }
+        self.excitement.extend(other.excitement);
+        self.markets.merge(&other.markets);
+        self.transitions.merge(&other.transitions);
+        self.situations.merge(&other.situations);
+        for (name, values) in other.metrics {
+            self.metrics.entry(name).or_default().extend(values);
+        }
+        for (model, score) in &other.forecast_scores {
+            self.forecast_scores.entry(model.clone()).or_default().merge(score);
+        }
+    }
+}
+
+struct SimulationReport {
+    match_wins: HashMap<String, i32>,
+    total_shots: i32,
+    execution_time: u128,
+    stats: HashMap<String, HashMap<String, i32>>,
+    excitement: Vec<ExcitementIndex>,
+    forecast_scores: HashMap<String, ForecastScore>,
+    markets: MarketCounts,
+    transitions: markov::ScoreTransitions,
+    situations: situations::ServeSituations,
+    metrics: HashMap<String, Vec<f64>>,
+}
+
+impl SimulationReport {
+    fn win_probability(&self, player_name: &str) -> f64 {
+        let matches: i32 = self.match_wins.values().sum();
+        *self.match_wins.get(player_name).unwrap_or(&0) as f64 / matches.max(1) as f64
+    }
+
+    fn stat(&self, player_name: &str, key: &str) -> i32 {
+        *self.stats.get(player_name).and_then(|player_stats| player_stats.get(key)).unwrap_or(&0)
+    }
+}
+
+fn point_log_columns(player1: &str, player2: &str) -> Vec<String> {
+    let mut columns: Vec<String> = ["server", "receiver", "point_score", "game_score", "set_score", "court", "serve_direction", "return_outcome", "conduct"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+    for prob in ["match_win_prob", "set_win_prob", "game_win_prob", "next_point_win_prob"] {
+        columns.push(format!("{}_{}", player1, prob));
+        columns.push(format!("{}_{}", player2, prob));
+    }
+    columns.push("next_serve_ace_prob".to_string());
+    columns.push("next_serve_win_prob".to_string());
+    columns.push("tiebreak_prob".to_string());
+    for column in ["point_number", "leverage", "pressure_index"] {
+        columns.push(column.to_string());
+    }
+    columns.push(format!("{}_price", player1));
+    columns.push(format!("{}_price", player2));
+    columns
+}
+
+fn game_log_columns() -> Vec<String> {
+    ["game_number", "set_number", "server", "receiver", "winner", "break", "tiebreak", "points_played", "score_progression", "game_score", "set_score"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect()
+}
+
+// One row per game of a finished match, from its point log: the server (a tiebreak's first server), whether the
+// game was a break, the point scores in order and the game and set scores after it, all server first
+fn game_log(match_sim: &TennisMatch) -> Vec<HashMap<String, serde_json::Value>> {
+    let names = [&match_sim.player1.name, &match_sim.player2.name];
+    let mut points = match_sim.point_log.iter();
+    match_sim.games_played.iter().enumerate()
+        .map(|(index, game)| {
+            let game_points: Vec<_> = points.by_ref().take(game.points).collect();
+            // The log's scores are given for the server of each point, who changes during a tiebreak
+            let server_first = |point: &HashMap<String, serde_json::Value>, key: &str| {
+                let score = point[key].as_str().unwrap_or("");
+                match score.split_once('-') {
+                    Some((a, b)) if point["server"].as_str() != Some(names[game.server].as_str()) => format!("{}-{}", b, a),
+                    _ => score.to_string(),
+                }
+            };
+            let progression: Vec<String> = game_points.iter().map(|point| server_first(point, "point_score")).collect();
+            let after = |key: &str| game_points.last().map_or("0-0".to_string(), |point| server_first(point, key));
+            [
+                ("game_number", serde_json::json!(index + 1)),
+                ("set_number", serde_json::json!(game.set + 1)),
+                ("server", serde_json::json!(names[game.server])),
+                ("receiver", serde_json::json!(names[1 - game.server])),
+                ("winner", serde_json::json!(names[game.winner])),
+                ("break", serde_json::json!((!game.tiebreak && game.winner != game.server) as i32)),
+                ("tiebreak", serde_json::json!(game.tiebreak as i32)),
+                ("points_played", serde_json::json!(game.points)),
+                ("score_progression", serde_json::json!(progression.join(" "))),
+                ("game_score", serde_json::json!(after("game_score"))),
+                ("set_score", serde_json::json!(after("set_score"))),
+            ]
+            .into_iter()
+            .map(|(column, value)| (column.to_string(), value))
+            .collect()
+        })
+        .collect()
+}
+
+fn match_log_columns(player1: &str, player2: &str) -> Vec<String> {
+    let mut columns: Vec<String> = ["match", "winner", "set_scores", "sets_played", "total_games", "tiebreaks"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+    for stat in ["aces", "double_faults"] {
+        columns.push(format!("{}_{}", player1, stat));
+        columns.push(format!("{}_{}", player2, stat));
+    }
+    columns.push("points".to_string());
+    columns.push("shots".to_string());
+    columns
+}
+
+// The match-level row of match `match_number`: its set scores (player1 first), games, tiebreaks, aces and
+// double faults, and its length in points and shots, the simulation having no clock
+fn match_log_row(match_sim: &TennisMatch, match_number: usize, winner: &Player) -> HashMap<String, serde_json::Value> {
+    let names = [&match_sim.player1.name, &match_sim.player2.name];
+    let sets_played = match_sim.games_played.last().map_or(0, |game| game.set + 1);
+    let set_scores: Vec<String> = (0..sets_played)
+        .map(|set| {
+            let games = match_sim.games_played.iter().filter(|game| game.set == set);
+            let won = games.fold([0, 0], |mut won, game| {
+                won[game.winner] += 1;
+                won
+            });
+            format!("{}-{}", won[0], won[1])
+        })
+        .collect();
+    let mut row: HashMap<String, serde_json::Value> = [
+        ("match", serde_json::json!(match_number)),
+        ("winner", serde_json::json!(winner.name)),
+        ("set_scores", serde_json::json!(set_scores.join(" "))),
+        ("sets_played", serde_json::json!(sets_played)),
+        ("total_games", serde_json::json!(match_sim.games_played.len())),
+        ("tiebreaks", serde_json::json!(match_sim.games_played.iter().filter(|game| game.tiebreak).count())),
+        ("points", serde_json::json!(match_sim.point_log.len())),
+        ("shots", serde_json::json!(match_sim.total_shots)),
+    ]
+    .into_iter()
+    .map(|(column, value)| (column.to_string(), value))
+    .collect();
+    for (player, name) in names.iter().enumerate() {
+        row.insert(format!("{}_aces", name), serde_json::json!(match_sim.match_stat(player, "aces")));
+        row.insert(format!("{}_double_faults", name), serde_json::json!(match_sim.match_stat(player, "double_faults")));
+    }
+    row
+}
+
+// Name of the CSV match log within the run directory
+const CSV_MATCH_LOG: &str = "match_summary.csv";
+
+// Name of the CSV point log within the run directory
+const CSV_POINT_LOG: &str = "match_log_parallel.csv";
+
+// A CSV field, quoted when it holds a comma, a quote or a line break, with its quotes doubled
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+// The header line of a CSV log with `columns`, which can hold player names
+fn csv_header(columns: &[String]) -> String {
+    columns.iter().map(|column| csv_field(column)).collect::<Vec<_>>().join(",")
+}
+
+fn write_point_log_csv(path: &Path, columns: &[String], points: &[HashMap<String, serde_json::Value>], metadata: &RunMetadata) -> std::io::Result<()> {
+    let mut rows = String::new();
+    for point in points {
+        let row: Vec<String> = columns.iter()
+            .map(|column| match &point[column] {
+                serde_json::Value::String(value) => csv_field(value).into_owned(),
+                value => value.as_f64().unwrap_or(0.0).to_string(),
+            })
+            .collect();
+        rows.push_str(&row.join(","));
+        rows.push('\n');
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", metadata.csv_comment())?;
+        writeln!(file, "{}", csv_header(columns))?;
+    }
+    file.write_all(rows.as_bytes())
+}
+
+// Simulates matches `first_match..first_match + batch_size` of a run, numbered from 1, on `match_sim`, a match
+// of the run's players reset for each one so its buffers are allocated once per worker rather than per match
+fn simulate_batch(config: &SimulationConfig, match_sim: &mut TennisMatch, first_match: usize, batch_size: usize, save_logs: bool, sinks: &[&dyn ResultSink]) -> BatchResult {
+    let player1 = &config.player1;
+    let player2 = &config.player2;
+    let mut result = BatchResult::new(player1, player2);
+    let wants_matches = sinks.iter().any(|sink| sink.wants_matches());
+
+    for match_number in first_match..first_match + batch_size {
+        let seed = match_seed(config.seed, match_number);
+        match_sim.reset(seed);
         let winner = match_sim.play_match();
-        *match_wins.get_mut(&winner.name).unwrap() += 1;
-        total_shots += match_sim.total_shots;
-        all_point_logs.extend(match_sim.point_log);
-
-        for player_name in &[player1.name.as_str(), player2.name.as_str()] {
-            let aces_sum: i32 = match_sim.set_history.iter()
-                .map(|set_stats| set_stats.get(*player_name)
-                    .and_then(|player_stats| player_stats.get("aces"))
-                    .unwrap_or(&0))
-                .sum();
-            *total_aces.get_mut(*player_name).unwrap() += aces_sum;
-
-            let double_faults_sum: i32 = match_sim.set_history.iter()
-                .map(|set_stats| set_stats.get(*player_name)
-                    .and_then(|player_stats| player_stats.get("double_faults"))
-                    .unwrap_or(&0))
-                .sum();
-            *total_double_faults.get_mut(*player_name).unwrap() += double_faults_sum;
-        }
-    }
-
-    if save_logs {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .append(true)
-            .open(filename)
-            .unwrap();
-
-        if file.metadata().unwrap().len() == 0 {
-            writeln!(file, "server,receiver,point_score,game_score,set_score,{0}_match_win_prob,{1}_match_win_prob,{0}_set_win_prob,{1}_set_win_prob,{0}_game_win_prob,{1}_game_win_prob,{0}_next_point_win_prob,{1}_next_point_win_prob,next_serve_ace_prob,tiebreak_prob",
-                player1.name, player2.name)
-                .unwrap();
-        }
-
-        for point in all_point_logs {
-            writeln!(file, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
-                point["server"].as_str().unwrap_or(""),
-                point["receiver"].as_str().unwrap_or(""),
-                point["point_score"].as_str().unwrap_or(""),
-                point["game_score"].as_str().unwrap_or(""),
-                point["set_score"].as_str().unwrap_or(""),
-                point[&format!("{}_match_win_prob", player1.name)].as_f64().unwrap_or(0.0),
-                point[&format!("{}_match_win_prob", player2.name)].as_f64().unwrap_or(0.0),
-                point[&format!("{}_set_win_prob", player1.name)].as_f64().unwrap_or(0.0),
-                point[&format!("{}_set_win_prob", player2.name)].as_f64().unwrap_or(0.0),
-                point[&format!("{}_game_win_prob", player1.name)].as_f64().unwrap_or(0.0),
-                point[&format!("{}_game_win_prob", player2.name)].as_f64().unwrap_or(0.0),
-                point[&format!("{}_next_point_win_prob", player1.name)].as_f64().unwrap_or(0.0),
-                point[&format!("{}_next_point_win_prob", player2.name)].as_f64().unwrap_or(0.0),
-                point["next_serve_ace_prob"].as_f64().unwrap_or(0.0),
-                point["tiebreak_prob"].as_f64().unwrap_or(0.0)
-            ).unwrap();
-        }
-    }
-
-    (match_wins, total_shots, total_aces, total_double_faults)
-}
-
-fn simulate_match_parallel(player1: Player, player2: Player, best_of: i32, grand_slam: bool, num_simulations: usize, _max_workers: usize, batch_size: usize, log_interval: usize) -> (HashMap<String, i32>, i32, u128, HashMap<String, i32>, HashMap<String, i32>) {
-    let match_wins = Arc::new(Mutex::new(HashMap::new()));
-    match_wins.lock().unwrap().insert(player1.name.clone(), 0);
-    match_wins.lock().unwrap().insert(player2.name.clone(), 0);
-    let total_shots = Arc::new(Mutex::new(0));
-    let total_aces = Arc::new(Mutex::new(HashMap::new()));
-    total_aces.lock().unwrap().insert(player1.name.clone(), 0);
-    total_aces.lock().unwrap().insert(player2.name.clone(), 0);
-    let total_double_faults = Arc::new(Mutex::new(HashMap::new()));
-    total_double_faults.lock().unwrap().insert(player1.name.clone(), 0);
-    total_double_faults.lock().unwrap().insert(player2.name.clone(), 0);
+        let excitement = match_sim.excitement(&winner);
+        *result.match_wins.get_mut(&winner.name).unwrap() += 1;
+        result.markets.add_match(&match_sim.games_played, [0, 1].map(|player| match_sim.match_stat(player, "aces")), config.race_to_games);
+        result.transitions.merge(&match_sim.score_transitions);
+        result.situations.merge(&match_sim.serve_situations);
+        result.total_shots += match_sim.total_shots;
+        for (model, score) in &match_sim.forecast_scores {
+            result.forecast_scores.entry(model.clone()).or_default().merge(score);
+        }
 
-    let start_time = Instant::now();
+        for (player, player_name) in [&player1.name, &player2.name].into_iter().enumerate() {
+            for key in SET_STAT_KEYS {
+                *result.stats.get_mut(player_name).unwrap().get_mut(key).unwrap() += match_sim.match_stat(player, key);
+            }
+        }
+
+        if save_logs || wants_matches {
+            odds::add_price_series(&mut match_sim.point_log, player1, player2, &config.prices, &mut match_sim.rng);
+        }
+        if wants_matches {
+            let match_stats: HashMap<&String, HashMap<&str, i32>> = [&player1.name, &player2.name].into_iter().enumerate()
+                .map(|(player, name)| (name, SET_STAT_KEYS.iter().map(|key| (*key, match_sim.match_stat(player, key))).collect()))
+                .collect();
+            let summary = serde_json::json!({
+                "match": match_number,
+                "seed": seed,
+                "player1": player1.name,
+                "player2": player2.name,
+                "winner": winner.name,
+                "sets": match_sim.score.sets,
+                "points": match_sim.point_log.len(),
+                "shots": match_sim.total_shots,
+                "stats": match_stats,
+                "cumulative_leverage": excitement.cumulative_leverage,
+                "lead_changes": excitement.lead_changes,
+                "winner_min_win_prob": excitement.winner_min_win_prob,
+                "toss_winner": match_sim.toss.as_ref().map(|toss| toss.winner.clone()),
+                "toss_election": match_sim.toss.as_ref().map(|toss| toss.election.as_str()),
+                "first_server": match_sim.toss.as_ref().map(|toss| toss.first_server.clone()),
+                "box_score": box_score::BoxScore::from_match(match_sim),
+                "serving_first": match_sim.set_first_servers.iter().map(|server| if *server == 0 { &player1.name } else { &player2.name }).collect::<Vec<_>>(),
+            });
+            for sink in sinks {
+                sink.on_match(&summary, &match_sim.point_log);
+            }
+        }
+        result.excitement.push(excitement);
+        for name in &config.metrics {
+            let value = metrics::find(name).map_or(0.0, |metric| (metric.extract)(match_sim));
+            result.metrics.entry(name.clone()).or_default().push(value);
+        }
+        if config.match_log {
+            result.match_log.push(match_log_row(match_sim, match_number, &winner));
+        }
+        if save_logs {
+            match config.log_granularity {
+                LogGranularity::Point => result.point_log.append(&mut match_sim.point_log),
+                LogGranularity::Game => result.point_log.extend(game_log(match_sim)),
+            }
+        }
+    }
 
-    (0..num_simulations / batch_size).into_par_iter().for_each(|i| {
-        let save_logs = (i + 1) * batch_size % log_interval == 0;
-        let (batch_match_wins, batch_shots, batch_aces, batch_double_faults) = simulate_batch(
-            player1.clone(),
-            player2.clone(),
-            best_of,
-            grand_slam,
-            batch_size,
-            save_logs,
-            "match_log_parallel.csv",
-        );
+    result
+}
 
-        let mut match_wins = match_wins.lock().unwrap();
-        for (player, wins) in batch_match_wins {
-            *match_wins.entry(player).or_insert(0) += wins;
+// The columns of the run's point log, or of its game log with `log_granularity: "game"`
+fn log_columns(config: &SimulationConfig) -> Vec<String> {
+    match config.log_granularity {
+        LogGranularity::Point => point_log_columns(&config.player1.name, &config.player2.name),
+        LogGranularity::Game => game_log_columns(),
+    }
+}
+
+// Appends the points (or games) of batch `batch_index` to the CSV log, or writes them to the batch's own Parquet file
+#[cfg_attr(not(feature = "parquet"), allow(unused_variables))]
+fn write_batch_log(config: &SimulationConfig, batch_index: usize, points: &[HashMap<String, serde_json::Value>]) -> Result<(), String> {
+    let columns = log_columns(config);
+    let metadata = RunMetadata::new(config);
+    match config.log_format {
+        LogFormat::Csv => {
+            let path = config.point_log_file();
+            write_point_log_csv(&path, &columns, points, &metadata).map_err(|e| format!("could not write {}: {}", path.display(), e))
+        }
+        #[cfg(feature = "parquet")]
+        LogFormat::Parquet => {
+            let path = config.run_dir.join("match_log_parallel").join(format!("part-{:05}.parquet", batch_index));
+            parquet_log::write_point_log(&path, &columns, points, &metadata)
         }
-        drop(match_wins);
+        #[cfg(not(feature = "parquet"))]
+        LogFormat::Parquet => unreachable!("parquet logging is rejected when loading the config"),
+    }
+}
 
-        *total_shots.lock().unwrap() += batch_shots;
+// Appends the matches of batch `batch_index` to the CSV match log, or writes them to the batch's own Parquet file
+#[cfg_attr(not(feature = "parquet"), allow(unused_variables))]
+fn write_batch_match_log(config: &SimulationConfig, batch_index: usize, matches: &[HashMap<String, serde_json::Value>]) -> Result<(), String> {
+    let columns = match_log_columns(&config.player1.name, &config.player2.name);
+    let metadata = RunMetadata::new(config);
+    match config.log_format {
+        LogFormat::Csv => {
+            let path = config.match_log_file();
+            write_point_log_csv(&path, &columns, matches, &metadata).map_err(|e| format!("could not write {}: {}", path.display(), e))
+        }
+        #[cfg(feature = "parquet")]
+        LogFormat::Parquet => {
+            let path = config.run_dir.join("match_summary").join(format!("part-{:05}.parquet", batch_index));
+            parquet_log::write_point_log(&path, &columns, matches, &metadata)
+        }
+        #[cfg(not(feature = "parquet"))]
+        LogFormat::Parquet => unreachable!("parquet logging is rejected when loading the config"),
+    }
+}
 
-        let mut total_aces = total_aces.lock().unwrap();
-        for (player, aces) in batch_aces {
-            *total_aces.entry(player).or_insert(0) += aces;
+// Simulates the batches of the run not yet in `checkpoint` (all of them without one). Runs with a run
+// directory save a checkpoint there about every 1% of batches, so an interrupted run can be resumed.
+fn simulate_match_parallel(config: &SimulationConfig, sinks: &[&dyn ResultSink], checkpoint: Option<Checkpoint>) -> SimulationReport {
+    let batch_size = config.batch_size;
+    let num_batches = config.num_simulations / batch_size;
+    let mut checkpoint = checkpoint.unwrap_or_else(|| Checkpoint::new(config));
+    let completed: HashSet<usize> = checkpoint.completed_batches.iter().copied().collect();
+    let previous_time = checkpoint.execution_time_ms as u128;
+    let save_checkpoints = !config.run_dir.as_os_str().is_empty();
+    let checkpoint_every = (num_batches / 100).max(1);
+    // A first checkpoint records where the logs started, which is not at 0 when appending to a file
+    if save_checkpoints && completed.is_empty() {
+        if let Err(e) = checkpoint.save(config) {
+            log::error!("{}", e);
         }
-        drop(total_aces);
+    }
+    let checkpoint = Mutex::new(checkpoint);
 
-        let mut total_double_faults = total_double_faults.lock().unwrap();
-        for (player, dfs) in batch_double_faults {
-            *total_double_faults.entry(player).or_insert(0) += dfs;
+    let start_time = Instant::now();
+
+    let new_match = || TennisMatch::new(config.player1.clone(), config.player2.clone(), config.scoring_rules(), config.model.clone(), config.seed);
+    (0..num_batches).into_par_iter().filter(|i| !completed.contains(i)).for_each_init(new_match, |match_sim, i| {
+        let save_logs = ((i + 1) * batch_size).is_multiple_of(config.log_interval);
+        let mut batch = simulate_batch(config, match_sim, i * batch_size + 1, batch_size, save_logs, sinks);
+        for sink in sinks {
+            sink.on_batch(&batch);
+        }
+
+        // Logging under the same lock as merging keeps batches from interleaving their rows and the
+        // checkpoint in step with what has been logged
+        let mut checkpoint = checkpoint.lock().unwrap();
+        if save_logs {
+            if let Err(e) = write_batch_log(config, i, &std::mem::take(&mut batch.point_log)) {
+                log::error!("{}", e);
+            }
+        }
+        if config.match_log {
+            if let Err(e) = write_batch_match_log(config, i, &std::mem::take(&mut batch.match_log)) {
+                log::error!("{}", e);
+            }
+        }
+        checkpoint.record(i, batch);
+        if save_checkpoints && checkpoint.completed_batches.len().is_multiple_of(checkpoint_every) {
+            checkpoint.execution_time_ms = (previous_time + start_time.elapsed().as_millis()) as u64;
+            if let Err(e) = checkpoint.save(config) {
+                log::error!("{}", e);
+            }
         }
-        drop(total_double_faults);
     });
 
-    let execution_time = start_time.elapsed().as_millis();
+    let execution_time = previous_time + start_time.elapsed().as_millis();
+    let totals = checkpoint.into_inner().unwrap().totals;
+
+    let report = SimulationReport {
+        match_wins: totals.match_wins,
+        total_shots: totals.total_shots,
+        execution_time,
+        stats: totals.stats,
+        excitement: totals.excitement,
+        forecast_scores: totals.forecast_scores,
+        markets: totals.markets,
+        transitions: totals.transitions,
+        situations: totals.situations,
+        metrics: totals.metrics,
+    };
+    for sink in sinks {
+        sink.on_complete(&report);
+    }
+    report
+}
+
+// One row per model and decile bucket: predicted vs realized win frequency
+fn write_calibration_curve(forecast_scores: &HashMap<String, ForecastScore>, metadata: &RunMetadata, csv_path: &Path, json_path: &Path) -> std::io::Result<()> {
+    let mut csv = std::fs::File::create(csv_path)?;
+    writeln!(csv, "{}", metadata.csv_comment())?;
+    writeln!(csv, "model,bucket_low,bucket_high,predictions,mean_predicted,realized_win_rate")?;
+    let mut rows = Vec::new();
+
+    for model in FORECAST_MODELS {
+        let Some(score) = forecast_scores.get(model) else { continue };
+        for (i, bucket) in score.buckets.iter().enumerate() {
+            let low = i as f64 / 10.0;
+            let high = (i + 1) as f64 / 10.0;
+            let mean_predicted = if bucket.count > 0 { bucket.prob_sum / bucket.count as f64 } else { 0.0 };
+            let realized = if bucket.count > 0 { bucket.wins as f64 / bucket.count as f64 } else { 0.0 };
+            writeln!(csv, "{},{:.1},{:.1},{},{:.4},{:.4}", model, low, high, bucket.count, mean_predicted, realized)?;
+            rows.push(serde_json::json!({
+                "model": model,
+                "bucket_low": low,
+                "bucket_high": high,
+                "predictions": bucket.count,
+                "mean_predicted": mean_predicted,
+                "realized_win_rate": realized,
+            }));
+        }
+    }
+
+    let report = serde_json::json!({ "metadata": metadata.to_json(), "buckets": rows });
+    std::fs::write(json_path, serde_json::to_string_pretty(&report)?)?;
+    Ok(())
+}
+
+// Mean, 10th percentile, median and 90th percentile of the values, or nothing when there are none
+fn distribution_summary(values: &[f64]) -> Option<[f64; 4]> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let quantile = |q: f64| sorted[((sorted.len() - 1) as f64 * q).round() as usize];
+    let mean = sorted.iter().sum::<f64>() / sorted.len() as f64;
+    Some([mean, quantile(0.1), quantile(0.5), quantile(0.9)])
+}
+
+// The value following `flag` on the command line, if the flag was given
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == flag).map(|index| args.get(index + 1).map(String::as_str).unwrap_or(""))
+}
+
+// The config given with --config (or the defaults), with the seed overridden by --seed, refused if `check`
+// reports errors for it
+fn config_from_args(args: &[String]) -> Result<SimulationConfig, String> {
+    let config = unchecked_config_from_args(args)?;
+    check::reject_errors(&config)?;
+    Ok(config)
+}
+
+// The config of `config_from_args` as given, errors and all, for `check` to report on
+fn unchecked_config_from_args(args: &[String]) -> Result<SimulationConfig, String> {
+    let mut config = match flag_value(args, "--config") {
+        Some(path) => SimulationConfig::from_file(path)?,
+        None => SimulationConfig::default(),
+    };
+    if let Some(seed) = flag_value(args, "--seed") {
+        config.seed = seed.parse().map_err(|e| format!("invalid --seed: {}", e))?;
+    }
+    Ok(config)
+}
 
-    // Safely unwrap the Arc<Mutex<_>> values
-    let final_match_wins = Arc::try_unwrap(match_wins).unwrap().into_inner().unwrap();
-    let final_total_shots = Arc::try_unwrap(total_shots).unwrap().into_inner().unwrap();
-    let final_total_aces = Arc::try_unwrap(total_aces).unwrap().into_inner().unwrap();
-    let final_total_double_faults = Arc::try_unwrap(total_double_faults).unwrap().into_inner().unwrap();
+// 64-bit FNV-1a, stable across platforms and releases, unlike the standard library's hasher
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
 
-    (final_match_wins, final_total_shots, execution_time, final_total_aces, final_total_double_faults)
+fn exit_on_error<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        log::error!("{}", e);
+        std::process::exit(1);
+    })
 }
 
 fn main() {
-    let num_simulations = 10000;
-    let num_sets = 5;
-    let max_workers = 10;
-    let batch_size = 10;
-    let log_interval = 10000;
-
-    let player1 = Player {
-        name: "Federer".to_string(),
-        serve_win_prob: 0.65,
-        ace_prob: 0.10,
-        double_fault_prob: 0.05,
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // The logger comes first, so that a bad verbosity flag is reported through it too
+    let verbosity = console::Verbosity::from_args(&args);
+    console::init(verbosity.clone().unwrap_or(console::Verbosity::Normal));
+    let verbosity = exit_on_error(verbosity);
+    match args.first().map(String::as_str) {
+        Some("backtest") => {
+            exit_on_error(backtest::run(args.get(1).map(String::as_str).unwrap_or("")));
+            return;
+        }
+        Some("diff") => {
+            exit_on_error(diff::run(&args[1..]));
+            return;
+        }
+        Some("edge") => {
+            exit_on_error(odds::run_edge_report(&args[1..]));
+            return;
+        }
+        Some("check") => {
+            exit_on_error(check::run(&args[1..]));
+            return;
+        }
+        Some("compare-format") => {
+            exit_on_error(compare_format::run(&args[1..]));
+            return;
+        }
+        Some("significance") => {
+            exit_on_error(significance::run(&args[1..]));
+            return;
+        }
+        Some("team") => {
+            exit_on_error(team::run(&args[1..]));
+            return;
+        }
+        Some("what-if") => {
+            exit_on_error(what_if::run(&args[1..]));
+            return;
+        }
+        Some("exact") => {
+            exit_on_error(exact::run(&args[1..]));
+            return;
+        }
+        Some("game") => {
+            exit_on_error(partial::run_game(&args[1..]));
+            return;
+        }
+        Some("set") => {
+            exit_on_error(partial::run_set(&args[1..]));
+            return;
+        }
+        Some("tiebreak") => {
+            exit_on_error(tiebreak::run(&args[1..]));
+            return;
+        }
+        Some("serve-first") => {
+            exit_on_error(serve_first::run(&args[1..]));
+            return;
+        }
+        Some("momentum") => {
+            exit_on_error(momentum::run(&args[1..]));
+            return;
+        }
+        Some("scaling") => {
+            exit_on_error(scaling::run(&args[1..]));
+            return;
+        }
+        Some("upset-curve") => {
+            exit_on_error(upset::run(&args[1..]));
+            return;
+        }
+        Some("inspect") => {
+            exit_on_error(inspect::run(&args[1..]));
+            return;
+        }
+        Some("repl") => {
+            exit_on_error(repl::run(&args[1..]));
+            return;
+        }
+        Some("interactive") => {
+            exit_on_error(interactive::run(&args[1..]));
+            return;
+        }
+        Some("feed") => {
+            #[cfg(feature = "feed")]
+            exit_on_error(feed::run(&args[1..]));
+            #[cfg(not(feature = "feed"))]
+            exit_on_error::<()>(Err("the feed subcommand requires building with --features feed".to_string()));
+            return;
+        }
+        Some("live") => {
+            exit_on_error(live::run(&args[1..]));
+            return;
+        }
+        Some("batches") => {
+            #[cfg(feature = "async")]
+            exit_on_error(batch_stream::run(&args[1..]));
+            #[cfg(not(feature = "async"))]
+            exit_on_error::<()>(Err("the batches subcommand requires building with --features async".to_string()));
+            return;
+        }
+        _ => {}
+    }
+    if args.iter().any(|arg| arg == "--verify-determinism") {
+        exit_on_error(determinism::verify(&args));
+        return;
+    }
+
+    // `resume <run dir>` continues an interrupted run from its last checkpoint, with the config it was started with
+    let mut resumed = None;
+    let mut config = if args.first().map(String::as_str) == Some("resume") {
+        let path = exit_on_error(args.get(1).filter(|arg| !arg.starts_with("--")).ok_or("usage: resume <run dir>".to_string()));
+        if ["--config", "--seed", "--point-log-path", "--match-log-path"].iter().any(|flag| flag_value(&args, flag).is_some()) {
+            exit_on_error::<()>(Err("resume takes its config, seed and log paths from the run directory".to_string()));
+        }
+        let run_dir = exit_on_error(RunDirectory::reopen(path));
+        let mut config = exit_on_error(SimulationConfig::from_file(&run_dir.path.join(run_dir::CONFIG_FILE).to_string_lossy()));
+        exit_on_error(check::reject_errors(&config));
+        config.run_dir = run_dir.path.clone();
+        resumed = Some((run_dir, exit_on_error(Checkpoint::load(&config))));
+        config
+    } else {
+        let mut config = exit_on_error(config_from_args(&args));
+        if let Some(path) = flag_value(&args, "--point-log-path") {
+            config.point_log_path = Some(PathBuf::from(path));
+        }
+        if let Some(path) = flag_value(&args, "--match-log-path") {
+            config.match_log_path = Some(PathBuf::from(path));
+        }
+        config
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.max_workers)
+        .build_global()
+        .unwrap();
+
+    let stream = args.iter().position(|arg| arg == "--stream").map(|index| match args.get(index + 1).map(String::as_str) {
+        Some("matches") => StreamSink::new(StreamMode::Matches),
+        _ => StreamSink::new(StreamMode::Points),
+    });
+    let completed_batches = resumed.as_ref().map_or(0, |(_, checkpoint)| checkpoint.completed_batches.len());
+    let variants = exit_on_error(scenario::variants(&config));
+    let progress = ProgressSink::new((config.num_simulations / config.batch_size - completed_batches) * variants.len().max(1));
+    let redis = flag_value(&args, "--redis").map(|url| exit_on_error(sink::redis_sink(url)));
+    let duckdb_path = flag_value(&args, "--duckdb");
+    let postgres_sample = match flag_value(&args, "--postgres-sample") {
+        Some(value) => exit_on_error(value.parse::<usize>().map_err(|e| format!("invalid --postgres-sample: {}", e))),
+        None => 100,
     };
+    let postgres = flag_value(&args, "--postgres").map(|url| exit_on_error(sink::postgres_sink(url, &config, postgres_sample)));
+    let outliers = flag_value(&args, "--capture-outliers")
+        .map(|value| OutlierSink::new(exit_on_error(value.parse::<usize>().map_err(|e| format!("invalid --capture-outliers: {}", e)))));
+    let results = args.iter().any(|arg| arg == "--keep-results").then(StoreSink::default);
+    let audit = args.iter().any(|arg| arg == "--audit-probabilities").then(|| ProbabilityAudit::new(&config));
+    if !variants.is_empty() && (stream.is_some() || redis.is_some() || duckdb_path.is_some() || postgres.is_some() || outliers.is_some() || results.is_some()) {
+        exit_on_error::<()>(Err("surface and scenario comparisons only support the --progress sink; other sinks have no column telling them apart".to_string()));
+    }
+    if resumed.is_some() && outliers.is_some() {
+        exit_on_error::<()>(Err("--capture-outliers can't be used with resume: the matches simulated before the interruption are not kept, so the outliers would miss them".to_string()));
+    }
+    let log_paths = [
+        (config.point_log_path.clone(), log_columns(&config)),
+        (config.match_log_path.clone(), match_log_columns(&config.player1.name, &config.player2.name)),
+    ];
+    let log_paths: Vec<(PathBuf, Vec<String>)> = log_paths.into_iter().filter_map(|(path, columns)| Some((path?, columns))).collect();
+    if !log_paths.is_empty() && (!variants.is_empty() || config.log_format != LogFormat::Csv) {
+        exit_on_error::<()>(Err("point_log_path and match_log_path are for the CSV logs of a single run; Parquet logs and surface and scenario comparisons are written to the run directory".to_string()));
+    }
+    if resumed.is_none() {
+        let policy = exit_on_error(LogPolicy::from_args(&args));
+        let metadata = RunMetadata::new(&config);
+        for (path, columns) in &log_paths {
+            exit_on_error(run_dir::prepare_log_file(path, policy, &metadata, columns));
+        }
+    }
+
+    let upload = flag_value(&args, "--upload");
+    #[cfg(not(feature = "object_store"))]
+    if let Some(url) = upload {
+        exit_on_error::<()>(Err(format!("uploading to {} requires building with --features object_store", url)));
+    }
+
+    for warning in check::warnings(&config) {
+        log::warn!("{}", warning);
+    }
+
+    let (run_dir, checkpoint) = match resumed {
+        Some((run_dir, checkpoint)) => {
+            log::info!("Resuming run {}: {} of {} batches already simulated", run_dir.run_id, completed_batches, config.num_simulations / config.batch_size);
+            (run_dir, Some(checkpoint))
+        }
+        None => (exit_on_error(RunDirectory::create(flag_value(&args, "--output-dir").unwrap_or("runs"), &config)), None),
+    };
+    config.run_dir = run_dir.path.clone();
+    config.plain = args.iter().any(|arg| arg == "--plain");
+
+    // Its rows are tagged with the run's id, which a resumed run keeps
+    let duckdb = duckdb_path.map(|path| exit_on_error(sink::duckdb_sink(path, &config, &run_dir.run_id, checkpoint.is_some())));
+    let mut sinks: Vec<&dyn ResultSink> = Vec::new();
+    if args.iter().any(|arg| arg == "--progress") || verbosity >= console::Verbosity::Verbose {
+        sinks.push(&progress);
+    }
+    if let Some(redis) = &redis {
+        sinks.push(redis.as_ref());
+    }
+    if let Some(duckdb) = &duckdb {
+        sinks.push(duckdb.as_ref());
+    }
+    if let Some(postgres) = &postgres {
+        sinks.push(postgres.as_ref());
+    }
+    if let Some(stream) = &stream {
+        sinks.push(stream);
+    }
+    if let Some(outliers) = &outliers {
+        sinks.push(outliers);
+    }
+    if let Some(results) = &results {
+        sinks.push(results);
+    }
+    if let Some(audit) = &audit {
+        sinks.push(audit);
+    }
+
+    let color = console::colors(if stream.is_some() { std::io::stderr().is_terminal() } else { std::io::stdout().is_terminal() });
+    let mut summary = Vec::new();
+    // What --quiet prints: a line of match win probabilities per run
+    let mut probabilities = Vec::new();
+    if variants.is_empty() {
+        let report = simulate_match_parallel(&config, &sinks, checkpoint);
+        for warning in sanity::warnings(&config, &report) {
+            log::warn!("{}", warning);
+        }
+        probabilities.push(win_probabilities(None, &config, &report, color));
+        exit_on_error(print_report(&mut summary, &config, &report).map_err(|e| e.to_string()));
+        let report_json = exit_on_error(serde_json::to_string_pretty(&report_json(&config, &report)).map_err(|e| e.to_string()));
+        exit_on_error(run_dir.write(run_dir::REPORT_JSON_FILE, report_json.as_bytes()));
+        if let Some(outliers) = &outliers {
+            let lines = exit_on_error(outliers.write(&run_dir));
+            exit_on_error(writeln!(summary, "\nMost extreme matches (logs in 'outliers'):\n{}", lines.join("\n")).map_err(|e| e.to_string()));
+        }
+        if let Some(results) = results {
+            let store = results.into_store();
+            exit_on_error(results::write_scorelines(&mut summary, &store, &config.player1.name, &config.player2.name).map_err(|e| e.to_string()));
+        }
+    } else {
+        let reports = exit_on_error(scenario::run_comparison(&mut summary, &config, variants, &sinks, checkpoint.is_some()));
+        for (name, variant, report) in &reports {
+            for warning in sanity::warnings(variant, report) {
+                log::warn!("{}: {}", name, warning);
+            }
+        }
+        probabilities.extend(reports.iter().map(|(name, variant, report)| win_probabilities(Some(name), variant, report, color)));
+    }
+    exit_on_error(run_dir.write(run_dir::REPORT_FILE, &summary));
+    exit_on_error(run_dir.write_manifest(&config, "complete"));
+    let mut out: Box<dyn Write> = if stream.is_some() { Box::new(std::io::stderr()) } else { Box::new(std::io::stdout()) };
+    if verbosity == console::Verbosity::Quiet {
+        exit_on_error(writeln!(out, "{}", probabilities.join("\n")).map_err(|e| e.to_string()));
+    } else {
+        exit_on_error(out.write_all(&summary).map_err(|e| e.to_string()));
+        exit_on_error(writeln!(out, "Run artifacts and manifest written to '{}'", run_dir.path.display()).map_err(|e| e.to_string()));
+    }
+    if let Some(audit) = &audit {
+        let line = exit_on_error(audit.result());
+        if verbosity != console::Verbosity::Quiet {
+            exit_on_error(writeln!(out, "{}", line).map_err(|e| e.to_string()));
+        }
+    }
+
+    #[cfg(feature = "object_store")]
+    if let Some(url) = upload {
+        let files = exit_on_error(upload::upload_run(url, &run_dir));
+        if verbosity != console::Verbosity::Quiet {
+            exit_on_error(writeln!(out, "Uploaded {} files to {}/{}", files, url.trim_end_matches('/'), run_dir.run_id).map_err(|e| e.to_string()));
+        }
+    }
+}
+
+// A run's match win probabilities on one line, the favourite in green and the other player in red
+fn win_probabilities(label: Option<&str>, config: &SimulationConfig, report: &SimulationReport, color: bool) -> String {
+    let names = [&config.player1.name, &config.player2.name];
+    let probabilities = names.map(|name| report.win_probability(name));
+    let players: Vec<String> = (0..2)
+        .map(|i| {
+            let favourite = probabilities[i] >= probabilities[1 - i];
+            console::paint(&format!("{} {:.2}%", names[i], probabilities[i] * 100.0), if favourite { console::Color::Green } else { console::Color::Red }, color)
+        })
+        .collect();
+    match label {
+        Some(label) => format!("{}: {}", label, players.join(", ")),
+        None => players.join(", "),
+    }
+}
+
+// The totals of a run as JSON, with the sample sizes needed to compare it with another run
+fn report_json(config: &SimulationConfig, report: &SimulationReport) -> serde_json::Value {
+    let forecast_scores: serde_json::Map<String, serde_json::Value> = FORECAST_MODELS.iter()
+        .map(|model| {
+            let score = report.forecast_scores.get(*model).cloned().unwrap_or_default();
+            let summary = serde_json::json!({ "count": score.count, "brier_score": score.brier_score(), "log_loss": score.log_loss() });
+            (model.to_string(), summary)
+        })
+        .collect();
+    serde_json::json!({
+        "metadata": RunMetadata::new(config).to_json(),
+        "results_hash": results_hash(report),
+        "players": [config.player1.name, config.player2.name],
+        "best_of": config.best_of,
+        "matches": report.match_wins.values().sum::<i32>(),
+        "match_wins": report.match_wins,
+        "total_shots": report.total_shots,
+        "execution_time_ms": report.execution_time as u64,
+        "stats": report.stats,
+        "forecast_scores": forecast_scores,
+        "metrics": metrics_json(config, report),
+    })
+}
+
+// Each configured metric's mean, quantiles and histogram
+fn metrics_json(config: &SimulationConfig, report: &SimulationReport) -> serde_json::Value {
+    let summaries: serde_json::Map<String, serde_json::Value> = config.metrics.iter()
+        .map(|name| {
+            let values = report.metrics.get(name).map_or(&[][..], Vec::as_slice);
+            let summary = match (distribution_summary(values), metrics::histogram(values)) {
+                (Some([mean, p10, median, p90]), Some((min, bin_width, counts))) => serde_json::json!({
+                    "count": values.len(),
+                    "mean": mean,
+                    "p10": p10,
+                    "median": median,
+                    "p90": p90,
+                    "histogram": { "min": min, "bin_width": bin_width, "counts": counts },
+                }),
+                _ => serde_json::json!({ "count": 0 }),
+            };
+            (name.clone(), summary)
+        })
+        .collect();
+    serde_json::Value::Object(summaries)
+}
+
+// Prints the summary of a simulation run; in streaming mode this goes to stderr so stdout only carries the stream
+fn print_report(out: &mut dyn Write, config: &SimulationConfig, report: &SimulationReport) -> std::io::Result<()> {
+    let num_simulations = config.num_simulations;
+    let metadata = RunMetadata::new(config);
+
+    writeln!(
+        out,
+        "Run: seed {}, config hash {}, run hash {}, results hash {} (output schema v{}, tennis_sim_rust {})",
+        metadata.seed, metadata.config_hash, metadata.run_hash, results_hash(report), metadata.schema_version, metadata.crate_version,
+    )?;
+    let players = [&config.player1, &config.player2];
+    let header = |first: &'static str| [first, config.player1.name.as_str(), config.player2.name.as_str()];
+    let per_match = |player: &Player, key: &str| report.stat(&player.name, key) as f64 / num_simulations as f64;
+
+    writeln!(out, "Percentage of Match wins after {} matches:", num_simulations)?;
+    let mut wins = table::new(&["Player", "Wins", "Win %", "95% interval"], config.plain);
+    for player in players {
+        let won = report.match_wins.get(&player.name).copied().unwrap_or(0);
+        let (low, high) = significance::wilson_interval(won as u64, num_simulations as u64);
+        wins.add_row(vec![player.name.clone(), won.to_string(), format!("{:.2}%", won as f64 / num_simulations as f64 * 100.0), format!("{:.2}-{:.2}%", low * 100.0, high * 100.0)]);
+    }
+    writeln!(out, "{}", wins)?;
 
-    let player2 = Player {
-        name: "Nadal".to_string(),
-        serve_win_prob: 0.62,
-        ace_prob: 0.08,
-        double_fault_prob: 0.04,
+    writeln!(out, "\nTotal shots played: {}", report.total_shots)?;
+    writeln!(out, "Execution time: {:.2} milliseconds", report.execution_time)?;
+
+    // Each row shows a value per player, formatted by `cell`
+    let mut stats = table::new(&header("Match statistics"), config.plain);
+    let mut stat_row = |label: &str, cell: &dyn Fn(&Player) -> String| {
+        stats.add_row(vec![label.to_string(), cell(players[0]), cell(players[1])]);
     };
+    let ratio = |won: i32, played: i32| format!("{}/{} ({:.2}%)", won, played, won as f64 / played.max(1) as f64 * 100.0);
+    stat_row("Avg. Aces per match", &|player| format!("{:.2}", per_match(player, "aces")));
+    stat_row("Avg. Double faults per match", &|player| format!("{:.2}", per_match(player, "double_faults")));
+    stat_row("Tiebreaks won", &|player| ratio(report.stat(&player.name, "tiebreaks_won"), report.stat(&player.name, "tiebreaks_played")));
+    stat_row("Deuce court serve points won", &|player| ratio(report.stat(&player.name, "deuce_points_won"), report.stat(&player.name, "deuce_points_served")));
+    stat_row("Ad court serve points won", &|player| ratio(report.stat(&player.name, "ad_points_won"), report.stat(&player.name, "ad_points_served")));
+    // Without a first serve profile no first serve misses, so the split says little
+    if players.iter().any(|player| player.first_serve.is_some()) {
+        let modeled = |player: &Player, cell: String| if player.first_serve.is_some() { cell } else { "-".to_string() };
+        let served = |player: &Player| report.stat(&player.name, "deuce_points_served") + report.stat(&player.name, "ad_points_served");
+        stat_row("1st serves in", &|player| modeled(player, ratio(report.stat(&player.name, "first_serves_in"), served(player))));
+        stat_row("1st serve points won", &|player| modeled(player, ratio(report.stat(&player.name, "first_serve_points_won"), report.stat(&player.name, "first_serves_in"))));
+        stat_row("2nd serve points won", &|player| modeled(player, ratio(report.stat(&player.name, "second_serve_points_won"), report.stat(&player.name, "second_serve_points"))));
+    }
+    stat_row("Service games held", &|player| ratio(report.stat(&player.name, "service_games_held"), report.stat(&player.name, "service_games")));
+    stat_row("Break points saved", &|player| ratio(report.stat(&player.name, "break_points_saved"), report.stat(&player.name, "break_points_faced")));
+
+    let conduct = &config.model.conduct;
+    if conduct.time_violation_prob > 0.0 || conduct.code_violation_prob > 0.0 {
+        stat_row("Avg. Time / code violations per match", &|player| format!("{:.3} / {:.3}", per_match(player, "time_violations"), per_match(player, "code_violations")));
+        stat_row("Avg. Points conceded to penalties per match", &|player| format!("{:.3}", per_match(player, "penalty_points")));
+    }
+
+    if config.model.challenges.wrong_call_prob > 0.0 {
+        stat_row("Challenges won", &|player| ratio(report.stat(&player.name, "challenges_won"), report.stat(&player.name, "challenges")));
+        stat_row("Avg. Wrong calls against, not overturned, per match", &|player| format!("{:.2}", per_match(player, "uncorrected_calls")));
+    }
 
-    let (results, total_shots, execution_time, aces, double_faults) = simulate_match_parallel(
-        player1.clone(),
-        player2.clone(),
-        num_sets,
-        true,
-        num_simulations,
-        max_workers,
-        batch_size,
-        log_interval,
-    );
+    // Players without a return profile have no return outcomes to count
+    if players.iter().any(|player| player.return_profile.is_some()) {
+        for (label, key) in [("Avg. Return errors per match", "return_errors"), ("Avg. Short returns per match", "short_returns"), ("Avg. Deep returns per match", "deep_returns")] {
+            stat_row(label, &|player| if player.return_profile.is_some() { format!("{:.2}", per_match(player, key)) } else { "-".to_string() });
+        }
+    }
+
+    // Rare sets won, in total and per match
+    let games = config.scoring_rules().games_per_set;
+    for (label, key) in [(format!("{}-0 sets won", games), "bagels"), (format!("{}-1 sets won", games), "breadsticks"), ("Golden sets won".to_string(), "golden_sets")] {
+        stat_row(&label, &|player| format!("{} ({:.4})", report.stat(&player.name, key), per_match(player, key)));
+    }
+    writeln!(out, "\n{}", stats)?;
+
+    // Each situation against all points shows what the pressure and momentum models do to the serve
+    let mut serve_situations = table::new(&header("Serve by situation"), config.plain);
+    for (situation, (_, label)) in situations::SITUATIONS.iter().enumerate() {
+        let cell = |server: usize| match report.situations.rates(server, situation) {
+            (0, _, _) => "-".to_string(),
+            (points, won, aces) => format!("{:.2}% won, {:.2}% aces of {}", won * 100.0, aces * 100.0, points),
+        };
+        serve_situations.add_row(vec![label.to_string(), cell(0), cell(1)]);
+    }
+    writeln!(out, "{}", serve_situations)?;
+    let situations_csv = config.run_dir.join("serve_situations.csv");
+    match report.situations.write_csv(&situations_csv, &metadata, [config.player1.name.as_str(), config.player2.name.as_str()]) {
+        Ok(()) => writeln!(out, " Serve by situation exported to '{}'", situations_csv.display())?,
+        Err(e) => log::error!("could not export serve by situation: {}", e),
+    }
 
-    println!("Percentage of Match wins after {} matches:", num_simulations);
-    for (player, wins) in &results {
-        println!("{}: {:.2}%", player, (*wins as f64 / num_simulations as f64) * 100.0);
+    if let (Some(rating1), Some(rating2)) = (config.player1.elo, config.player2.elo) {
+        writeln!(out, "\nElo (K = {}):", config.elo.k_factor)?;
+        let mut elo = table::new(&["Player", "Rating", "Win % by rating", "Win % simulated", "On a win", "On a loss", "Expected"], config.plain);
+        for (player, rating, opponent) in [(&config.player1, rating1, rating2), (&config.player2, rating2, rating1)] {
+            let win_prob = report.win_probability(&player.name);
+            let (on_win, on_loss, expected) = elo::expected_change(rating, opponent, win_prob, config.elo.k_factor);
+            elo.add_row(vec![
+                player.name.clone(),
+                format!("{:.0}", rating),
+                format!("{:.2}%", elo::expected_score(rating, opponent) * 100.0),
+                format!("{:.2}%", win_prob * 100.0),
+                format!("{:+.1}", on_win),
+                format!("{:+.1}", on_loss),
+                format!("{:+.2}", expected),
+            ]);
+        }
+        writeln!(out, "{}", elo)?;
     }
 
-    println!("\nTotal shots played: {}", total_shots);
-    println!("Execution time: {:.2} milliseconds", execution_time);
+    let markets = &report.markets;
+    let matches = num_simulations.max(1) as f64 / 100.0;
+    writeln!(out, "\nMarkets:")?;
+    let mut market_table = table::new(&["Market", config.player1.name.as_str(), config.player2.name.as_str(), "Other"], config.plain);
+    let share = |count: i32, of: f64| format!("{:.2}%", count as f64 / of);
+    market_table.add_row(vec!["First break of serve".to_string(), share(markets.first_break[0], matches), share(markets.first_break[1], matches), format!("no break {}", share(markets.first_break[2], matches))]);
+    for (set, race) in markets.race.iter().enumerate() {
+        let sets = (race[0] + race[1]).max(1) as f64 / 100.0;
+        market_table.add_row(vec![format!("Race to {} games, set {}", config.race_to_games, set + 1), share(race[0], sets), share(race[1], sets), format!("{} sets", race[0] + race[1])]);
+    }
+    market_table.add_row(vec!["Most aces".to_string(), share(markets.more_aces[0], matches), share(markets.more_aces[1], matches), format!("tie {}", share(markets.more_aces[2], matches))]);
+    writeln!(out, "{}", market_table)?;
+
+    // Counts up to the one covering 99% of matches for either player, then the rest together
+    let mut last = 0;
+    for counts in &markets.ace_counts {
+        let mut covered = 0;
+        for (aces, count) in counts.iter().enumerate() {
+            if covered as f64 >= num_simulations as f64 * 0.99 {
+                break;
+            }
+            covered += count;
+            last = last.max(aces + 1);
+        }
+    }
+    let mut aces = table::new(&header("Aces per match"), config.plain);
+    for aces_in_match in 0..=last {
+        let count = |counts: &Vec<i32>| if aces_in_match == last { counts.iter().skip(last).sum() } else { counts.get(aces_in_match).copied().unwrap_or(0) };
+        let label = if aces_in_match == last { format!("{}+", last) } else { aces_in_match.to_string() };
+        aces.add_row(vec![label, share(count(&markets.ace_counts[0]), matches), share(count(&markets.ace_counts[1]), matches)]);
+    }
+    writeln!(out, "{}", aces)?;
+
+    let mut paths: Vec<(&String, &i32)> = markets.score_paths.iter().collect();
+    paths.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    writeln!(out, "Most likely set scores ({} first, {} of {} seen):", config.player1.name, config.score_paths.min(paths.len()), paths.len())?;
+    let mut path_table = table::new(&["Set scores", "Share"], config.plain);
+    for (path, count) in paths.iter().take(config.score_paths) {
+        path_table.add_row(vec![path.to_string(), share(**count, matches)]);
+    }
+    writeln!(out, "{}", path_table)?;
+
+    // Both players count every deciding set
+    let deciding_sets = report.stat(&config.player1.name, "deciding_sets_played");
+    writeln!(out, "\nDeciding sets: reached in {}/{} matches ({:.2}%), {:.2}% of them decided by a tiebreak", deciding_sets, num_simulations, deciding_sets as f64 / num_simulations as f64 * 100.0, report.stat(&config.player1.name, "deciding_set_tiebreaks") as f64 / deciding_sets.max(1) as f64 * 100.0)?;
+    let mut deciding = table::new(&["Player", "Deciding sets won"], config.plain);
+    for player in players {
+        deciding.add_row(vec![player.name.clone(), ratio(report.stat(&player.name, "deciding_sets_won"), deciding_sets)]);
+    }
+    writeln!(out, "{}", deciding)?;
 
-    println!("\nMatch statistics:");
-    for player in &[&player1, &player2] {
-        println!("{}:", player.name);
-        println!(" Avg. Aces per match: {:.2}", *aces.get(&player.name).unwrap_or(&0) as f64 / num_simulations as f64);
-        println!(" Avg. Double faults per match: {:.2}", *double_faults.get(&player.name).unwrap_or(&0) as f64 / num_simulations as f64);
+    let weather = &config.model.weather;
+    if !weather.indoor && weather.suspension_prob > 0.0 {
+        // Both players count every suspension
+        writeln!(out, "\nAvg. Rain suspensions per match: {:.3}", per_match(&config.player1, "rain_suspensions"))?;
     }
 
-    println!("\nPoint-by-point log exported to 'match_log_parallel.csv'");
-}
\ No newline at end of file
+    writeln!(out, "\nMatch excitement:")?;
+    let mut excitement = table::new(&["Measure", "Mean", "p10", "Median", "p90"], config.plain);
+    let leverage: Vec<f64> = report.excitement.iter().map(|index| index.cumulative_leverage).collect();
+    let lead_changes: Vec<f64> = report.excitement.iter().map(|index| index.lead_changes as f64).collect();
+    let winner_min: Vec<f64> = report.excitement.iter().map(|index| index.winner_min_win_prob).collect();
+    for (label, values) in [("Cumulative leverage", &leverage), ("Lead changes", &lead_changes), ("Winner's minimum win probability", &winner_min)] {
+        let mut row = vec![label.to_string()];
+        match distribution_summary(values) {
+            Some(summary) => row.extend(summary.iter().map(|value| format!("{:.3}", value))),
+            None => row.extend(["n/a"; 4].map(String::from)),
+        }
+        excitement.add_row(row);
+    }
+    writeln!(out, "{}", excitement)?;
+
+    if !config.metrics.is_empty() {
+        writeln!(out, "\nMetrics per match:")?;
+        let mut metric_table = table::new(&["Metric", "Mean", "p10", "Median", "p90"], config.plain);
+        for name in &config.metrics {
+            let label = metrics::find(name).map_or(name.as_str(), |metric| metric.description);
+            let mut row = vec![label.to_string()];
+            match report.metrics.get(name).and_then(|values| distribution_summary(values)) {
+                Some(summary) => row.extend(summary.iter().map(|value| format!("{:.3}", value))),
+                None => row.extend(["n/a"; 4].map(String::from)),
+            }
+            metric_table.add_row(row);
+        }
+        writeln!(out, "{}", metric_table)?;
+    }
+
+    writeln!(out, "\nProbability model evaluation ({}'s estimates before each point):", config.player1.name)?;
+    let mut forecasts = table::new(&["Model", "Brier score", "Log-loss", "Predictions"], config.plain);
+    for model in FORECAST_MODELS {
+        let score = report.forecast_scores.get(model).cloned().unwrap_or_default();
+        forecasts.add_row(vec![model.to_string(), format!("{:.4}", score.brier_score()), format!("{:.4}", score.log_loss()), score.count.to_string()]);
+    }
+    writeln!(out, "{}", forecasts)?;
+    let calibration_csv = config.run_dir.join("calibration_curve.csv");
+    let calibration_json = config.run_dir.join("calibration_curve.json");
+    match write_calibration_curve(&report.forecast_scores, &metadata, &calibration_csv, &calibration_json) {
+        Ok(()) => writeln!(out, " Calibration curves exported to '{}' and '{}'", calibration_csv.display(), calibration_json.display())?,
+        Err(e) => log::error!("could not export calibration curves: {}", e),
+    }
+    let transitions_csv = config.run_dir.join("score_transitions.csv");
+    match report.transitions.write_csv(&transitions_csv, &metadata, &config.scoring_rules(), [config.player1.name.as_str(), config.player2.name.as_str()]) {
+        Ok(()) => writeln!(out, " Score-state transitions exported to '{}'", transitions_csv.display())?,
+        Err(e) => log::error!("could not export score-state transitions: {}", e),
+    }
+
+    let granularity = match config.log_granularity {
+        LogGranularity::Point => "Point-by-point",
+        LogGranularity::Game => "Game-by-game",
+    };
+    match config.log_format {
+        LogFormat::Csv => writeln!(out, "\n{} log exported to '{}'", granularity, config.point_log_file().display())?,
+        LogFormat::Parquet => writeln!(out, "\n{} log exported to '{}/' (Parquet)", granularity, config.run_dir.join("match_log_parallel").display())?,
+    }
+    if config.match_log {
+        match config.log_format {
+            LogFormat::Csv => writeln!(out, "Match summary log exported to '{}'", config.match_log_file().display())?,
+            LogFormat::Parquet => writeln!(out, "Match summary log exported to '{}/' (Parquet)", config.run_dir.join("match_summary").display())?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiebreak_continues_the_serve_order_and_its_receiver_serves_next() {
+        let config = SimulationConfig::default();
+        let mut tiebreaks = 0;
+        for n in 1..=200 {
+            let mut match_sim = TennisMatch::new(config.player1.clone(), config.player2.clone(), config.scoring_rules(), config.model.clone(), match_seed(config.seed, n));
+            match_sim.play_match();
+            for (index, game) in match_sim.games_played.iter().enumerate().filter(|(_, game)| game.tiebreak) {
+                tiebreaks += 1;
+                // The player who received at 5-6 or 6-5 serves the first point of the tiebreak at 6-6
+                assert_eq!(game.server, 1 - match_sim.games_played[index - 1].server);
+                // and receives first in the next set
+                if let Some(next_set_server) = match_sim.set_first_servers.get(game.set + 1) {
+                    assert_eq!(*next_set_server, 1 - game.server);
+                }
+            }
+        }
+        assert!(tiebreaks > 0);
+    }
+}
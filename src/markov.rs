@@ -0,0 +1,179 @@
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scoring::{GameState, ScoringRules};
+use crate::{csv_field, RunMetadata};
+
+// The states of a game, tiebreaks aside, server first: the scores up to 40-30 and 30-40, deuce, the
+// advantages, and the two ways a game ends. A score is placed at (server points) * 4 + (receiver points).
+pub const STATES: [&str; 20] = [
+    "0-0", "0-15", "0-30", "0-40", "15-0", "15-15", "15-30", "15-40", "30-0", "30-15", "30-30", "30-40", "40-0", "40-15", "40-30",
+    "Deuce", "Ad-In", "Ad-Out", "Hold", "Break",
+];
+const DEUCE: usize = 15;
+const AD_IN: usize = 16;
+pub const HOLD: usize = 18;
+pub const BREAK: usize = 19;
+
+// The state of a game at `points`, server first
+pub fn state(rules: &ScoringRules, points: [i32; 2]) -> usize {
+    match rules.game_state(points) {
+        GameState::InProgress => (points[0] * 4 + points[1]) as usize,
+        GameState::Deuce => DEUCE,
+        GameState::Advantage(side) => AD_IN + side,
+        GameState::Won(side) => HOLD + side,
+    }
+}
+
+// A score that leaves a game in `state`, server first
+fn points(state: usize) -> [i32; 2] {
+    match state {
+        DEUCE => [3, 3],
+        AD_IN => [4, 3],
+        state if state < DEUCE => [state as i32 / 4, state as i32 % 4],
+        _ => [3, 4],
+    }
+}
+
+// How often games went from one state to another on a point, by server (0 = player1): the empirical
+// transition matrix of a game's Markov chain, to set against the one a constant serve point win rate gives
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ScoreTransitions {
+    // [server][from][to], flattened; empty until a point is counted
+    counts: Vec<u64>,
+}
+
+impl ScoreTransitions {
+    pub fn add(&mut self, server: usize, from: usize, to: usize) {
+        if self.counts.is_empty() {
+            self.counts.resize(2 * STATES.len() * STATES.len(), 0);
+        }
+        self.counts[(server * STATES.len() + from) * STATES.len() + to] += 1;
+    }
+
+    pub fn merge(&mut self, other: &ScoreTransitions) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (total, count) in self.counts.iter_mut().zip(&other.counts) {
+            *total += count;
+        }
+    }
+
+    // Back to no points, keeping the counts' allocation
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+    }
+
+    // A row per transition seen: the server, the states before and after the point, whether the server won
+    // it, how often it happened and its share of the points played from `from` by that server
+    pub fn write_csv(&self, path: &Path, metadata: &RunMetadata, rules: &ScoringRules, players: [&str; 2]) -> std::io::Result<()> {
+        let mut csv = std::fs::File::create(path)?;
+        writeln!(csv, "{}", metadata.csv_comment())?;
+        writeln!(csv, "server,from,to,server_won,count,probability")?;
+        for (row, counts) in self.counts.chunks(STATES.len()).enumerate() {
+            let (server, from) = (row / STATES.len(), row % STATES.len());
+            let total: u64 = counts.iter().sum();
+            let [won, lost] = points(from);
+            let server_won_state = state(rules, [won + 1, lost]);
+            for (to, count) in counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+                writeln!(csv, "{},{},{},{},{},{:.6}", csv_field(players[server]), STATES[from], STATES[to], to == server_won_state, count, *count as f64 / total as f64)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::SetDecider;
+    use crate::SimulationConfig;
+
+    fn no_ad() -> ScoringRules {
+        ScoringRules { no_ad: true, decider: SetDecider::Tiebreak { at: 3, points: 7 }, games_per_set: 4, ..ScoringRules::default() }
+    }
+
+    fn label(rules: &ScoringRules, points: [i32; 2]) -> &'static str {
+        STATES[state(rules, points)]
+    }
+
+    #[test]
+    fn states_are_placed_server_first() {
+        let rules = ScoringRules::default();
+        assert_eq!(label(&rules, [0, 0]), "0-0");
+        assert_eq!(label(&rules, [2, 1]), "30-15");
+        assert_eq!(label(&rules, [1, 2]), "15-30");
+        assert_eq!(label(&rules, [0, 3]), "0-40");
+        assert_eq!(label(&rules, [3, 2]), "40-30");
+        for tie in 3..6 {
+            assert_eq!(label(&rules, [tie, tie]), "Deuce");
+        }
+        assert_eq!(label(&rules, [4, 3]), "Ad-In");
+        assert_eq!(label(&rules, [5, 6]), "Ad-Out");
+        assert_eq!(state(&rules, [4, 2]), HOLD);
+        assert_eq!(state(&rules, [3, 5]), BREAK);
+        // Every state but the finished ones is its own score
+        for (index, _) in STATES.iter().enumerate().take(HOLD) {
+            assert_eq!(state(&rules, points(index)), index);
+        }
+    }
+
+    #[test]
+    fn no_ad_deuce_is_the_deciding_point() {
+        let rules = no_ad();
+        assert_eq!(label(&rules, [2, 2]), "30-30");
+        assert_eq!(label(&rules, [3, 3]), "Deuce");
+        assert_eq!(state(&rules, [4, 3]), HOLD);
+        assert_eq!(state(&rules, [3, 4]), BREAK);
+    }
+
+    // The rows of `transitions` written as CSV, as (server, from, to, server_won)
+    fn rows(transitions: &ScoreTransitions, rules: &ScoringRules, name: &str) -> Vec<(String, String, String, String)> {
+        let path = std::env::temp_dir().join(format!("tennis_sim_markov_{}_{}.csv", name, std::process::id()));
+        let metadata = RunMetadata::new(&SimulationConfig::default());
+        transitions.write_csv(&path, &metadata, rules, ["Federer, R.", "Nadal"]).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        csv.lines()
+            .skip(2)
+            .map(|line| {
+                // The first player's name is quoted for its comma
+                let (server, rest) = line.strip_prefix('"').map_or_else(|| line.split_once(',').unwrap(), |line| line.split_once("\",").unwrap());
+                let fields: Vec<&str> = rest.split(',').collect();
+                (server.to_string(), fields[0].to_string(), fields[1].to_string(), fields[2].to_string())
+            })
+            .collect()
+    }
+
+    fn row(server: &str, from: &str, to: &str, server_won: bool) -> (String, String, String, String) {
+        (server.to_string(), from.to_string(), to.to_string(), server_won.to_string())
+    }
+
+    #[test]
+    fn server_won_follows_the_server_not_the_player() {
+        let rules = ScoringRules::default();
+        let mut transitions = ScoreTransitions::default();
+        for (server, from, to) in [(0, [2, 1], [3, 1]), (1, [2, 1], [2, 2]), (0, [3, 3], [3, 4]), (1, [3, 4], [4, 4]), (1, [4, 3], [5, 3])] {
+            transitions.add(server, state(&rules, from), state(&rules, to));
+        }
+        assert_eq!(
+            rows(&transitions, &rules, "advantage"),
+            vec![
+                row("Federer, R.", "30-15", "40-15", true),
+                row("Federer, R.", "Deuce", "Ad-Out", false),
+                row("Nadal", "30-15", "30-30", false),
+                row("Nadal", "Ad-In", "Hold", true),
+                row("Nadal", "Ad-Out", "Deuce", true),
+            ]
+        );
+
+        let rules = no_ad();
+        let mut transitions = ScoreTransitions::default();
+        transitions.add(0, state(&rules, [3, 3]), state(&rules, [4, 3]));
+        transitions.add(1, state(&rules, [3, 3]), state(&rules, [3, 4]));
+        assert_eq!(rows(&transitions, &rules, "no_ad"), vec![row("Federer, R.", "Deuce", "Hold", true), row("Nadal", "Deuce", "Break", false)]);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::TennisMatch;
+
+// Bins of the histogram reported for each metric
+const HISTOGRAM_BINS: usize = 10;
+
+// A per-match measure the parallel driver collects from every finished match and summarizes with the built-in
+// stats: its mean, quantiles and a histogram
+pub struct Metric {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub extract: fn(&TennisMatch) -> f64,
+}
+
+// The metrics a config's `metrics` list can name. A research metric is added by adding an entry here: the
+// extractor sees the finished match, with its point log, games and stats, and the driver does the rest.
+pub const METRICS: &[Metric] = &[
+    Metric { name: "points", description: "Points played", extract: |match_sim| match_sim.point_log.len() as f64 },
+    Metric { name: "shots", description: "Shots played", extract: |match_sim| match_sim.total_shots as f64 },
+    Metric { name: "games", description: "Games played, tiebreaks included", extract: |match_sim| match_sim.games_played.len() as f64 },
+    Metric { name: "tiebreaks", description: "Tiebreaks played", extract: |match_sim| match_sim.games_played.iter().filter(|game| game.tiebreak).count() as f64 },
+    Metric {
+        name: "breaks",
+        description: "Service games lost",
+        extract: |match_sim| match_sim.games_played.iter().filter(|game| !game.tiebreak && game.winner != game.server).count() as f64,
+    },
+    Metric { name: "aces", description: "Aces by both players", extract: |match_sim| (match_sim.match_stat(0, "aces") + match_sim.match_stat(1, "aces")) as f64 },
+    Metric {
+        name: "longest_game",
+        description: "Points in the longest game",
+        extract: |match_sim| match_sim.games_played.iter().map(|game| game.points).max().unwrap_or(0) as f64,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Metric> {
+    METRICS.iter().find(|metric| metric.name == name)
+}
+
+// Names in `names` that are not metrics, as a message listing the ones there are
+pub fn unknown(names: &[String]) -> Option<String> {
+    let unknown: Vec<&str> = names.iter().map(String::as_str).filter(|name| find(name).is_none()).collect();
+    if unknown.is_empty() {
+        return None;
+    }
+    let known: Vec<&str> = METRICS.iter().map(|metric| metric.name).collect();
+    Some(format!("unknown metrics {}; the metrics are {}", unknown.join(", "), known.join(", ")))
+}
+
+// Equal-width bins from the smallest value to the largest, as (lower edge, bin width, count per bin)
+pub fn histogram(values: &[f64]) -> Option<(f64, f64, Vec<usize>)> {
+    let min = values.iter().copied().min_by(f64::total_cmp)?;
+    let max = values.iter().copied().max_by(f64::total_cmp)?;
+    let width = if max > min { (max - min) / HISTOGRAM_BINS as f64 } else { 1.0 };
+    let mut counts = vec![0; HISTOGRAM_BINS];
+    for value in values {
+        counts[(((value - min) / width) as usize).min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    Some((min, width, counts))
+}
@@ -0,0 +1,114 @@
+use rayon::prelude::*;
+
+use crate::{config_from_args, flag_value, match_seed, significance, table, ModelConfig, SimulationConfig, TennisMatch};
+
+// A player's chances won, split by whether they won the one before: [after a win, after a loss]
+#[derive(Clone, Copy, Default)]
+struct Split {
+    played: [u64; 2],
+    won: [u64; 2],
+}
+
+impl Split {
+    fn add(&mut self, won_before: bool, won: bool) {
+        let side = if won_before { 0 } else { 1 };
+        self.played[side] += 1;
+        self.won[side] += won as u64;
+    }
+
+    fn merge(mut self, other: Split) -> Split {
+        for side in 0..2 {
+            self.played[side] += other.played[side];
+            self.won[side] += other.won[side];
+        }
+        self
+    }
+
+    fn share(&self, side: usize) -> String {
+        format!("{:.2}% ({})", self.won[side] as f64 / self.played[side].max(1) as f64 * 100.0, self.played[side])
+    }
+
+    // The win rate after a win minus the rate after a loss, in percentage points, with its 95% interval
+    fn effect(&self) -> (f64, String) {
+        let test = significance::two_proportion_z_test(self.won[1], self.played[1], self.won[0], self.played[0]);
+        let (low, high) = test.confidence_interval;
+        let effect = (test.p2 - test.p1) * 100.0;
+        (effect, format!("{:+.2} [{:+.2}, {:+.2}]", effect, low * 100.0, high * 100.0))
+    }
+}
+
+// The splits of a set of matches for each player (0 = player1): their serve points by whether they won the
+// point before, and their service games, tiebreaks aside, by whether they won the game before
+#[derive(Clone, Copy, Default)]
+struct Splits {
+    points: [Split; 2],
+    games: [Split; 2],
+}
+
+impl Splits {
+    fn merge(self, other: Splits) -> Splits {
+        Splits {
+            points: [0, 1].map(|player| self.points[player].merge(other.points[player])),
+            games: [0, 1].map(|player| self.games[player].merge(other.games[player])),
+        }
+    }
+}
+
+// Plays matches 1..=matches of `config` under `model` and splits their points and games
+fn measure(config: &SimulationConfig, model: &ModelConfig, matches: usize) -> Splits {
+    let index = |name: &serde_json::Value| if name.as_str() == Some(config.player1.name.as_str()) { 0 } else { 1 };
+    (1..=matches)
+        .into_par_iter()
+        .map(|n| {
+            let mut match_sim = TennisMatch::new(config.player1.clone(), config.player2.clone(), config.scoring_rules(), model.clone(), match_seed(config.seed, n));
+            match_sim.play_match();
+            let mut splits = Splits::default();
+            for pair in match_sim.point_log.windows(2) {
+                let server = index(&pair[1]["server"]);
+                splits.points[server].add(index(&pair[0]["winner"]) == server, index(&pair[1]["winner"]) == server);
+            }
+            for pair in match_sim.games_played.windows(2).filter(|pair| !pair[1].tiebreak) {
+                let server = pair[1].server;
+                splits.games[server].add(pair[0].winner == server, pair[1].winner == server);
+            }
+            splits
+        })
+        .reduce(Splits::default, Splits::merge)
+}
+
+// momentum [--config <config.json>] [--matches <n>] [--seed <n>] [--plain]
+// Measures what the momentum model does: each player's serve points won after winning and after losing the
+// point before, and service games held after winning and losing the game before, in the configured model and
+// in the same matches with momentum turned off. The score alone makes the splits differ (the pressure curve,
+// the ace model's lead), so the momentum effect is the difference between the two runs.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let config = config_from_args(args)?;
+    let matches = match flag_value(args, "--matches") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --matches: {}", e))?,
+        None => config.num_simulations,
+    };
+    let mut without = config.model.clone();
+    without.momentum.strength = 0.0;
+    without.momentum.ace_strength = 0.0;
+    let runs = [measure(&config, &config.model, matches), measure(&config, &without, matches)];
+
+    let momentum = &config.model.momentum;
+    println!(
+        "Momentum effect: {} vs {}, {} matches (seed {}) with momentum (strength {}, ace strength {}) and without, win rates with the number of chances:",
+        config.player1.name, config.player2.name, matches, config.seed, momentum.strength, momentum.ace_strength
+    );
+    let plain = args.iter().any(|arg| arg == "--plain");
+    let mut output = table::new(&["", config.player1.name.as_str(), config.player2.name.as_str()], plain);
+    // [with momentum, without][player] for each measure
+    for (label, before, splits) in [("Serve point", "point", runs.map(|run| run.points)), ("Service game", "game", runs.map(|run| run.games))] {
+        for (mode, split) in ["with momentum", "without momentum"].iter().zip(&splits) {
+            output.add_row(vec![format!("{} after winning the {} before, {}", label, before, mode), split[0].share(0), split[1].share(0)]);
+            output.add_row(vec![format!("{} after losing the {} before, {}", label, before, mode), split[0].share(1), split[1].share(1)]);
+            output.add_row(vec![format!("{}, effect of winning the {} before (pts, 95% interval), {}", label, before, mode), split[0].effect().1, split[1].effect().1]);
+        }
+        let momentum_effect = |player: usize| format!("{:+.2}", splits[0][player].effect().0 - splits[1][player].effect().0);
+        output.add_row(vec![format!("{}, effect of momentum (pts)", label), momentum_effect(0), momentum_effect(1)]);
+    }
+    println!("{}", output);
+    Ok(())
+}
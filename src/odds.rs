@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{check, find_player, flag_value, load_players, simulate_match_parallel, Player, SimulationConfig};
+
+// How logged match win probabilities are turned into synthetic in-play prices
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PriceSeriesConfig {
+    // Bookmaker margin; 0.05 prices the two sides to a 105% book
+    pub margin: f64,
+    // Half-width of the uniform noise added to the win probability on the logit scale
+    pub noise: f64,
+}
+
+// Adds `<player>_price` decimal odds to every logged point, derived from player1's match win
+// probability so both prices always come from the same (noisy) probability
+pub fn add_price_series(points: &mut [HashMap<String, serde_json::Value>], player1: &Player, player2: &Player, config: &PriceSeriesConfig, rng: &mut impl Rng) {
+    let prob_column = format!("{}_match_win_prob", player1.name);
+    for point in points.iter_mut() {
+        let prob = point[&prob_column].as_f64().unwrap_or(0.5).clamp(0.01, 0.99);
+        let noise = if config.noise > 0.0 { rng.gen_range(-config.noise..=config.noise) } else { 0.0 };
+        let noisy_prob = 1.0 / (1.0 + libm::exp(-(libm::log(prob / (1.0 - prob)) + noise)));
+
+        let book = 1.0 + config.margin;
+        let prices = [1.0 / (noisy_prob * book), 1.0 / ((1.0 - noisy_prob) * book)];
+        for (player, price) in [player1, player2].iter().zip(prices) {
+            point.insert(format!("{}_price", player.name), serde_json::Value::from((price * 100.0).round() / 100.0));
+        }
+    }
+}
+
+// Bookmaker prices for one matchup, as decimal odds for player1 and player2
+#[derive(Deserialize)]
+struct MarketOdds {
+    player1: String,
+    player2: String,
+    odds: [f64; 2],
+    #[serde(default = "default_best_of")]
+    best_of: i32,
+    #[serde(default)]
+    grand_slam: bool,
+}
+
+fn default_best_of() -> i32 {
+    3
+}
+
+// Reads a JSON list of matchups, or a CSV with a header naming the columns
+// player1,player2,odds1,odds2 and optionally best_of,grand_slam
+fn load_market_odds(path: &str) -> Result<Vec<MarketOdds>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    if path.ends_with(".json") {
+        return serde_json::from_str(&contents).map_err(|e| format!("invalid odds file {}: {}", path, e));
+    }
+
+    let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<&str> = lines.next().unwrap_or("").split(',').map(str::trim).collect();
+    let mut market = Vec::new();
+    for (line_number, line) in lines.enumerate() {
+        let values: HashMap<&str, &str> = header.iter().copied().zip(line.split(',').map(str::trim)).collect();
+        let field = |name: &str| values.get(name).copied().ok_or_else(|| format!("{}: row {} has no {} column", path, line_number + 2, name));
+        let number = |name: &str| field(name)?.parse::<f64>().map_err(|e| format!("{}: row {} has an invalid {}: {}", path, line_number + 2, name, e));
+        market.push(MarketOdds {
+            player1: field("player1")?.to_string(),
+            player2: field("player2")?.to_string(),
+            odds: [number("odds1")?, number("odds2")?],
+            best_of: values.get("best_of").and_then(|value| value.parse().ok()).unwrap_or_else(default_best_of),
+            grand_slam: values.get("grand_slam").is_some_and(|value| *value == "true"),
+        });
+    }
+    Ok(market)
+}
+
+// edge <odds.csv|odds.json> --players <players.json> [--config <config.json>] [--threshold <edge>]
+pub fn run_edge_report(args: &[String]) -> Result<(), String> {
+    let odds_path = args.first().filter(|arg| !arg.starts_with("--")).ok_or("usage: edge <odds file> --players <players file>")?;
+    let players = load_players(flag_value(args, "--players").ok_or("edge requires --players <players file>")?)?;
+    let base_config = match flag_value(args, "--config") {
+        Some(path) => SimulationConfig::from_file(path)?,
+        None => SimulationConfig::default(),
+    };
+    check::reject_errors(&base_config)?;
+    let threshold = match flag_value(args, "--threshold") {
+        Some(value) => value.parse::<f64>().map_err(|e| format!("invalid --threshold: {}", e))?,
+        None => 0.05,
+    };
+
+    let market = load_market_odds(odds_path)?;
+    println!("Edge report ({} simulations per matchup, value threshold {:+.1}%):", base_config.num_simulations, threshold * 100.0);
+    println!("{:<20} {:>9} {:>10} {:>11} {:>9}", "player", "sim prob", "fair odds", "market odds", "edge");
+
+    let mut value_count = 0;
+    for matchup in &market {
+        let config = SimulationConfig {
+            player1: find_player(&players, &matchup.player1)?.clone(),
+            player2: find_player(&players, &matchup.player2)?.clone(),
+            best_of: matchup.best_of,
+            grand_slam: matchup.grand_slam,
+            model: base_config.model.clone(),
+            num_simulations: base_config.num_simulations,
+            batch_size: base_config.batch_size,
+            log_interval: usize::MAX,
+            ..SimulationConfig::default()
+        };
+        let report = simulate_match_parallel(&config, &[], None);
+
+        let overround = 1.0 / matchup.odds[0] + 1.0 / matchup.odds[1] - 1.0;
+        println!("{} vs {} (market margin {:.1}%)", config.player1.name, config.player2.name, overround * 100.0);
+        for (name, odds) in [(&config.player1.name, matchup.odds[0]), (&config.player2.name, matchup.odds[1])] {
+            let prob = report.win_probability(name);
+            let fair_odds = if prob > 0.0 { 1.0 / prob } else { f64::INFINITY };
+            let edge = prob * odds - 1.0;
+            let flag = if edge >= threshold {
+                value_count += 1;
+                "  VALUE"
+            } else {
+                ""
+            };
+            println!(" {:<19} {:>8.1}% {:>10.2} {:>11.2} {:>+8.1}%{}", name, prob * 100.0, fair_odds, odds, edge * 100.0, flag);
+        }
+    }
+
+    println!("\n{} value opportunities across {} matchups", value_count, market.len());
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use crate::run_dir::RunDirectory;
+use crate::sink::ResultSink;
+use crate::{BatchResult, SimulationReport};
+
+// The kinds of extreme match kept by `--capture-outliers`
+const CATEGORIES: [&str; 4] = ["longest", "most_aces", "biggest_comeback", "lowest_nadir"];
+
+struct Outlier {
+    // Higher is more extreme
+    score: f64,
+    description: String,
+    summary: serde_json::Value,
+    points: Vec<HashMap<String, serde_json::Value>>,
+}
+
+// Keeps the N most extreme matches of each category, with their point logs, as the run goes, so they can be
+// written out without logging every match
+pub struct OutlierSink {
+    keep: usize,
+    captured: Mutex<HashMap<&'static str, Vec<Outlier>>>,
+}
+
+impl OutlierSink {
+    pub fn new(keep: usize) -> Self {
+        OutlierSink { keep, captured: Mutex::new(HashMap::new()) }
+    }
+
+    // Writes each captured match's point log to `outliers/<category>_<rank>_match_<k>.json` and an index of
+    // them to `outliers/outliers.json`, and returns a line per category for the report
+    pub fn write(&self, run_dir: &RunDirectory) -> Result<Vec<String>, String> {
+        let dir = run_dir.path.join("outliers");
+        fs::create_dir_all(&dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+        let captured = self.captured.lock().unwrap();
+        let mut index = serde_json::Map::new();
+        let mut lines = Vec::new();
+        for category in CATEGORIES {
+            let outliers = captured.get(category).map_or(&[][..], Vec::as_slice);
+            let mut entries = Vec::new();
+            for (rank, outlier) in outliers.iter().enumerate() {
+                let file = format!("{}_{}_match_{}.json", category, rank + 1, outlier.summary["match"]);
+                let log = serde_json::to_string_pretty(&outlier.points).map_err(|e| e.to_string())?;
+                run_dir.write(&format!("outliers/{}", file), log.as_bytes())?;
+                entries.push(serde_json::json!({ "rank": rank + 1, "description": outlier.description, "file": file, "summary": outlier.summary }));
+            }
+            if let Some(most) = outliers.first() {
+                lines.push(format!(" {}: match {} ({})", category, most.summary["match"], most.description));
+            }
+            index.insert(category.to_string(), serde_json::Value::Array(entries));
+        }
+        let index = serde_json::to_string_pretty(&index).map_err(|e| e.to_string())?;
+        run_dir.write("outliers/outliers.json", index.as_bytes())?;
+        Ok(lines)
+    }
+}
+
+impl ResultSink for OutlierSink {
+    fn on_batch(&self, _batch: &BatchResult) {}
+
+    fn on_complete(&self, _report: &SimulationReport) {}
+
+    fn on_match(&self, summary: &serde_json::Value, points: &[HashMap<String, serde_json::Value>]) {
+        let measures = measures(summary, points);
+        let match_number = summary["match"].as_u64().unwrap_or(0);
+        let mut captured = self.captured.lock().unwrap();
+        for (category, (score, description)) in CATEGORIES.into_iter().zip(measures) {
+            let outliers = captured.entry(category).or_default();
+            // Ties go to the earlier match, so the captured matches don't depend on the order batches finish in
+            let rank = outliers.iter()
+                .position(|outlier| score > outlier.score || (score == outlier.score && match_number < outlier.summary["match"].as_u64().unwrap_or(0)))
+                .unwrap_or(outliers.len());
+            if rank < self.keep {
+                outliers.insert(rank, Outlier { score, description, summary: summary.clone(), points: points.to_vec() });
+                outliers.truncate(self.keep);
+            }
+        }
+    }
+}
+
+// How extreme a match is in each category, in the order of CATEGORIES, with a description of it
+fn measures(summary: &serde_json::Value, points: &[HashMap<String, serde_json::Value>]) -> [(f64, String); 4] {
+    let aces: i64 = summary["stats"].as_object().map_or(0, |stats| stats.values().map(|player| player["aces"].as_i64().unwrap_or(0)).sum());
+
+    // The furthest the winner was behind, in sets and then in games of the set; the scores are server first, and
+    // a set-ending point still shows the finished set's games
+    let winner = summary["winner"].as_str().unwrap_or("");
+    let behind = |point: &HashMap<String, serde_json::Value>, key: &str| {
+        let score = point.get(key).and_then(|value| value.as_str()).unwrap_or("0-0");
+        let (server, receiver) = score.split_once('-').unwrap_or(("0", "0"));
+        let (server, receiver) = (server.parse::<i32>().unwrap_or(0), receiver.parse::<i32>().unwrap_or(0));
+        if point["server"] == winner { receiver - server } else { server - receiver }
+    };
+    let mut deficit = (0, 0);
+    let mut sets_behind = 0;
+    for point in points {
+        let sets = behind(point, "set_score");
+        let games = if sets == sets_behind { behind(point, "game_score") } else { 0 };
+        deficit = deficit.max((sets, games));
+        sets_behind = sets;
+    }
+    let (sets, games) = deficit;
+
+    let nadir = summary["winner_min_win_prob"].as_f64().unwrap_or(0.0);
+    [
+        (points.len() as f64, format!("{} points, {} shots", points.len(), summary["shots"])),
+        (aces as f64, format!("{} aces", aces)),
+        ((sets * 100 + games) as f64, format!("{} won from {} set(s) and {} game(s) down", winner, sets, games)),
+        (-nadir, format!("{} won from a {:.1}% chance", winner, nadir * 100.0)),
+    ]
+}
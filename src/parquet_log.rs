@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Float64Builder, Int64Builder, ListBuilder, StringBuilder, StructBuilder};
+use arrow::datatypes::{DataType, Field, Fields, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+
+use crate::RunMetadata;
+
+// Writes one batch of point log entries to a Parquet file. String values become Utf8
+// columns, integers Int64 and other numbers Float64 columns, in the same order as the CSV log, followed
+// by a `shots` column holding the shots of each point as a list of structs (game logs have none). The
+// run metadata is stored as key-value metadata of the file.
+pub fn write_point_log(path: &Path, columns: &[String], points: &[HashMap<String, serde_json::Value>], metadata: &RunMetadata) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("could not create {}: {}", parent.display(), e))?;
+    }
+
+    let mut fields = Vec::new();
+    let mut arrays: Vec<ArrayRef> = Vec::new();
+
+    for column in columns {
+        let is_string = points.first().is_none_or(|point| point[column].is_string());
+        let is_integer = points.first().is_some_and(|point| point[column].is_i64());
+        if is_string {
+            let mut builder = StringBuilder::new();
+            for point in points {
+                builder.append_value(point[column].as_str().unwrap_or(""));
+            }
+            fields.push(Field::new(column, DataType::Utf8, false));
+            arrays.push(Arc::new(builder.finish()));
+        } else if is_integer {
+            let mut builder = Int64Builder::new();
+            for point in points {
+                builder.append_value(point[column].as_i64().unwrap_or(0));
+            }
+            fields.push(Field::new(column, DataType::Int64, false));
+            arrays.push(Arc::new(builder.finish()));
+        } else {
+            let mut builder = Float64Builder::new();
+            for point in points {
+                builder.append_value(point[column].as_f64().unwrap_or(0.0));
+            }
+            fields.push(Field::new(column, DataType::Float64, false));
+            arrays.push(Arc::new(builder.finish()));
+        }
+    }
+
+    if points.first().is_some_and(|point| point.contains_key("shots")) {
+        let shot_fields = Fields::from(vec![
+            Field::new("shot", DataType::Utf8, false),
+            Field::new("direction", DataType::Utf8, true),
+            Field::new("outcome", DataType::Utf8, false),
+        ]);
+        let mut shots_builder = ListBuilder::new(StructBuilder::from_fields(shot_fields, 0));
+        for point in points {
+            let shot_builder = shots_builder.values();
+            for shot in point["shots"].as_array().into_iter().flatten() {
+                shot_builder.field_builder::<StringBuilder>(0).unwrap().append_value(shot["shot"].as_str().unwrap_or(""));
+                shot_builder.field_builder::<StringBuilder>(1).unwrap().append_option(shot["direction"].as_str());
+                shot_builder.field_builder::<StringBuilder>(2).unwrap().append_value(shot["outcome"].as_str().unwrap_or(""));
+                shot_builder.append(true);
+            }
+            shots_builder.append(true);
+        }
+        let shots: ArrayRef = Arc::new(shots_builder.finish());
+        fields.push(Field::new("shots", shots.data_type().clone(), false));
+        arrays.push(shots);
+    }
+
+    let schema = Arc::new(Schema::new(fields));
+    let batch = RecordBatch::try_new(schema.clone(), arrays).map_err(|e| e.to_string())?;
+
+    let file = File::create(path).map_err(|e| format!("could not create {}: {}", path.display(), e))?;
+    let key_values = metadata.pairs().into_iter().map(|(key, value)| KeyValue::new(key.to_string(), value)).collect();
+    let properties = WriterProperties::builder().set_key_value_metadata(Some(key_values)).build();
+    let mut writer = ArrowWriter::try_new(file, schema, Some(properties)).map_err(|e| e.to_string())?;
+    writer.write(&batch).map_err(|e| e.to_string())?;
+    writer.close().map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use rayon::prelude::*;
+
+use crate::scoring::ScoringRules;
+use crate::what_if::MatchState;
+use crate::{config_from_args, find_player, flag_value, match_seed, ModelConfig, Player, TennisMatch};
+
+// Holds and breaks of a set of service games, with how many games took each number of points
+#[derive(Default)]
+pub struct GameResults {
+    pub holds: usize,
+    pub breaks: usize,
+    // (server won, points played) -> games
+    pub lengths: BTreeMap<(bool, usize), usize>,
+}
+
+// How a set is decided at 6-6
+#[derive(Clone, Copy, PartialEq)]
+pub enum SetFormat {
+    TiebreakTo7,
+    // As in the final set of a grand slam
+    TiebreakTo10,
+}
+
+// Winners and final games of a set of single sets, in player order
+#[derive(Default)]
+pub struct SetResults {
+    pub wins: [usize; 2],
+    pub scores: BTreeMap<[i32; 2], usize>,
+}
+
+// Plays `simulations` service games of `server` against `returner` from 0-0. Game n draws from the per-match
+// seed of n.
+pub fn simulate_game(server: &Player, returner: &Player, model: &ModelConfig, seed: u64, simulations: usize) -> GameResults {
+    (1..=simulations)
+        .into_par_iter()
+        .map(|n| {
+            let mut match_sim = TennisMatch::new(server.clone(), returner.clone(), ScoringRules::standard(1, false), model.clone(), match_seed(seed, n));
+            match_sim.resume_from(&MatchState { sets: [0, 0], games: [0, 0], points: [0, 0], server: 0 });
+            let (winner, _) = match_sim.play_game();
+            (winner.name == server.name, match_sim.point_log.len())
+        })
+        .fold(GameResults::default, |mut results, (held, points)| {
+            if held { results.holds += 1 } else { results.breaks += 1 }
+            *results.lengths.entry((held, points)).or_insert(0) += 1;
+            results
+        })
+        .reduce(GameResults::default, |mut results, other| {
+            results.holds += other.holds;
+            results.breaks += other.breaks;
+            for (length, count) in other.lengths {
+                *results.lengths.entry(length).or_insert(0) += count;
+            }
+            results
+        })
+}
+
+// Plays `simulations` single sets between `player1` and `player2`, each starting with a coin toss for the
+// first serve. Set n draws from the per-match seed of n.
+pub fn simulate_set(player1: &Player, player2: &Player, format: SetFormat, model: &ModelConfig, seed: u64, simulations: usize) -> SetResults {
+    (1..=simulations)
+        .into_par_iter()
+        .map(|n| {
+            // A one-set match is the set on its own; as the final set of a grand slam its tiebreak goes to 10
+            let mut match_sim = TennisMatch::new(player1.clone(), player2.clone(), ScoringRules::standard(1, format == SetFormat::TiebreakTo10), model.clone(), match_seed(seed, n));
+            match_sim.play_match();
+            // The games are reset once the set is over, but the last point logged still shows them, server first
+            let last = match_sim.point_log.last().unwrap();
+            let (server, receiver) = last["game_score"].as_str().unwrap().split_once('-').unwrap();
+            let (server, receiver) = (server.parse::<i32>().unwrap(), receiver.parse::<i32>().unwrap());
+            if last["server"] == player1.name.as_str() { [server, receiver] } else { [receiver, server] }
+        })
+        .fold(SetResults::default, |mut results, score| {
+            results.wins[if score[0] > score[1] { 0 } else { 1 }] += 1;
+            *results.scores.entry(score).or_insert(0) += 1;
+            results
+        })
+        .reduce(SetResults::default, |mut results, other| {
+            for player in 0..2 {
+                results.wins[player] += other.wins[player];
+            }
+            for (score, count) in other.scores {
+                *results.scores.entry(score).or_insert(0) += count;
+            }
+            results
+        })
+}
+
+fn simulations(args: &[String], default: usize) -> Result<usize, String> {
+    match flag_value(args, "--simulations") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --simulations: {}", e)),
+        None => Ok(default),
+    }
+}
+
+// game [--config <config.json>] [--server <player>] [--simulations <n>] [--seed <n>]
+// Simulates service games of one player (player1 by default) and prints the hold rate and how games were won
+pub fn run_game(args: &[String]) -> Result<(), String> {
+    let config = config_from_args(args)?;
+    let players = [config.player1.clone(), config.player2.clone()];
+    let server = match flag_value(args, "--server") {
+        Some(name) => find_player(&players, name)?.clone(),
+        None => players[0].clone(),
+    };
+    let returner = if server.name == players[0].name { &players[1] } else { &players[0] };
+    let simulations = simulations(args, config.num_simulations)?;
+
+    let results = simulate_game(&server, returner, &config.model, config.seed, simulations);
+    let share = |count: usize| count as f64 / simulations.max(1) as f64 * 100.0;
+    println!("{} serving to {}, {} games (seed {}):", server.name, returner.name, simulations, config.seed);
+    println!(" Held: {:.2}%, broken: {:.2}%", share(results.holds), share(results.breaks));
+    for (held, label) in [(true, "Held"), (false, "Broken")] {
+        // Games of 4 to 6 points end at 4-0, 4-1 and 4-2; longer ones went to deuce
+        let at = |points: usize| share(results.lengths.get(&(held, points)).copied().unwrap_or(0));
+        let deuce: usize = results.lengths.iter().filter(|((won, points), _)| *won == held && *points >= 8).map(|(_, count)| count).sum();
+        println!(" {} to love {:.2}%, to 15 {:.2}%, to 30 {:.2}%, after deuce {:.2}%", label, at(4), at(5), at(6), share(deuce));
+    }
+    Ok(())
+}
+
+// set [--config <config.json>] [--tiebreak 7|10] [--simulations <n>] [--seed <n>]
+// Simulates single sets and prints each player's chance of winning one and the distribution of scorelines
+pub fn run_set(args: &[String]) -> Result<(), String> {
+    let config = config_from_args(args)?;
+    let format = match flag_value(args, "--tiebreak") {
+        Some("7") | None => SetFormat::TiebreakTo7,
+        Some("10") => SetFormat::TiebreakTo10,
+        Some(other) => return Err(format!("invalid --tiebreak '{}'; tiebreaks are played to 7 or 10", other)),
+    };
+    let simulations = simulations(args, config.num_simulations)?;
+
+    let results = simulate_set(&config.player1, &config.player2, format, &config.model, config.seed, simulations);
+    let share = |count: usize| count as f64 / simulations.max(1) as f64 * 100.0;
+    let tiebreak = if format == SetFormat::TiebreakTo10 { 10 } else { 7 };
+    println!("{} vs {}, {} sets with a tiebreak to {} at 6-6 (seed {}):", config.player1.name, config.player2.name, simulations, tiebreak, config.seed);
+    for (player, wins) in [&config.player1, &config.player2].iter().zip(results.wins) {
+        println!(" {}: {:.2}%", player.name, share(wins));
+    }
+    println!("Scorelines ({} first):", config.player1.name);
+    for winner in 0..2 {
+        for (score, count) in results.scores.iter().filter(|(score, _)| (score[0] > score[1]) == (winner == 0)) {
+            println!(" {}-{}: {:.2}%", score[0], score[1], share(*count));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::exact::hold_probability;
+use crate::scoring::ScoringRules;
+use crate::sink::ResultSink;
+use crate::what_if::parse_points;
+use crate::{BatchResult, SimulationConfig, SimulationReport, TennisMatch, FORECAST_MODELS};
+
+// Largest distance allowed between a logged probability and the one recomputed from the logged score
+const TOLERANCE: f64 = 1e-9;
+// Points of the lead at the end of a game whose score the log only gives as "GAME"
+const MAX_GAME_LEAD: i32 = 4;
+// Failures listed in the audit's error; the rest are only counted
+const MAX_REPORTED: usize = 10;
+
+// Whose side a pair of values is read from. The match keeps its scores in player order (0 = player1) and the
+// serve model works from the server's side; conversions between the two go through here rather than indexing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Perspective {
+    // A player by index (0 = player1)
+    Player(usize),
+    Server,
+}
+
+impl Perspective {
+    // The player (0 = player1) this side is when `server` is serving
+    pub fn player(self, server: usize) -> usize {
+        match self {
+            Perspective::Player(player) => player,
+            Perspective::Server => server,
+        }
+    }
+
+    // `pair`, kept in player order, as [this side, the other side]
+    pub fn orient<T: Copy>(self, server: usize, pair: [T; 2]) -> [T; 2] {
+        let player = self.player(server);
+        [pair[player], pair[1 - player]]
+    }
+}
+
+// The chance of winning something, held once for the match so the two players' chances always add up to 1.
+// It is built from whichever side a model works from and read back per player.
+#[derive(Clone, Copy, Debug)]
+pub struct WinProbability {
+    player1: f64,
+}
+
+impl WinProbability {
+    // `prob` is the chance of the side `perspective` when `server` is serving; it is clamped to [0, 1]
+    pub fn new(perspective: Perspective, server: usize, prob: f64) -> Self {
+        let prob = prob.clamp(0.0, 1.0);
+        WinProbability { player1: if perspective.player(server) == 0 { prob } else { 1.0 - prob } }
+    }
+
+    // The chance of player `player` (0 = player1)
+    pub fn player(self, player: usize) -> f64 {
+        if player == 0 { self.player1 } else { 1.0 - self.player1 }
+    }
+}
+
+// --audit-probabilities: checks every logged point of every match as it finishes against the forecast models
+// worked out again from the logged score, read from the server's side and turned into player order here rather
+// than through WinProbability. The set and match models follow from the score alone and the game model from
+// the score and the logged serve chance; the next point model also depends on the match's history (momentum,
+// aces and double faults in the game), so it is only checked to be within those adjustments of the serve chance.
+pub struct ProbabilityAudit {
+    rules: ScoringRules,
+    player1: String,
+    // Most that momentum and a recent ace or double fault can move the next point forecast
+    history_margin: f64,
+    points: AtomicUsize,
+    // The number of failures and the first MAX_REPORTED of them
+    failures: Mutex<(usize, Vec<String>)>,
+}
+
+impl ProbabilityAudit {
+    pub fn new(config: &SimulationConfig) -> Self {
+        ProbabilityAudit {
+            rules: config.scoring_rules(),
+            player1: config.player1.name.clone(),
+            history_margin: config.model.momentum.cap + 0.03,
+            points: AtomicUsize::default(),
+            failures: Mutex::default(),
+        }
+    }
+
+    // Player1's probability from each of FORECAST_MODELS at a logged point, with the distance allowed from it
+    fn expected(&self, point: &HashMap<String, serde_json::Value>) -> Result<[(f64, f64); 4], String> {
+        let text = |key: &str| point.get(key).and_then(|value| value.as_str()).ok_or(format!("no {}", key));
+        let pair = |key: &str| -> Result<[i32; 2], String> {
+            let value = text(key)?;
+            let (a, b) = value.split_once('-').ok_or(format!("invalid {} '{}'", key, value))?;
+            Ok([a.parse().map_err(|_| format!("invalid {} '{}'", key, value))?, b.parse().map_err(|_| format!("invalid {} '{}'", key, value))?])
+        };
+        let server = if text("server")? == self.player1 { 0 } else { 1 };
+        let player1_serving = |prob: f64| if server == 0 { prob } else { 1.0 - prob };
+        // The logged scores are server first
+        let [sets, games] = [pair("set_score")?, pair("game_score")?].map(|score| Perspective::Server.orient(server, score));
+        let serve_prob = point.get("next_serve_win_prob").and_then(|value| value.as_f64()).ok_or("no next_serve_win_prob")?;
+
+        // The point score is logged as it was before the point ended a game or started a tiebreak, and the
+        // forecasts as they are after it: a game just won, a tiebreak at 0-0 or the next point of one
+        let point_score = text("point_score")?;
+        let game_won = if text("winner")? == text("server")? { 1.0 } else { 0.0 };
+        let (game, lead) = match point_score {
+            "GAME" if self.rules.tiebreak_at(sets, games).is_some() => (serve_prob, 0),
+            "GAME" => (game_won, MAX_GAME_LEAD),
+            _ if self.rules.tiebreak_at(sets, games).is_some() => {
+                let [won, lost] = parse_points(point_score, true)?;
+                (serve_prob, (won - lost).abs())
+            }
+            "Deuce" => (hold_probability(serve_prob, [3, 3], self.rules.no_ad), 0),
+            "Ad-In" | "Ad-Out" => (hold_probability(serve_prob, if point_score == "Ad-In" { [4, 3] } else { [3, 4] }, self.rules.no_ad), 1),
+            _ => match parse_points(point_score, false) {
+                Ok(points) => (hold_probability(serve_prob, points, self.rules.no_ad), (points[0] - points[1]).abs()),
+                // A tiebreak that the point ended
+                Err(_) => {
+                    let [won, lost] = parse_points(point_score, true)?;
+                    (game_won, (won - lost).abs())
+                }
+            },
+        };
+        Ok([
+            (player1_serving(serve_prob), 0.02 * lead as f64 + self.history_margin + TOLERANCE),
+            (player1_serving(game), TOLERANCE),
+            (TennisMatch::set_win_probability_from(games).clamp(0.0, 1.0), TOLERANCE),
+            (TennisMatch::match_win_probability_from(sets, games), TOLERANCE),
+        ])
+    }
+
+    // A line saying what was checked, or the failures found
+    pub fn result(&self) -> Result<String, String> {
+        let points = self.points.load(Ordering::SeqCst);
+        let (count, failures) = &*self.failures.lock().unwrap();
+        if *count == 0 {
+            return Ok(format!("Probability audit: the {} forecasts of both players match their models on all {} points", FORECAST_MODELS.len(), points));
+        }
+        let more = if *count > failures.len() { format!("\n  ... and {} more", count - failures.len()) } else { String::new() };
+        Err(format!("probability audit failed on {} of {} point forecasts:\n  {}{}", count, points * FORECAST_MODELS.len(), failures.join("\n  "), more))
+    }
+}
+
+impl ResultSink for ProbabilityAudit {
+    fn on_batch(&self, _batch: &BatchResult) {}
+
+    fn on_complete(&self, _report: &SimulationReport) {}
+
+    fn on_match(&self, summary: &serde_json::Value, points: &[HashMap<String, serde_json::Value>]) {
+        let players = [summary["player1"].as_str().unwrap_or(""), summary["player2"].as_str().unwrap_or("")];
+        let mut found = Vec::new();
+        for point in points {
+            let expected = self.expected(point);
+            for (index, model) in FORECAST_MODELS.iter().enumerate() {
+                let probs = players.map(|player| point.get(&format!("{}_{}", player, model)).and_then(|value| value.as_f64()));
+                let failure = match (probs, &expected) {
+                    (_, Err(e)) => e.clone(),
+                    ([Some(prob1), Some(prob2)], Ok(expected)) => {
+                        let (player1, allowed) = expected[index];
+                        if (prob1 - player1).abs() <= allowed && (prob2 - (1.0 - player1)).abs() <= allowed {
+                            continue;
+                        }
+                        format!("logged {} {}, {} {}; expected {} {}", players[0], prob1, players[1], prob2, players[0], player1)
+                    }
+                    _ => "missing".to_string(),
+                };
+                let score = ["server", "point_score", "game_score", "set_score"].map(|key| point.get(key).and_then(|value| value.as_str()).unwrap_or("?"));
+                found.push(format!("match {} point {} ({} serving at {}, {}, {}) {}: {}", summary["match"], point.get("point_number").unwrap_or(&serde_json::Value::Null), score[0], score[1], score[2], score[3], model, failure));
+            }
+        }
+        self.points.fetch_add(points.len(), Ordering::SeqCst);
+        if !found.is_empty() {
+            let mut failures = self.failures.lock().unwrap();
+            failures.0 += found.len();
+            let room = MAX_REPORTED.saturating_sub(failures.1.len());
+            failures.1.extend(found.into_iter().take(room));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orient_reads_a_pair_from_either_side() {
+        let pair = [3, 1];
+        assert_eq!(Perspective::Player(0).orient(0, pair), [3, 1]);
+        assert_eq!(Perspective::Player(0).orient(1, pair), [3, 1]);
+        assert_eq!(Perspective::Player(1).orient(0, pair), [1, 3]);
+        assert_eq!(Perspective::Player(1).orient(1, pair), [1, 3]);
+        assert_eq!(Perspective::Server.orient(0, pair), [3, 1]);
+        assert_eq!(Perspective::Server.orient(1, pair), [1, 3]);
+    }
+
+    #[test]
+    fn win_probability_is_held_for_the_side_it_was_built_from() {
+        for server in 0..2 {
+            let from_server = WinProbability::new(Perspective::Server, server, 0.7);
+            assert_eq!(from_server.player(server), 0.7);
+            assert!((from_server.player(1 - server) - 0.3).abs() < 1e-12);
+            for player in 0..2 {
+                let from_player = WinProbability::new(Perspective::Player(player), server, 0.7);
+                assert_eq!(from_player.player(player), 0.7);
+                assert!((from_player.player(1 - player) - 0.3).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn win_probability_is_clamped() {
+        assert_eq!(WinProbability::new(Perspective::Server, 1, 1.2).player(1), 1.0);
+        assert_eq!(WinProbability::new(Perspective::Player(0), 0, -0.1).player(1), 1.0);
+    }
+}
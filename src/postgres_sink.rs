@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::types::Json;
+
+use crate::sink::ResultSink;
+use crate::{BatchResult, RunMetadata, SimulationConfig, SimulationReport};
+
+// Created on connect if missing. Each worker machine writes its own run; summaries are upserted per batch so
+// a table can be queried while runs are still going.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    run_id TEXT PRIMARY KEY,
+    hostname TEXT NOT NULL,
+    schema_version INTEGER NOT NULL,
+    crate_version TEXT NOT NULL,
+    config_hash TEXT NOT NULL,
+    -- Text because seeds use the full unsigned 64-bit range
+    seed TEXT NOT NULL,
+    player1 TEXT NOT NULL,
+    player2 TEXT NOT NULL,
+    best_of INTEGER NOT NULL,
+    grand_slam BOOLEAN NOT NULL,
+    num_simulations BIGINT NOT NULL,
+    status TEXT NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    finished_at TIMESTAMPTZ,
+    execution_time_ms BIGINT
+);
+CREATE TABLE IF NOT EXISTS matchup_summaries (
+    run_id TEXT NOT NULL REFERENCES runs (run_id),
+    player1 TEXT NOT NULL,
+    player2 TEXT NOT NULL,
+    matches BIGINT NOT NULL,
+    player1_wins BIGINT NOT NULL,
+    player2_wins BIGINT NOT NULL,
+    total_shots BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (run_id, player1, player2)
+);
+CREATE TABLE IF NOT EXISTS point_samples (
+    run_id TEXT NOT NULL REFERENCES runs (run_id),
+    match_number BIGINT NOT NULL,
+    point_number BIGINT NOT NULL,
+    point JSONB NOT NULL,
+    PRIMARY KEY (run_id, match_number, point_number)
+);
+";
+
+// Writes run metadata, per-matchup summaries and the point logs of every `sample_every`th match to Postgres
+pub struct PostgresSink {
+    runtime: tokio::runtime::Runtime,
+    pool: PgPool,
+    run_id: String,
+    player1: String,
+    player2: String,
+    sample_every: usize,
+}
+
+impl PostgresSink {
+    pub fn connect(url: &str, config: &SimulationConfig, sample_every: usize) -> Result<Self, String> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|e| e.to_string())?;
+        let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_string());
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_millis()).unwrap_or(0);
+        let run_id = format!("{}-{}", hostname, millis);
+        let metadata = RunMetadata::new(config);
+
+        let pool = runtime.block_on(async {
+            let pool = PgPoolOptions::new().max_connections(4).connect(url).await
+                .map_err(|e| format!("could not connect to {}: {}", url, e))?;
+            sqlx::raw_sql(SCHEMA).execute(&pool).await.map_err(|e| format!("could not create the schema: {}", e))?;
+            sqlx::query(
+                "INSERT INTO runs (run_id, hostname, schema_version, crate_version, config_hash, seed, player1, player2, best_of, grand_slam, num_simulations, status)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, 'running')
+                 ON CONFLICT (run_id) DO UPDATE SET status = 'running'",
+            )
+            .bind(&run_id)
+            .bind(&hostname)
+            .bind(metadata.schema_version as i32)
+            .bind(metadata.crate_version)
+            .bind(&metadata.config_hash)
+            .bind(metadata.seed.to_string())
+            .bind(&config.player1.name)
+            .bind(&config.player2.name)
+            .bind(config.best_of)
+            .bind(config.grand_slam)
+            .bind(config.num_simulations as i64)
+            .execute(&pool).await
+            .map_err(|e| format!("could not record the run: {}", e))?;
+            Ok::<_, String>(pool)
+        })?;
+
+        Ok(PostgresSink {
+            runtime,
+            pool,
+            run_id,
+            player1: config.player1.name.clone(),
+            player2: config.player2.name.clone(),
+            sample_every: sample_every.max(1),
+        })
+    }
+
+    async fn insert_points(&self, match_number: i64, points: &[HashMap<String, serde_json::Value>]) -> Result<(), sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        for point in points {
+            sqlx::query(
+                "INSERT INTO point_samples (run_id, match_number, point_number, point) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT DO NOTHING",
+            )
+            .bind(&self.run_id)
+            .bind(match_number)
+            .bind(point["point_number"].as_i64().unwrap_or(0))
+            .bind(Json(point))
+            .execute(&mut *transaction).await?;
+        }
+        transaction.commit().await
+    }
+}
+
+impl ResultSink for PostgresSink {
+    fn on_batch(&self, batch: &BatchResult) {
+        let wins = |player: &String| batch.match_wins.get(player).copied().unwrap_or(0) as i64;
+        let result = self.runtime.block_on(
+            sqlx::query(
+                "INSERT INTO matchup_summaries (run_id, player1, player2, matches, player1_wins, player2_wins, total_shots)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 ON CONFLICT (run_id, player1, player2) DO UPDATE SET
+                     matches = matchup_summaries.matches + EXCLUDED.matches,
+                     player1_wins = matchup_summaries.player1_wins + EXCLUDED.player1_wins,
+                     player2_wins = matchup_summaries.player2_wins + EXCLUDED.player2_wins,
+                     total_shots = matchup_summaries.total_shots + EXCLUDED.total_shots,
+                     updated_at = now()",
+            )
+            .bind(&self.run_id)
+            .bind(&self.player1)
+            .bind(&self.player2)
+            .bind(wins(&self.player1) + wins(&self.player2))
+            .bind(wins(&self.player1))
+            .bind(wins(&self.player2))
+            .bind(batch.total_shots as i64)
+            .execute(&self.pool),
+        );
+        if let Err(e) = result {
+            log::error!("postgres: could not update the matchup summary: {}", e);
+        }
+    }
+
+    fn on_complete(&self, report: &SimulationReport) {
+        let result = self.runtime.block_on(
+            sqlx::query("UPDATE runs SET status = 'complete', finished_at = now(), execution_time_ms = $2 WHERE run_id = $1")
+                .bind(&self.run_id)
+                .bind(report.execution_time as i64)
+                .execute(&self.pool),
+        );
+        if let Err(e) = result {
+            log::error!("postgres: could not mark run {} complete: {}", self.run_id, e);
+        }
+    }
+
+    fn on_match(&self, summary: &serde_json::Value, points: &[HashMap<String, serde_json::Value>]) {
+        let match_number = summary["match"].as_i64().unwrap_or(0);
+        if !(match_number as usize).is_multiple_of(self.sample_every) {
+            return;
+        }
+        if let Err(e) = self.runtime.block_on(self.insert_points(match_number, points)) {
+            log::error!("postgres: could not write the points of match {}: {}", match_number, e);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use redis::Commands;
+
+use crate::sink::ResultSink;
+use crate::{BatchResult, SimulationReport};
+
+// Publishes results to Redis pub/sub: live points of each match to `tennis:match:<match id>`,
+// batch and run summaries to `tennis:results`
+pub struct RedisSink {
+    connection: Mutex<redis::Connection>,
+}
+
+impl RedisSink {
+    pub fn connect(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("invalid redis url {}: {}", url, e))?;
+        let connection = client.get_connection().map_err(|e| format!("could not connect to {}: {}", url, e))?;
+        Ok(RedisSink { connection: Mutex::new(connection) })
+    }
+
+    // A failed publish only loses that message, so it is reported without stopping the simulation
+    fn publish(&self, channel: &str, message: serde_json::Value) {
+        let result: redis::RedisResult<i64> = self.connection.lock().unwrap().publish(channel, message.to_string());
+        if let Err(e) = result {
+            log::error!("redis publish to {} failed: {}", channel, e);
+        }
+    }
+}
+
+impl ResultSink for RedisSink {
+    fn on_batch(&self, batch: &BatchResult) {
+        self.publish("tennis:results", serde_json::json!({
+            "match_wins": batch.match_wins,
+            "total_shots": batch.total_shots,
+        }));
+    }
+
+    fn on_complete(&self, report: &SimulationReport) {
+        self.publish("tennis:results", serde_json::json!({
+            "match_wins": report.match_wins,
+            "total_shots": report.total_shots,
+            "execution_time": report.execution_time,
+            "complete": true,
+        }));
+    }
+
+    fn wants_matches(&self) -> bool {
+        false
+    }
+
+    fn on_point(&self, match_id: &str, point: &HashMap<String, serde_json::Value>) {
+        let mut message = point.clone();
+        message.insert("match_id".to_string(), serde_json::Value::from(match_id));
+        self.publish(&format!("tennis:match:{}", match_id), serde_json::json!(message));
+    }
+
+    fn on_match_complete(&self, match_id: &str, winner: &str) {
+        self.publish(&format!("tennis:match:{}", match_id), serde_json::json!({
+            "match_id": match_id,
+            "winner": winner,
+        }));
+    }
+}
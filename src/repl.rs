@@ -0,0 +1,138 @@
+use std::io::{BufRead, Write};
+
+use crate::check;
+use crate::what_if::{self, MatchState};
+use crate::{config_from_args, find_player, SimulationConfig};
+
+const HELP: &str = "commands:
+  prob at <score> [with <player> serving]   e.g. prob at 6-4 4-4 30-40 with Nadal serving?
+  simulate <n> [from here]                  play the match out n times from the current score
+  set <field> <value>                       e.g. set p1.serve 0.67, set best_of 3 (serve, ace and df are
+                                            short for serve_win_prob, ace_prob and double_fault_prob)
+  show                                      the current score and players
+  help, quit";
+
+// Playouts behind a quick `prob at` answer
+const QUICK_SIMULATIONS: usize = 2000;
+
+// repl [--config <config.json>] [--seed <n>]
+// Explores match states interactively: set a score, tweak the players and simulate from there
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut config = config_from_args(args)?;
+    let mut state = MatchState { sets: [0, 0], games: [0, 0], points: [0, 0], server: 0 };
+    println!("{} vs {}, best of {}; type help for commands", config.player1.name, config.player2.name, config.best_of);
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        let Some(line) = lines.next() else { break };
+        let line = line.map_err(|e| e.to_string())?;
+        let words: Vec<&str> = line.trim().trim_end_matches('?').split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["quit"] | ["exit"] => break,
+            ["help"] => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            ["show"] => {
+                show(&config, &state);
+                Ok(())
+            }
+            ["prob", "at", rest @ ..] => parse_state(&config, &state, rest).map(|new_state| {
+                state = new_state;
+                report(&config, &state, QUICK_SIMULATIONS);
+            }),
+            ["simulate", n, rest @ ..] if rest.is_empty() || rest == ["from", "here"] => {
+                n.parse::<usize>().map_err(|e| format!("invalid number of simulations '{}': {}", n, e)).map(|n| report(&config, &state, n))
+            }
+            ["set", field, value] => set(&config, field, value).and_then(|updated| {
+                state.validate(&updated.scoring_rules()).map_err(|e| format!("{}; the current score no longer fits", e))?;
+                config = updated;
+                println!("{} set to {}", field, value);
+                Ok(())
+            }),
+            _ => Err(format!("unknown command '{}'; type help for commands", line.trim())),
+        };
+        if let Err(e) = result {
+            println!("Error: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// "6-4 4-4 30-40 with Nadal serving": the finished sets, the current games, optionally the points (in tennis
+// notation, or numbers after 6-6) and optionally who is serving, which otherwise stays as it was
+fn parse_state(config: &SimulationConfig, current: &MatchState, words: &[&str]) -> Result<MatchState, String> {
+    let with = words.iter().position(|word| *word == "with").unwrap_or(words.len());
+    let mut score = words[..with].to_vec();
+    let server = match &words[with..] {
+        [] => current.server,
+        ["with", name @ .., "serving"] if !name.is_empty() => {
+            let players = [config.player1.clone(), config.player2.clone()];
+            if find_player(&players, &name.join(" "))?.name == players[0].name { 0 } else { 1 }
+        }
+        _ => return Err("expected 'with <player> serving' after the score".to_string()),
+    };
+
+    let tennis_points = |token: &str| token.split('-').any(|value| matches!(value.to_ascii_uppercase().as_str(), "15" | "30" | "40" | "A" | "AD"));
+    let points = match score.as_slice() {
+        [.., last] if tennis_points(last) => score.pop(),
+        // Finished sets are never level, so a token after level games is tiebreak points
+        [.., games, _] if games.split_once('-').is_some_and(|(a, b)| a == b) => score.pop(),
+        _ => None,
+    };
+    let rules = config.scoring_rules();
+    let state = MatchState::parse(&score.join(" "), points, server, &rules)?;
+    state.validate(&rules)?;
+    Ok(state)
+}
+
+fn report(config: &SimulationConfig, state: &MatchState, simulations: usize) {
+    let prob = what_if::win_probability(config, state, simulations);
+    let margin = 1.96 * (prob * (1.0 - prob) / simulations.max(1) as f64).sqrt();
+    let server = if state.server == 0 { &config.player1.name } else { &config.player2.name };
+    println!("At {} ({} serving), {} playouts:", state.describe(&config.scoring_rules()), server, simulations);
+    println!(" {}: {:.2}% (+/- {:.2})", config.player1.name, prob * 100.0, margin * 100.0);
+    println!(" {}: {:.2}% (+/- {:.2})", config.player2.name, (1.0 - prob) * 100.0, margin * 100.0);
+}
+
+fn show(config: &SimulationConfig, state: &MatchState) {
+    let server = if state.server == 0 { &config.player1.name } else { &config.player2.name };
+    let format = if config.scoring.is_some() { ", custom scoring" } else if config.grand_slam { ", grand slam" } else { "" };
+    println!("{} ({} serving), best of {}{}", state.describe(&config.scoring_rules()), server, config.best_of, format);
+    for (label, player) in [("p1", &config.player1), ("p2", &config.player2)] {
+        println!(" {} {}: serve {:.3}, ace {:.3}, df {:.3}", label, player.name, player.serve_win_prob, player.ace_prob, player.double_fault_prob);
+    }
+}
+
+// Sets a config field given as a dotted path, with p1 / p2 for the players; the value is read as JSON, or as a
+// plain string if it isn't valid JSON
+fn set(config: &SimulationConfig, field: &str, value: &str) -> Result<SimulationConfig, String> {
+    let mut path: Vec<&str> = field.split('.').collect();
+    for part in path.iter_mut() {
+        *part = match *part {
+            "p1" => "player1",
+            "p2" => "player2",
+            "serve" => "serve_win_prob",
+            "ace" => "ace_prob",
+            "df" => "double_fault_prob",
+            other => other,
+        };
+    }
+
+    let mut json = serde_json::to_value(config).map_err(|e| e.to_string())?;
+    let mut target = &mut json;
+    for part in &path {
+        target = target.get_mut(*part).ok_or_else(|| format!("unknown field '{}'", field))?;
+    }
+    *target = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::from(value));
+    let mut updated: SimulationConfig = serde_json::from_value(json).map_err(|e| format!("invalid value for {}: {}", field, e))?;
+    updated.run_dir = config.run_dir.clone();
+    match check::errors(&updated).first() {
+        Some(error) => Err(error.clone()),
+        None => Ok(updated),
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::sync::Mutex;
+
+use crate::sink::ResultSink;
+use crate::{BatchResult, SimulationReport};
+
+// Per-match summary rows of a run, kept in memory column by column, e.g.
+// `results.filter(|m| m.sets == (3, 2)).mean(|m| m.aces[0])` for player1's aces in five-set wins
+#[derive(Default)]
+pub struct ResultStore {
+    match_number: Vec<usize>,
+    sets: Vec<(i32, i32)>,
+    points: Vec<i32>,
+    shots: Vec<i32>,
+    aces: Vec<[i32; 2]>,
+    double_faults: Vec<[i32; 2]>,
+    tiebreaks: Vec<i32>,
+    winner_min_win_prob: Vec<f64>,
+}
+
+// One match of a store, as seen by filter, group-by and mean closures; pairs are in player order
+pub struct MatchRow {
+    pub sets: (i32, i32),
+    pub points: i32,
+    pub shots: i32,
+    pub aces: [i32; 2],
+    pub double_faults: [i32; 2],
+    pub tiebreaks: i32,
+    pub winner_min_win_prob: f64,
+}
+
+// The matches of a store picked out by filters and group-bys
+pub struct Selection<'a> {
+    store: &'a ResultStore,
+    rows: Vec<usize>,
+}
+
+impl ResultStore {
+    pub fn len(&self) -> usize {
+        self.match_number.len()
+    }
+
+    fn row(&self, index: usize) -> MatchRow {
+        MatchRow {
+            sets: self.sets[index],
+            points: self.points[index],
+            shots: self.shots[index],
+            aces: self.aces[index],
+            double_faults: self.double_faults[index],
+            tiebreaks: self.tiebreaks[index],
+            winner_min_win_prob: self.winner_min_win_prob[index],
+        }
+    }
+
+    pub fn all(&self) -> Selection<'_> {
+        Selection { store: self, rows: (0..self.len()).collect() }
+    }
+
+    pub fn filter(&self, predicate: impl Fn(&MatchRow) -> bool) -> Selection<'_> {
+        self.all().filter(predicate)
+    }
+
+    pub fn group_by<K: Ord>(&self, key: impl Fn(&MatchRow) -> K) -> BTreeMap<K, Selection<'_>> {
+        self.all().group_by(key)
+    }
+
+    fn push(&mut self, summary: &serde_json::Value) {
+        let pair = |value: &serde_json::Value| [value[0].as_i64().unwrap_or(0) as i32, value[1].as_i64().unwrap_or(0) as i32];
+        let players = [summary["player1"].as_str().unwrap_or(""), summary["player2"].as_str().unwrap_or("")];
+        let stat = |key: &str| players.map(|player| summary["stats"][player][key].as_i64().unwrap_or(0) as i32);
+        let [sets1, sets2] = pair(&summary["sets"]);
+        self.match_number.push(summary["match"].as_u64().unwrap_or(0) as usize);
+        self.sets.push((sets1, sets2));
+        self.points.push(summary["points"].as_i64().unwrap_or(0) as i32);
+        self.shots.push(summary["shots"].as_i64().unwrap_or(0) as i32);
+        self.aces.push(stat("aces"));
+        self.double_faults.push(stat("double_faults"));
+        // Both players are credited with each tiebreak played
+        self.tiebreaks.push(stat("tiebreaks_played")[0]);
+        self.winner_min_win_prob.push(summary["winner_min_win_prob"].as_f64().unwrap_or(0.0));
+    }
+
+    // Puts the rows in match order, which they arrive out of when batches finish concurrently
+    fn sort(&mut self) {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by_key(|index| self.match_number[*index]);
+        fn reorder<T: Clone>(column: &mut Vec<T>, order: &[usize]) {
+            *column = order.iter().map(|index| column[*index].clone()).collect();
+        }
+        reorder(&mut self.match_number, &order);
+        reorder(&mut self.sets, &order);
+        reorder(&mut self.points, &order);
+        reorder(&mut self.shots, &order);
+        reorder(&mut self.aces, &order);
+        reorder(&mut self.double_faults, &order);
+        reorder(&mut self.tiebreaks, &order);
+        reorder(&mut self.winner_min_win_prob, &order);
+    }
+}
+
+impl<'a> Selection<'a> {
+    pub fn filter(self, predicate: impl Fn(&MatchRow) -> bool) -> Selection<'a> {
+        let rows = self.rows.into_iter().filter(|index| predicate(&self.store.row(*index))).collect();
+        Selection { store: self.store, rows }
+    }
+
+    pub fn group_by<K: Ord>(self, key: impl Fn(&MatchRow) -> K) -> BTreeMap<K, Selection<'a>> {
+        let mut groups: BTreeMap<K, Selection<'a>> = BTreeMap::new();
+        for index in self.rows {
+            groups.entry(key(&self.store.row(index))).or_insert_with(|| Selection { store: self.store, rows: Vec::new() }).rows.push(index);
+        }
+        groups
+    }
+
+    pub fn count(&self) -> usize {
+        self.rows.len()
+    }
+
+    // The mean of `value` over the selected matches, 0 if there are none
+    pub fn mean<T: Into<f64>>(&self, value: impl Fn(&MatchRow) -> T) -> f64 {
+        let sum: f64 = self.rows.iter().map(|index| value(&self.store.row(*index)).into()).sum();
+        sum / self.rows.len().max(1) as f64
+    }
+}
+
+// Fills a result store from the match summaries of a run
+#[derive(Default)]
+pub struct StoreSink {
+    store: Mutex<ResultStore>,
+}
+
+impl StoreSink {
+    pub fn into_store(self) -> ResultStore {
+        let mut store = self.store.into_inner().unwrap();
+        store.sort();
+        store
+    }
+}
+
+impl ResultSink for StoreSink {
+    fn on_batch(&self, _batch: &BatchResult) {}
+
+    fn on_complete(&self, _report: &SimulationReport) {}
+
+    fn on_match(&self, summary: &serde_json::Value, _points: &[HashMap<String, serde_json::Value>]) {
+        self.store.lock().unwrap().push(summary);
+    }
+}
+
+// The share of each final set score with the averages of its matches, for `--keep-results`
+pub fn write_scorelines(out: &mut dyn Write, store: &ResultStore, player1: &str, player2: &str) -> std::io::Result<()> {
+    writeln!(out, "\nScorelines over {} matches ({} first; aces and double faults {} - {}):", store.len(), player1, player1, player2)?;
+    for ((sets1, sets2), matches) in store.group_by(|m| m.sets) {
+        writeln!(
+            out,
+            " {}-{}: {:.2}%, {:.1} points, {:.1} shots, aces {:.2} - {:.2}, double faults {:.2} - {:.2}, {:.2} tiebreaks",
+            sets1, sets2, matches.count() as f64 / store.len().max(1) as f64 * 100.0, matches.mean(|m| m.points), matches.mean(|m| m.shots),
+            matches.mean(|m| m.aces[0]), matches.mean(|m| m.aces[1]), matches.mean(|m| m.double_faults[0]), matches.mean(|m| m.double_faults[1]), matches.mean(|m| m.tiebreaks),
+        )?;
+    }
+    let comebacks = store.filter(|m| m.winner_min_win_prob < 0.5).count();
+    writeln!(out, "Matches won by a player who trailed in match win probability: {} ({:.2}%)", comebacks, comebacks as f64 / store.len().max(1) as f64 * 100.0)
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{csv_header, fnv1a, BatchResult, LogFormat, RunMetadata, SimulationConfig};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const CONFIG_FILE: &str = "config.json";
+pub const REPORT_FILE: &str = "report.txt";
+pub const REPORT_JSON_FILE: &str = "report.json";
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+// One directory per run under the output directory, named `<UTC timestamp>-<low 32 bits of the seed in hex>`,
+// holding the point log, calibration curves, report, a snapshot of the effective config and a manifest of them all
+pub struct RunDirectory {
+    pub run_id: String,
+    pub path: PathBuf,
+    created_at: String,
+}
+
+impl RunDirectory {
+    pub fn create(output_dir: &str, config: &SimulationConfig) -> Result<Self, String> {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+        let created_at = utc_timestamp(secs);
+        let base_id = format!("{}-{:08x}", created_at, config.seed as u32);
+
+        // Runs started in the same second with the same seed get a numbered suffix
+        let mut run_id = base_id.clone();
+        let mut attempt = 1;
+        let path = loop {
+            let path = Path::new(output_dir).join(&run_id);
+            fs::create_dir_all(output_dir).map_err(|e| format!("could not create {}: {}", output_dir, e))?;
+            match fs::create_dir(&path) {
+                Ok(()) => break path,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    attempt += 1;
+                    run_id = format!("{}-{}", base_id, attempt);
+                }
+                Err(e) => return Err(format!("could not create {}: {}", path.display(), e)),
+            }
+        };
+
+        let run_dir = RunDirectory { run_id, path, created_at };
+        let snapshot = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+        run_dir.write(CONFIG_FILE, snapshot.as_bytes())?;
+        run_dir.write_manifest(config, "running")?;
+        Ok(run_dir)
+    }
+
+    // Opens the directory of an interrupted run so it can be resumed
+    pub fn reopen(path: &str) -> Result<Self, String> {
+        let manifest_path = Path::new(path).join(MANIFEST_FILE);
+        let contents = fs::read_to_string(&manifest_path).map_err(|e| format!("could not read {}: {}", manifest_path.display(), e))?;
+        let manifest: serde_json::Value = serde_json::from_str(&contents).map_err(|e| format!("invalid manifest {}: {}", manifest_path.display(), e))?;
+        let field = |name: &str| manifest[name].as_str().map(str::to_string).ok_or_else(|| format!("{} has no {}", manifest_path.display(), name));
+        if field("status")? == "complete" {
+            return Err(format!("run {} is already complete", field("run_id")?));
+        }
+        Ok(RunDirectory { run_id: field("run_id")?, path: PathBuf::from(path), created_at: field("created_at")? })
+    }
+
+    pub fn write(&self, name: &str, contents: &[u8]) -> Result<(), String> {
+        let path = self.path.join(name);
+        fs::write(&path, contents).map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+
+    // Files of the run relative to its directory, with their sizes, excluding the manifest itself
+    pub fn artifacts(&self) -> Vec<(String, u64)> {
+        let mut artifacts = Vec::new();
+        let mut pending = vec![self.path.clone()];
+        while let Some(dir) = pending.pop() {
+            for entry in fs::read_dir(&dir).into_iter().flatten().filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if let Ok(relative) = path.strip_prefix(&self.path) {
+                    let name = relative.to_string_lossy().replace('\\', "/");
+                    if name != MANIFEST_FILE {
+                        artifacts.push((name, entry.metadata().map(|metadata| metadata.len()).unwrap_or(0)));
+                    }
+                }
+            }
+        }
+        artifacts.sort();
+        artifacts
+    }
+
+    // `status` is "running" while the simulation is in progress and "complete" once every artifact is written.
+    // Each artifact is listed with its size and a hash of its contents, so a copy can be checked against the run.
+    pub fn write_manifest(&self, config: &SimulationConfig, status: &str) -> Result<(), String> {
+        let artifacts: Vec<serde_json::Value> = self.artifacts().into_iter()
+            .map(|(path, bytes)| {
+                let hash = file_hash(&self.path.join(&path)).map_err(|e| format!("could not read {}: {}", path, e))?;
+                Ok(serde_json::json!({ "path": path, "bytes": bytes, "fnv1a": hash }))
+            })
+            .collect::<Result<_, String>>()?;
+        let manifest = serde_json::json!({
+            "run_id": self.run_id,
+            "status": status,
+            "created_at": self.created_at,
+            "metadata": RunMetadata::new(config).to_json(),
+            "num_simulations": config.num_simulations,
+            "artifacts": artifacts,
+        });
+        let contents = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        self.write(MANIFEST_FILE, contents.as_bytes())
+    }
+}
+
+// What a new run does with a file already at its `point_log_path` or `match_log_path`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogPolicy {
+    // Stop before simulating anything, so runs are never mixed in one file by accident
+    Refuse,
+    Overwrite,
+    // Add the run's rows after those already in the file
+    Append,
+}
+
+impl LogPolicy {
+    // --overwrite or --append, refusing without either
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let has = |flag: &str| args.iter().any(|arg| arg == flag);
+        match (has("--overwrite"), has("--append")) {
+            (true, true) => Err("--overwrite and --append can't be combined".to_string()),
+            (true, false) => Ok(LogPolicy::Overwrite),
+            (false, true) => Ok(LogPolicy::Append),
+            (false, false) => Ok(LogPolicy::Refuse),
+        }
+    }
+}
+
+// Readies `path` for a new run to log to under `policy`, creating its directory if needed. A run only appends
+// to a log of the same configuration and layout: one whose metadata line has the run's `schema_version` and
+// `config_hash` and whose header is `columns`, so rows of different matchups or models never share a file.
+pub fn prepare_log_file(path: &Path, policy: LogPolicy, metadata: &RunMetadata, columns: &[String]) -> Result<(), String> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("could not create {}: {}", parent.display(), e))?;
+    }
+    if !path.exists() {
+        return Ok(());
+    }
+    match policy {
+        LogPolicy::Refuse => Err(format!("{} already exists; pass --overwrite to replace it or --append to add this run to it", path.display())),
+        LogPolicy::Overwrite => fs::remove_file(path).map_err(|e| format!("could not remove {}: {}", path.display(), e)),
+        LogPolicy::Append => check_appendable(path, metadata, columns),
+    }
+}
+
+fn check_appendable(path: &Path, metadata: &RunMetadata, columns: &[String]) -> Result<(), String> {
+    // Only the metadata line and the header are read, however long the log
+    let mut lines = BufReader::new(File::open(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?).lines();
+    let Some(comment) = lines.next() else {
+        return Ok(());
+    };
+    let comment = comment.map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    let existing: HashMap<&str, &str> = comment.strip_prefix("# ").unwrap_or("").split(' ').filter_map(|pair| pair.split_once('=')).collect();
+    let refuse = |reason: String| Err(format!("can't append to {}: {}; use --overwrite or another path", path.display(), reason));
+    for (key, value) in metadata.pairs().into_iter().filter(|(key, _)| ["schema_version", "config_hash"].contains(key)) {
+        match existing.get(key) {
+            None => return refuse(format!("it has no {} in its metadata line", key)),
+            Some(existing) if *existing != value => return refuse(format!("it was written with {} {}, and this run has {}", key, existing, value)),
+            Some(_) => {}
+        }
+    }
+    if lines.next().and_then(Result::ok).unwrap_or_default().trim_end() != csv_header(columns) {
+        return refuse("its columns differ from this run's".to_string());
+    }
+    Ok(())
+}
+
+// The batches of a run simulated so far and their merged results, saved to the run directory while it runs
+#[derive(Deserialize, Serialize)]
+pub struct Checkpoint {
+    pub completed_batches: Vec<usize>,
+    // Lengths of the CSV point log and match log once those batches were logged; rows past them belong to
+    // batches that finished after the checkpoint and are simulated again on resume
+    point_log_bytes: u64,
+    match_log_bytes: u64,
+    pub execution_time_ms: u64,
+    pub totals: BatchResult,
+}
+
+impl Checkpoint {
+    pub fn new(config: &SimulationConfig) -> Self {
+        Checkpoint {
+            completed_batches: Vec::new(),
+            point_log_bytes: 0,
+            match_log_bytes: 0,
+            execution_time_ms: 0,
+            totals: BatchResult::new(&config.player1, &config.player2),
+        }
+    }
+
+    // Loads the last checkpoint of the run in `config.run_dir`, or an empty one if none was saved, and
+    // rewinds the CSV point log and match log to match it
+    pub fn load(config: &SimulationConfig) -> Result<Self, String> {
+        let path = config.run_dir.join(CHECKPOINT_FILE);
+        let checkpoint = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| format!("invalid checkpoint {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Checkpoint::new(config),
+            Err(e) => return Err(format!("could not read {}: {}", path.display(), e)),
+        };
+
+        if config.log_format == LogFormat::Csv {
+            for (log_path, bytes) in [(config.point_log_file(), checkpoint.point_log_bytes), (config.match_log_file(), checkpoint.match_log_bytes)] {
+                if log_path.exists() {
+                    OpenOptions::new()
+                        .write(true)
+                        .open(&log_path)
+                        .and_then(|file| file.set_len(bytes))
+                        .map_err(|e| format!("could not rewind {}: {}", log_path.display(), e))?;
+                }
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn record(&mut self, batch_index: usize, batch: BatchResult) {
+        self.completed_batches.push(batch_index);
+        self.totals.merge(batch);
+    }
+
+    // Written to a temporary file first, so an interruption never leaves a partial checkpoint behind
+    pub fn save(&mut self, config: &SimulationConfig) -> Result<(), String> {
+        let bytes = |path: PathBuf| fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+        self.point_log_bytes = bytes(config.point_log_file());
+        self.match_log_bytes = bytes(config.match_log_file());
+        let path = config.run_dir.join(CHECKPOINT_FILE);
+        let temp_path = path.with_extension("json.tmp");
+        let contents = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(&temp_path, contents).map_err(|e| format!("could not write {}: {}", temp_path.display(), e))?;
+        fs::rename(&temp_path, &path).map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+}
+
+// FNV-1a hash of a file's contents, as 16 hex digits
+fn file_hash(path: &Path) -> std::io::Result<String> {
+    let mut error = None;
+    let hash = fnv1a(BufReader::new(File::open(path)?).bytes().map_while(|byte| byte.map_err(|e| error = Some(e)).ok()));
+    match error {
+        Some(e) => Err(e),
+        None => Ok(format!("{:016x}", hash)),
+    }
+}
+
+// UTC date and time of a Unix timestamp as YYYYMMDDTHHMMSSZ, using the days-to-civil conversion
+// from Howard Hinnant's date algorithms
+fn utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", year, month, day, secs % 86_400 / 3_600, secs % 3_600 / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::sink::ResultSink;
+    use crate::{simulate_match_parallel, SimulationReport};
+
+    const MATCHES: usize = 200;
+
+    // A run of MATCHES single-match batches logging every point and match to its own directory
+    fn config(name: &str) -> SimulationConfig {
+        let config = SimulationConfig {
+            seed: 7,
+            num_simulations: MATCHES,
+            batch_size: 1,
+            log_interval: 1,
+            match_log: true,
+            run_dir: std::env::temp_dir().join(format!("tennis_sim_resume_{}_{}", name, std::process::id())),
+            ..SimulationConfig::default()
+        };
+        fs::create_dir_all(&config.run_dir).unwrap();
+        config
+    }
+
+    // Sets aside the run's checkpoint as its `after`th batch finishes, as the last one a run interrupted there
+    // would have left behind
+    struct Interruption {
+        run_dir: PathBuf,
+        after: usize,
+        batches: AtomicUsize,
+    }
+
+    impl ResultSink for Interruption {
+        fn on_batch(&self, _batch: &BatchResult) {
+            if self.batches.fetch_add(1, Ordering::SeqCst) + 1 == self.after {
+                fs::copy(self.run_dir.join(CHECKPOINT_FILE), self.run_dir.join("interrupted.json")).unwrap();
+            }
+        }
+
+        fn on_complete(&self, _report: &SimulationReport) {}
+
+        fn wants_matches(&self) -> bool {
+            false
+        }
+    }
+
+    // Plays `config` through, then takes it back to the checkpoint set aside part way, with the rows of the
+    // batches after it still in the logs, and resumes it from there
+    fn interrupt_and_resume(config: &SimulationConfig) -> SimulationReport {
+        let interruption = Interruption { run_dir: config.run_dir.clone(), after: MATCHES / 4, batches: AtomicUsize::new(0) };
+        simulate_match_parallel(config, &[&interruption], None);
+        fs::rename(config.run_dir.join("interrupted.json"), config.run_dir.join(CHECKPOINT_FILE)).unwrap();
+        let checkpoint = Checkpoint::load(config).unwrap();
+        assert!(checkpoint.completed_batches.len() < MATCHES / 4);
+        simulate_match_parallel(config, &[], Some(checkpoint))
+    }
+
+    // The rows of a CSV log below its metadata line, as the values of `column`
+    fn column(path: &Path, column: &str) -> Vec<String> {
+        let contents = fs::read_to_string(path).unwrap();
+        let mut lines = contents.lines().skip(1).map(|line| {
+            // Split on the commas outside quoted fields
+            let mut quoted = false;
+            line.split(|c| {
+                quoted ^= c == '"';
+                c == ',' && !quoted
+            })
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+        });
+        let index = lines.next().unwrap().iter().position(|name| name == column).unwrap();
+        lines.map(|fields| fields[index].clone()).collect()
+    }
+
+    #[test]
+    fn a_resumed_run_logs_each_match_once() {
+        let config = config("once");
+        interrupt_and_resume(&config);
+
+        let matches = column(&config.match_log_file(), "match");
+        let distinct: std::collections::HashSet<&String> = matches.iter().collect();
+        assert_eq!(matches.len(), MATCHES);
+        assert_eq!(distinct.len(), MATCHES);
+        // The point log has no match column; each match has one first point
+        let first_points = column(&config.point_log_file(), "point_number").into_iter().filter(|number| number == "1").count();
+        assert_eq!(first_points, MATCHES);
+        fs::remove_dir_all(&config.run_dir).unwrap();
+    }
+
+    #[test]
+    fn a_resumed_run_matches_an_uninterrupted_one() {
+        let uninterrupted = config("uninterrupted");
+        let expected = simulate_match_parallel(&uninterrupted, &[], None);
+        let resumed = config("resumed");
+        let report = interrupt_and_resume(&resumed);
+
+        assert_eq!(report.match_wins, expected.match_wins);
+        assert_eq!(report.total_shots, expected.total_shots);
+        assert_eq!(report.stats, expected.stats);
+        for log in [SimulationConfig::point_log_file, SimulationConfig::match_log_file] {
+            let rows = |config: &SimulationConfig| fs::read_to_string(log(config)).unwrap().lines().count();
+            assert_eq!(rows(&resumed), rows(&uninterrupted));
+        }
+        fs::remove_dir_all(&uninterrupted.run_dir).unwrap();
+        fs::remove_dir_all(&resumed.run_dir).unwrap();
+    }
+}
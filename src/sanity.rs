@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use crate::exact::hold_probability;
+use crate::scoring::ScoringRules;
+use crate::{ModelConfig, Player, SimulationConfig, SimulationReport};
+
+// A simulated aggregate is reported once it strays from the value the parameters imply by more than this many
+// standard errors, and by more than its tolerance: momentum, pressure and the other model effects move every
+// aggregate some way off what the parameters alone imply
+const MAX_STANDARD_ERRORS: f64 = 4.0;
+const SERVE_POINTS_TOLERANCE: f64 = 0.03;
+const HOLD_TOLERANCE: f64 = 0.05;
+const ACE_RATE_TOLERANCE: f64 = 0.02;
+const SET_GAMES_TOLERANCE: f64 = 1.0;
+// Sets without a tiebreak are followed this far
+const MAX_SET_GAMES: usize = 200;
+
+// A simulated aggregate next to the value the configured parameters imply for it
+struct Check {
+    what: String,
+    simulated: f64,
+    expected: f64,
+    standard_error: f64,
+    tolerance: f64,
+    percent: bool,
+}
+
+impl Check {
+    // A rate of `count` out of `trials` against an expected probability
+    fn rate(what: String, count: i32, trials: i32, expected: f64, tolerance: f64) -> Self {
+        let trials = trials.max(1) as f64;
+        let standard_error = (expected * (1.0 - expected) / trials).sqrt();
+        Check { what, simulated: count as f64 / trials, expected, standard_error, tolerance, percent: true }
+    }
+
+    fn warning(&self) -> Option<String> {
+        let show = |value: f64| if self.percent { format!("{:.2}%", value * 100.0) } else { format!("{:.2}", value) };
+        log::debug!("sanity check: {} simulated {}, expected {} (standard error {})", self.what, show(self.simulated), show(self.expected), show(self.standard_error));
+        let gap = (self.simulated - self.expected).abs();
+        if gap <= self.tolerance || gap <= MAX_STANDARD_ERRORS * self.standard_error {
+            return None;
+        }
+        Some(format!("{} is {} simulated but {} from the player parameters; check the model configuration", self.what, show(self.simulated), show(self.expected)))
+    }
+}
+
+// The share of serve points `server` wins against `receiver` by their parameters alone: aces, double faults and
+// rallies, with the placement, first serve and return profiles when they are set and the court modifiers averaged
+fn expected_serve_points(server: &Player, receiver: &Player, model: &ModelConfig) -> f64 {
+    let ace_prob = server.serve_placement.as_ref().map_or(server.ace_prob, |placement| placement.expected(|profile| profile.ace_prob)).clamp(0.0, 0.3);
+    let serve_win_prob = server.serve_placement.as_ref().map_or(server.serve_win_prob, |placement| placement.expected(|profile| profile.serve_win_prob))
+        + (server.deuce_court_modifier + server.ad_court_modifier) / 2.0;
+    let rally = |penalty: f64| {
+        let win = |adjustment: f64| (serve_win_prob + adjustment - penalty).clamp(0.0, 1.0);
+        match &receiver.return_profile {
+            Some(profile) => {
+                let scale = 1.0 + profile.aggressiveness;
+                let error = (profile.error_prob * scale).clamp(0.0, 1.0);
+                let deep = (profile.deep_prob * scale).clamp(0.0, 1.0);
+                error + (1.0 - error) * (deep * win(-model.returns.deep_return_penalty) + (1.0 - deep) * win(model.returns.short_return_bonus))
+            }
+            None => win(0.0),
+        }
+    };
+    match &server.first_serve {
+        Some(first_serve) => {
+            let in_prob = first_serve.in_prob.clamp(0.01, 1.0);
+            let ace_prob = ace_prob.min(in_prob);
+            let double_fault_prob = server.double_fault_prob.min(1.0 - in_prob);
+            ace_prob + (in_prob - ace_prob) * rally(0.0) + (1.0 - in_prob - double_fault_prob) * rally(first_serve.second_serve_penalty)
+        }
+        None => ace_prob + (1.0 - ace_prob) * (1.0 - server.double_fault_prob) * rally(0.0),
+    }
+}
+
+// The mean and mean square of the games in the first set, tiebreak included, with each player holding serve
+// with their own probability and `first_server` (0 for player1) serving first
+fn first_set_games(rules: &ScoringRules, holds: [f64; 2], first_server: usize) -> (f64, f64) {
+    let sets = [0, 0];
+    let mut states = HashMap::from([([0, 0], 1.0)]);
+    let (mut mean, mut square) = (0.0, 0.0);
+    for played in 0..MAX_SET_GAMES {
+        let server = if played % 2 == 0 { first_server } else { 1 - first_server };
+        let player1_wins = if server == 0 { holds[0] } else { 1.0 - holds[1] };
+        let games = (played + 1) as f64;
+        let mut next = HashMap::new();
+        for (score, prob) in states {
+            if rules.tiebreak_at(sets, score).is_some() {
+                mean += prob * games;
+                square += prob * games * games;
+                continue;
+            }
+            for (winner, game_prob) in [(0, player1_wins), (1, 1.0 - player1_wins)] {
+                let mut after: [i32; 2] = score;
+                after[winner] += 1;
+                if rules.is_finished_set(sets, [after[winner], after[1 - winner]]) {
+                    mean += prob * game_prob * games;
+                    square += prob * game_prob * games * games;
+                } else {
+                    *next.entry(after).or_insert(0.0) += prob * game_prob;
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        states = next;
+    }
+    (mean, square)
+}
+
+// Compares the run's aggregates with what the players' parameters imply: each player's serve points won, hold
+// rate and ace rate, and the games in the first set. Returns a warning per aggregate too far off.
+pub fn warnings(config: &SimulationConfig, report: &SimulationReport) -> Vec<String> {
+    let players = [&config.player1, &config.player2];
+    let rules = config.scoring_rules();
+    let serve_points = [0, 1].map(|i| expected_serve_points(players[i], players[1 - i], &config.model));
+    let holds = serve_points.map(|p| hold_probability(p, [0, 0], rules.no_ad));
+
+    let mut checks = Vec::new();
+    for (i, player) in players.iter().enumerate() {
+        let stat = |key: &str| report.stat(&player.name, key);
+        let served = stat("deuce_points_served") + stat("ad_points_served");
+        let won = stat("deuce_points_won") + stat("ad_points_won");
+        checks.push(Check::rate(format!("{}'s share of serve points won", player.name), won, served, serve_points[i], SERVE_POINTS_TOLERANCE));
+        checks.push(Check::rate(format!("{}'s hold rate", player.name), stat("service_games_held"), stat("service_games"), holds[i], HOLD_TOLERANCE));
+        let ace_prob = player.serve_placement.as_ref().map_or(player.ace_prob, |placement| placement.expected(|profile| profile.ace_prob));
+        checks.push(Check::rate(format!("{}'s aces per serve point", player.name), stat("aces"), served, ace_prob, ACE_RATE_TOLERANCE));
+    }
+
+    // The first server is decided by a toss, so either player serves first half the time
+    let moments = [0, 1].map(|first_server| first_set_games(&rules, holds, first_server));
+    let mean = (moments[0].0 + moments[1].0) / 2.0;
+    let variance = (moments[0].1 + moments[1].1) / 2.0 - mean * mean;
+    let (mut sets, mut games) = (0, 0);
+    for (path, count) in &report.markets.score_paths {
+        let Some((a, b)) = path.split(' ').next().and_then(|set| set.split_once('-')) else { continue };
+        sets += count;
+        games += count * (a.parse::<i32>().unwrap_or(0) + b.parse::<i32>().unwrap_or(0));
+    }
+    if sets > 0 {
+        checks.push(Check {
+            what: "Games in the first set".to_string(),
+            simulated: games as f64 / sets as f64,
+            expected: mean,
+            standard_error: (variance.max(0.0) / sets as f64).sqrt(),
+            tolerance: SET_GAMES_TOLERANCE,
+            percent: false,
+        });
+    }
+    checks.iter().filter_map(Check::warning).collect()
+}
@@ -0,0 +1,61 @@
+use std::time::Instant;
+
+use crate::{config_from_args, flag_value, simulate_match_parallel, table};
+
+// Thread counts 1, 2, 4, ... up to `max`, with `max` itself last when it is not a power of two
+fn thread_counts(max: usize) -> Vec<usize> {
+    let mut counts: Vec<usize> = std::iter::successors(Some(1usize), |n| Some(n * 2)).take_while(|n| *n < max).collect();
+    counts.push(max);
+    counts
+}
+
+// scaling [--config <config.json>] [--matches <n>] [--max-threads <n>] [--seed <n>] [--plain]
+// Runs the same seeded workload at 1, 2, 4, ... threads and prints the throughput, speedup over one thread and
+// parallel efficiency of each, to find how many workers pay off on this machine
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut config = config_from_args(args)?;
+    if let Some(value) = flag_value(args, "--matches") {
+        config.num_simulations = value.parse::<usize>().map_err(|e| format!("invalid --matches: {}", e))?;
+    }
+    let max_threads = match flag_value(args, "--max-threads") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --max-threads: {}", e))?,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    if max_threads == 0 {
+        return Err("--max-threads must be at least 1".to_string());
+    }
+    // Only whole batches are played; nothing is logged or checkpointed, so every run does the same work
+    config.num_simulations -= config.num_simulations % config.batch_size;
+    config.log_interval = usize::MAX;
+    config.match_log = false;
+    config.run_dir = Default::default();
+
+    println!("Scaling: {} matches in batches of {} (seed {}), up to {} threads:", config.num_simulations, config.batch_size, config.seed, max_threads);
+    let plain = args.iter().any(|arg| arg == "--plain");
+    let mut output = table::new(&["Threads", "Time (s)", "Matches/s", "Speedup", "Efficiency"], plain);
+    let mut baseline = None;
+    let mut first_wins = None;
+    for threads in thread_counts(max_threads) {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().map_err(|e| format!("could not start {} threads: {}", threads, e))?;
+        let start = Instant::now();
+        let report = pool.install(|| simulate_match_parallel(&config, &[], None));
+        let seconds = start.elapsed().as_secs_f64().max(f64::EPSILON);
+        let baseline = *baseline.get_or_insert(seconds);
+        let speedup = baseline / seconds;
+        output.add_row(vec![
+            threads.to_string(),
+            format!("{:.2}", seconds),
+            format!("{:.0}", config.num_simulations as f64 / seconds),
+            format!("{:.2}x", speedup),
+            format!("{:.0}%", speedup / threads as f64 * 100.0),
+        ]);
+        // The seeded matches are the same whatever the thread count, so the merged totals must be too
+        match &first_wins {
+            None => first_wins = Some(report.match_wins),
+            Some(wins) if *wins != report.match_wins => log::warn!("match wins differ at {} threads from the single-threaded run", threads),
+            Some(_) => {}
+        }
+    }
+    println!("{}", output);
+    Ok(())
+}
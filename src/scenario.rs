@@ -0,0 +1,140 @@
+use std::fs;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::run_dir::{Checkpoint, REPORT_FILE, REPORT_JSON_FILE};
+use crate::sink::ResultSink;
+use crate::surface::Surface;
+use crate::table;
+use crate::{print_report, report_json, simulate_match_parallel, SimulationConfig, SimulationReport};
+
+// A named variant of the run's config: any config field given here replaces the run's value, with objects
+// such as `player1` or `model` merged field by field, and an optional surface applied to both players
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub surface: Option<Surface>,
+    #[serde(flatten)]
+    pub overrides: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Scenario {
+    pub fn config(&self, base: &SimulationConfig) -> Result<SimulationConfig, String> {
+        for nested in ["scenarios", "surfaces"] {
+            if self.overrides.contains_key(nested) {
+                return Err(format!("scenario '{}' can't set {}", self.name, nested));
+            }
+        }
+        let mut value = serde_json::to_value(base).map_err(|e| e.to_string())?;
+        merge(&mut value, &serde_json::Value::Object(self.overrides.clone()));
+        let mut config: SimulationConfig = serde_json::from_value(value).map_err(|e| format!("invalid scenario '{}': {}", self.name, e))?;
+        config.apply_scoring().map_err(|e| format!("scenario '{}': {}", self.name, e))?;
+        if let Some(surface) = &self.surface {
+            config.player1 = surface.apply(&config.player1);
+            config.player2 = surface.apply(&config.player2);
+        }
+        Ok(config)
+    }
+}
+
+fn merge(base: &mut serde_json::Value, overrides: &serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value.clone(),
+    }
+}
+
+// Subdirectory of the run holding the artifacts of a surface or scenario
+pub fn dir_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect()
+}
+
+// The surfaces or scenarios of the run as named configs, or nothing for a plain run
+pub fn variants(config: &SimulationConfig) -> Result<Vec<(String, SimulationConfig)>, String> {
+    if !config.surfaces.is_empty() && !config.scenarios.is_empty() {
+        return Err("a config can list surfaces or scenarios, not both; give each scenario a surface instead".to_string());
+    }
+
+    let mut variants = Vec::new();
+    for surface in &config.surfaces {
+        let scenario = Scenario { name: surface.name.clone(), surface: Some(surface.clone()), overrides: serde_json::Map::new() };
+        variants.push((surface.name.clone(), scenario.config(config)?));
+    }
+    for scenario in &config.scenarios {
+        variants.push((scenario.name.clone(), scenario.config(config)?));
+    }
+    for (_, variant) in &mut variants {
+        variant.surfaces = Vec::new();
+        variant.scenarios = Vec::new();
+    }
+    Ok(variants)
+}
+
+// Simulates each variant in its own subdirectory of the run, with its own report and checkpoints, and writes
+// a table comparing them. Variants share the run's seed unless they override it, so the same match numbers
+// draw the same random numbers and differences come from the settings rather than sampling noise. Returns
+// each variant's name, config and report.
+pub fn run_comparison(out: &mut dyn Write, config: &SimulationConfig, variants: Vec<(String, SimulationConfig)>, sinks: &[&dyn ResultSink], resuming: bool) -> Result<Vec<(String, SimulationConfig, SimulationReport)>, String> {
+    let mut reports = Vec::new();
+    for (name, mut variant) in variants {
+        variant.run_dir = config.run_dir.join(dir_name(&name));
+        variant.plain = config.plain;
+        fs::create_dir_all(&variant.run_dir).map_err(|e| format!("could not create {}: {}", variant.run_dir.display(), e))?;
+        let checkpoint = if resuming { Some(Checkpoint::load(&variant)?) } else { None };
+
+        let report = simulate_match_parallel(&variant, sinks, checkpoint);
+        let mut summary = Vec::new();
+        print_report(&mut summary, &variant, &report).map_err(|e| e.to_string())?;
+        let report_json = serde_json::to_string_pretty(&report_json(&variant, &report)).map_err(|e| e.to_string())?;
+        for (file, contents) in [(REPORT_FILE, summary), (REPORT_JSON_FILE, report_json.into_bytes())] {
+            let path = variant.run_dir.join(file);
+            fs::write(&path, contents).map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+        }
+        reports.push((name, variant, report));
+    }
+
+    let kind = if config.surfaces.is_empty() { "scenario" } else { "surface" };
+    print_comparison(out, kind, config, &reports).map_err(|e| e.to_string())?;
+    Ok(reports)
+}
+
+fn print_comparison(out: &mut dyn Write, kind: &str, config: &SimulationConfig, reports: &[(String, SimulationConfig, SimulationReport)]) -> std::io::Result<()> {
+    writeln!(out, "Comparison of {} {}s: {} vs {} (seed {})", reports.len(), kind, config.player1.name, config.player2.name, config.seed)?;
+    let mut table = table::new(&[kind, "best of", "matches", "p1 win", "p2 win", "aces p1/p2", "DFs p1/p2", "serve won p1/p2", "tiebreaks", "shots"], config.plain);
+    for (name, variant, report) in reports {
+        let matches = report.match_wins.values().sum::<i32>().max(1) as f64;
+        let names = [&variant.player1.name, &variant.player2.name];
+        let per_match = |name: &str, key: &str| report.stat(name, key) as f64 / matches;
+        let serve_won = |name: &str| {
+            let served = report.stat(name, "deuce_points_served") + report.stat(name, "ad_points_served");
+            let won = report.stat(name, "deuce_points_won") + report.stat(name, "ad_points_won");
+            won as f64 / served.max(1) as f64 * 100.0
+        };
+        table.add_row(vec![
+            name.clone(),
+            variant.best_of.to_string(),
+            matches.to_string(),
+            format!("{:.2}%", report.win_probability(names[0]) * 100.0),
+            format!("{:.2}%", report.win_probability(names[1]) * 100.0),
+            format!("{:.2}/{:.2}", per_match(names[0], "aces"), per_match(names[1], "aces")),
+            format!("{:.2}/{:.2}", per_match(names[0], "double_faults"), per_match(names[1], "double_faults")),
+            format!("{:.1}%/{:.1}%", serve_won(names[0]), serve_won(names[1])),
+            // Both players count every tiebreak played
+            format!("{:.2}", per_match(names[0], "tiebreaks_played")),
+            format!("{:.1}", report.total_shots as f64 / matches),
+        ]);
+    }
+    writeln!(out, "{}", table)?;
+    writeln!(out, "\nAces, double faults and tiebreaks are per match; each {}'s full report is in its subdirectory", kind)
+}
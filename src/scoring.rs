@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+
+// How a set is decided once the games are level late in it
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SetDecider {
+    // A tiebreak to `points`, won by two, at `at` games all; at 0 games all the set is a tiebreak on its own
+    Tiebreak { at: i32, points: i32 },
+    // No tiebreak: games go on until a player leads by two
+    Advantage,
+}
+
+// Where a game stands on the points each side has won, with sides in the order the points were given in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameState {
+    InProgress,
+    // Level at 40 or beyond; under no-ad the next point decides the game
+    Deuce,
+    // The side a point from winning the game after deuce
+    Advantage(usize),
+    Won(usize),
+}
+
+// The scoring format of a match, e.g. Fast4 (first to 4 games, tiebreak at 3-3, no-ad):
+// {"sets_to_win": 2, "games_per_set": 4, "no_ad": true, "decider": {"tiebreak": {"at": 3, "points": 7}}}
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScoringRules {
+    pub sets_to_win: i32,
+    // Games needed to win a set, by two clear
+    pub games_per_set: i32,
+    // The point at 40-40 decides the game
+    pub no_ad: bool,
+    pub decider: SetDecider,
+    // How the final set is decided when it differs from the others, e.g. a match tiebreak in place of it
+    pub final_set: Option<SetDecider>,
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        ScoringRules::standard(3, false)
+    }
+}
+
+impl ScoringRules {
+    // Sets to 6 games with a tiebreak to 7 at 6-6, the final set of a grand slam with a tiebreak to 10
+    pub fn standard(best_of: i32, grand_slam: bool) -> Self {
+        ScoringRules {
+            sets_to_win: best_of / 2 + 1,
+            games_per_set: 6,
+            no_ad: false,
+            decider: SetDecider::Tiebreak { at: 6, points: 7 },
+            final_set: grand_slam.then_some(SetDecider::Tiebreak { at: 6, points: 10 }),
+        }
+    }
+
+    pub fn best_of(&self) -> i32 {
+        self.sets_to_win * 2 - 1
+    }
+
+    // Whether the set played with `sets` already won (player order) is the last one the match can have
+    pub fn is_final_set(&self, sets: [i32; 2]) -> bool {
+        sets.iter().sum::<i32>() == self.best_of() - 1
+    }
+
+    pub fn decider(&self, sets: [i32; 2]) -> SetDecider {
+        match self.final_set {
+            Some(decider) if self.is_final_set(sets) => decider,
+            _ => self.decider,
+        }
+    }
+
+    // The points of the tiebreak that `games` call for in the set played with `sets` already won, if they call for one
+    pub fn tiebreak_at(&self, sets: [i32; 2], games: [i32; 2]) -> Option<i32> {
+        match self.decider(sets) {
+            SetDecider::Tiebreak { at, points } if games == [at, at] => Some(points),
+            _ => None,
+        }
+    }
+
+    // The points a tiebreak in the set played with `sets` already won goes to
+    pub fn tiebreak_points(&self, sets: [i32; 2]) -> i32 {
+        match self.decider(sets) {
+            SetDecider::Tiebreak { points, .. } => points,
+            // Advantage sets have no tiebreak; the usual target stands in for callers asking anyway
+            SetDecider::Advantage => 7,
+        }
+    }
+
+    // Whether a player on `won` points of a game to the opponent's `lost` has won it
+    pub fn game_won(&self, won: i32, lost: i32) -> bool {
+        won >= 4 && (self.no_ad || won - lost >= 2)
+    }
+
+    // Where a game (not a tiebreak) stands at `points`
+    pub fn game_state(&self, points: [i32; 2]) -> GameState {
+        let [a, b] = points;
+        if self.game_won(a, b) {
+            GameState::Won(0)
+        } else if self.game_won(b, a) {
+            GameState::Won(1)
+        } else if a == b && a >= 3 {
+            GameState::Deuce
+        } else if a.max(b) >= 4 {
+            GameState::Advantage(if a > b { 0 } else { 1 })
+        } else {
+            GameState::InProgress
+        }
+    }
+
+    // Whether a player on `won` games of a set to the opponent's `lost` has won it without a tiebreak
+    pub fn set_won(&self, won: i32, lost: i32) -> bool {
+        won >= self.games_per_set && won - lost >= 2
+    }
+
+    // Whether `games` (winner first) is a set won, by games or on a tiebreak, played with `sets` already won
+    pub fn is_finished_set(&self, sets: [i32; 2], games: [i32; 2]) -> bool {
+        let [won, lost] = games;
+        self.set_won(won, lost) || matches!(self.decider(sets), SetDecider::Tiebreak { at, .. } if won == at + 1 && lost == at)
+    }
+
+    // Whether `games` (player order) can be the score of a set still in play, played with `sets` already won
+    pub fn is_set_in_progress(&self, sets: [i32; 2], games: [i32; 2]) -> bool {
+        let [a, b] = games;
+        let past_tiebreak = matches!(self.decider(sets), SetDecider::Tiebreak { at, .. } if a.max(b) > at);
+        a >= 0 && b >= 0 && !past_tiebreak && !self.set_won(a.max(b), a.min(b))
+    }
+
+    // What makes the rules unplayable or contradict themselves, as messages naming the config fields
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.sets_to_win < 1 {
+            errors.push(format!("scoring.sets_to_win is {}; it must be at least 1", self.sets_to_win));
+        }
+        if self.games_per_set < 1 {
+            errors.push(format!("scoring.games_per_set is {}; it must be at least 1", self.games_per_set));
+        }
+        for (field, decider) in [("decider", Some(self.decider)), ("final_set", self.final_set)] {
+            let Some(SetDecider::Tiebreak { at, points }) = decider else { continue };
+            if points < 1 {
+                errors.push(format!("scoring.{} tiebreak points is {}; it must be at least 1", field, points));
+            }
+            if at < 0 {
+                errors.push(format!("scoring.{} tiebreak at is {}; it must not be negative", field, at));
+            } else if at > 0 && at + 1 < self.games_per_set {
+                errors.push(format!(
+                    "scoring.{} tiebreak at {} games all would end a set to {} games on {}; use at least {}, or 0 for a set played as a tiebreak alone",
+                    field, at, self.games_per_set, at + 1, self.games_per_set - 1,
+                ));
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast4() -> ScoringRules {
+        ScoringRules {
+            sets_to_win: 2,
+            games_per_set: 4,
+            no_ad: true,
+            decider: SetDecider::Tiebreak { at: 3, points: 7 },
+            // A match tiebreak in place of the third set
+            final_set: Some(SetDecider::Tiebreak { at: 0, points: 10 }),
+        }
+    }
+
+    #[test]
+    fn no_ad_decides_the_game_on_the_point_at_deuce() {
+        let advantage = ScoringRules::default();
+        assert_eq!(advantage.game_state([3, 3]), GameState::Deuce);
+        assert_eq!(advantage.game_state([4, 3]), GameState::Advantage(0));
+        assert_eq!(advantage.game_state([4, 5]), GameState::Advantage(1));
+        assert_eq!(advantage.game_state([5, 3]), GameState::Won(0));
+        assert_eq!(advantage.game_state([2, 3]), GameState::InProgress);
+
+        let no_ad = fast4();
+        assert_eq!(no_ad.game_state([3, 3]), GameState::Deuce);
+        assert_eq!(no_ad.game_state([4, 3]), GameState::Won(0));
+        assert_eq!(no_ad.game_state([3, 4]), GameState::Won(1));
+        assert_eq!(no_ad.game_state([4, 0]), GameState::Won(0));
+    }
+
+    #[test]
+    fn tiebreaks_come_at_the_configured_games() {
+        let standard = ScoringRules::standard(5, true);
+        assert_eq!(standard.tiebreak_at([0, 0], [6, 6]), Some(7));
+        assert_eq!(standard.tiebreak_at([0, 0], [5, 5]), None);
+        // The fifth set of a grand slam goes to a tiebreak to 10
+        assert_eq!(standard.tiebreak_at([2, 2], [6, 6]), Some(10));
+
+        // A tiebreak one game short of the set's games
+        let rules = fast4();
+        assert_eq!(rules.tiebreak_at([0, 0], [3, 3]), Some(7));
+        assert_eq!(rules.tiebreak_at([1, 0], [3, 3]), Some(7));
+        assert_eq!(rules.tiebreak_at([0, 0], [4, 4]), None);
+        assert!(rules.is_finished_set([0, 0], [4, 3]));
+        assert!(!rules.is_set_in_progress([0, 0], [4, 3]));
+
+        // The deciding set is a match tiebreak from its first point
+        assert_eq!(rules.tiebreak_at([1, 1], [0, 0]), Some(10));
+        assert_eq!(rules.tiebreak_at([1, 0], [0, 0]), None);
+        assert_eq!(rules.tiebreak_points([1, 1]), 10);
+        assert!(rules.is_final_set([1, 1]));
+        assert!(!rules.is_final_set([1, 0]));
+        assert!(rules.is_finished_set([1, 1], [1, 0]));
+    }
+
+    #[test]
+    fn best_of_counts_the_sets_a_match_can_have() {
+        assert_eq!(ScoringRules::standard(3, false).best_of(), 3);
+        assert_eq!(ScoringRules::standard(5, false).best_of(), 5);
+        for sets_to_win in 1..5 {
+            let rules = ScoringRules { sets_to_win, ..ScoringRules::default() };
+            assert_eq!(rules.best_of(), sets_to_win * 2 - 1);
+        }
+    }
+
+    #[test]
+    fn unplayable_rules_are_errors() {
+        assert!(ScoringRules::default().errors().is_empty());
+        assert!(ScoringRules::standard(5, true).errors().is_empty());
+        assert!(fast4().errors().is_empty());
+
+        let errors = ScoringRules { sets_to_win: 0, games_per_set: 0, ..ScoringRules::default() }.errors();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("scoring.sets_to_win is 0"));
+        assert!(errors[1].contains("scoring.games_per_set is 0"));
+
+        // A tiebreak at 2-2 would end a set to 4 games at 3-2
+        let early = ScoringRules { decider: SetDecider::Tiebreak { at: 2, points: 7 }, ..fast4() };
+        assert_eq!(early.errors().len(), 1);
+        assert!(early.errors()[0].contains("use at least 3"));
+
+        let errors = ScoringRules { final_set: Some(SetDecider::Tiebreak { at: -1, points: 0 }), ..ScoringRules::default() }.errors();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("scoring.final_set tiebreak points is 0"));
+        assert!(errors[1].contains("scoring.final_set tiebreak at is -1"));
+    }
+}
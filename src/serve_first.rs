@@ -0,0 +1,112 @@
+use rayon::prelude::*;
+
+use crate::{config_from_args, flag_value, match_seed, significance, table, TennisMatch};
+
+// Wins out of chances for each player (0 = player1), split by whether the player served first
+#[derive(Clone, Copy, Default)]
+struct Split {
+    // [player][0 serving first, 1 receiving first]
+    played: [[u64; 2]; 2],
+    won: [[u64; 2]; 2],
+}
+
+impl Split {
+    fn add(&mut self, first_server: usize, winner: usize) {
+        for player in 0..2 {
+            let side = if player == first_server { 0 } else { 1 };
+            self.played[player][side] += 1;
+            self.won[player][side] += (player == winner) as u64;
+        }
+    }
+
+    fn merge(mut self, other: Split) -> Split {
+        for player in 0..2 {
+            for side in 0..2 {
+                self.played[player][side] += other.played[player][side];
+                self.won[player][side] += other.won[player][side];
+            }
+        }
+        self
+    }
+
+    fn share(&self, player: usize, side: usize) -> String {
+        let played = self.played[player][side];
+        format!("{:.2}% ({})", self.won[player][side] as f64 / played.max(1) as f64 * 100.0, played)
+    }
+
+    // Serving first's win rate minus receiving first's, in percentage points, with its 95% interval
+    fn effect(&self, player: usize) -> String {
+        let test = significance::two_proportion_z_test(self.won[player][1], self.played[player][1], self.won[player][0], self.played[player][0]);
+        let (low, high) = test.confidence_interval;
+        format!("{:+.2} [{:+.2}, {:+.2}]", (test.p2 - test.p1) * 100.0, low * 100.0, high * 100.0)
+    }
+}
+
+// The splits of a set of matches: matches by who served first, the first set and every set by who served
+// first in it, and matches by who won the toss
+#[derive(Clone, Copy, Default)]
+struct Splits {
+    matches: Split,
+    first_sets: Split,
+    sets: Split,
+    toss: Split,
+}
+
+impl Splits {
+    fn merge(self, other: Splits) -> Splits {
+        Splits {
+            matches: self.matches.merge(other.matches),
+            first_sets: self.first_sets.merge(other.first_sets),
+            sets: self.sets.merge(other.sets),
+            toss: self.toss.merge(other.toss),
+        }
+    }
+}
+
+// serve-first [--config <config.json>] [--matches <n>] [--seed <n>] [--plain]
+// Quantifies the advantage of serving first under the configured model: each player's chance of winning the
+// match and its sets when serving first and when receiving first, and the match after winning the toss
+pub fn run(args: &[String]) -> Result<(), String> {
+    let config = config_from_args(args)?;
+    let matches = match flag_value(args, "--matches") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --matches: {}", e))?,
+        None => config.num_simulations,
+    };
+    let players = [&config.player1, &config.player2];
+    let index = |name: &str| if name == players[0].name { 0 } else { 1 };
+
+    let splits = (1..=matches)
+        .into_par_iter()
+        .map(|n| {
+            let mut match_sim = TennisMatch::new(config.player1.clone(), config.player2.clone(), config.scoring_rules(), config.model.clone(), match_seed(config.seed, n));
+            let winner = index(&match_sim.play_match().name);
+            let toss = match_sim.toss.as_ref().unwrap();
+            let mut splits = Splits::default();
+            splits.matches.add(index(&toss.first_server), winner);
+            // A toss winner "serving first" here is the player who won the toss
+            splits.toss.add(index(&toss.winner), winner);
+            for (set, first_server) in match_sim.set_first_servers.iter().enumerate() {
+                // The last game of a set is won by the set's winner
+                let set_winner = match_sim.games_played.iter().rev().find(|game| game.set == set).unwrap().winner;
+                if set == 0 {
+                    splits.first_sets.add(*first_server, set_winner);
+                }
+                splits.sets.add(*first_server, set_winner);
+            }
+            splits
+        })
+        .reduce(Splits::default, Splits::merge);
+
+    println!("Serving first: {} vs {}, {} matches (seed {}), win rates with the number of chances:", players[0].name, players[1].name, matches, config.seed);
+    let plain = args.iter().any(|arg| arg == "--plain");
+    let mut output = table::new(&["", players[0].name.as_str(), players[1].name.as_str()], plain);
+    for (label, split) in [("Match", &splits.matches), ("First set", &splits.first_sets), ("Set", &splits.sets)] {
+        output.add_row(vec![format!("{}, serving first", label), split.share(0, 0), split.share(1, 0)]);
+        output.add_row(vec![format!("{}, receiving first", label), split.share(0, 1), split.share(1, 1)]);
+        output.add_row(vec![format!("{}, effect of serving first (pts, 95% interval)", label), split.effect(0), split.effect(1)]);
+    }
+    output.add_row(vec!["Match, toss won".to_string(), splits.toss.share(0, 0), splits.toss.share(1, 0)]);
+    output.add_row(vec!["Match, toss lost".to_string(), splits.toss.share(0, 1), splits.toss.share(1, 1)]);
+    println!("{}", output);
+    Ok(())
+}
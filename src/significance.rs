@@ -0,0 +1,163 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::diff::ReportFile;
+use crate::flag_value;
+
+// Outcome of comparing the proportions x1 / n1 and x2 / n2 of two independent samples
+pub struct ProportionTest {
+    pub p1: f64,
+    pub p2: f64,
+    pub z: f64,
+    // Two-sided p-value of the pooled z-test
+    pub p_value: f64,
+    // 95% confidence interval of p2 - p1, from the unpooled standard error
+    pub confidence_interval: (f64, f64),
+    // Cohen's h: 2 asin(sqrt(p2)) - 2 asin(sqrt(p1))
+    pub cohens_h: f64,
+}
+
+pub fn two_proportion_z_test(x1: u64, n1: u64, x2: u64, n2: u64) -> ProportionTest {
+    let p1 = x1 as f64 / n1.max(1) as f64;
+    let p2 = x2 as f64 / n2.max(1) as f64;
+    let (n1, n2) = (n1.max(1) as f64, n2.max(1) as f64);
+    let pooled = (x1 + x2) as f64 / (n1 + n2);
+    let pooled_error = (pooled * (1.0 - pooled) * (1.0 / n1 + 1.0 / n2)).sqrt();
+    let z = if pooled_error > 0.0 { (p2 - p1) / pooled_error } else { 0.0 };
+    let unpooled_error = (p1 * (1.0 - p1) / n1 + p2 * (1.0 - p2) / n2).sqrt();
+    ProportionTest {
+        p1,
+        p2,
+        z,
+        p_value: two_sided_p_value(z),
+        confidence_interval: (p2 - p1 - 1.96 * unpooled_error, p2 - p1 + 1.96 * unpooled_error),
+        cohens_h: 2.0 * p2.sqrt().asin() - 2.0 * p1.sqrt().asin(),
+    }
+}
+
+// Wilson score 95% interval of the proportion successes / trials, which stays within 0 and 1 near the ends
+pub fn wilson_interval(successes: u64, trials: u64) -> (f64, f64) {
+    let n = trials.max(1) as f64;
+    let p = successes as f64 / n;
+    let z2 = 1.96 * 1.96;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let margin = 1.96 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+    (centre - margin, centre + margin)
+}
+
+// P(|Z| >= |z|) for a standard normal Z
+pub fn two_sided_p_value(z: f64) -> f64 {
+    erfc(z.abs() / std::f64::consts::SQRT_2).min(1.0)
+}
+
+// Complementary error function, from the Chebyshev fit in Numerical Recipes (relative error below 1.2e-7)
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x.abs());
+    let poly = -x * x - 1.265_512_23
+        + t * (1.000_023_68 + t * (0.374_091_96 + t * (0.096_784_18 + t * (-0.186_288_06 + t * (0.278_868_07
+        + t * (-1.135_203_98 + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let value = t * poly.exp();
+    if x >= 0.0 { value } else { 2.0 - value }
+}
+
+fn format_p_value(p_value: f64) -> String {
+    if p_value >= 1e-4 { format!("{:.4}", p_value) } else { format!("{:.1e}", p_value) }
+}
+
+// Conventional reading of |h|: 0.2 small, 0.5 medium, 0.8 large
+fn describe_effect(cohens_h: f64) -> &'static str {
+    match cohens_h.abs() {
+        h if h < 0.2 => "negligible",
+        h if h < 0.5 => "small",
+        h if h < 0.8 => "medium",
+        _ => "large",
+    }
+}
+
+// Number of successes in n draws with success probability p
+fn resample(n: u64, p: f64, rng: &mut impl Rng) -> u64 {
+    (0..n).filter(|_| rng.gen::<f64>() < p).count() as u64
+}
+
+// Bootstrap of the difference p2 - p1: a percentile 95% interval from resampling each run on its own, and a
+// p-value from resampling both runs under the pooled proportion
+pub fn bootstrap(x1: u64, n1: u64, x2: u64, n2: u64, resamples: usize, rng: &mut impl Rng) -> (f64, (f64, f64)) {
+    let (n1, n2) = (n1.max(1), n2.max(1));
+    let p1 = x1 as f64 / n1 as f64;
+    let p2 = x2 as f64 / n2 as f64;
+    let pooled = (x1 + x2) as f64 / (n1 + n2) as f64;
+    let observed = (p2 - p1).abs();
+
+    let mut differences = Vec::with_capacity(resamples);
+    let mut as_extreme = 0;
+    for _ in 0..resamples {
+        differences.push(resample(n2, p2, rng) as f64 / n2 as f64 - resample(n1, p1, rng) as f64 / n1 as f64);
+        let null_difference = resample(n2, pooled, rng) as f64 / n2 as f64 - resample(n1, pooled, rng) as f64 / n1 as f64;
+        if null_difference.abs() >= observed - 1e-12 {
+            as_extreme += 1;
+        }
+    }
+    differences.sort_by(|a, b| a.total_cmp(b));
+    let quantile = |q: f64| differences[((differences.len() - 1) as f64 * q).round() as usize];
+    let p_value = (as_extreme + 1) as f64 / (resamples + 1) as f64;
+    (p_value, (quantile(0.025), quantile(0.975)))
+}
+
+// A win count for one side: from a run's report.json (or run directory), or given directly as wins/matches
+fn parse_sample(arg: &str) -> Result<(String, u64, u64), String> {
+    if let Some((wins, matches)) = arg.split_once('/').filter(|_| !std::path::Path::new(arg).exists()) {
+        let wins = wins.parse().map_err(|e| format!("invalid wins in {}: {}", arg, e))?;
+        let matches = matches.parse().map_err(|e| format!("invalid matches in {}: {}", arg, e))?;
+        if wins > matches {
+            return Err(format!("{}: more wins than matches", arg));
+        }
+        return Ok(("player1".to_string(), wins, matches));
+    }
+    let report = ReportFile::load(arg)?;
+    let wins = report.match_wins.get(&report.players[0]).copied().unwrap_or(0);
+    Ok((report.players[0].clone(), wins.max(0) as u64, report.matches.max(0) as u64))
+}
+
+// significance <a> <b> [--bootstrap <resamples>] [--seed <n>]: tests whether player1's win probability differs
+// between two independent runs, each given as a report.json, a run directory or wins/matches
+pub fn run(args: &[String]) -> Result<(), String> {
+    let usage = "usage: significance <report.json, run dir or wins/matches> <report.json, run dir or wins/matches> [--bootstrap <resamples>]";
+    let samples: Vec<&String> = args.iter().take(2).filter(|arg| !arg.starts_with("--")).collect();
+    let [a, b] = samples.as_slice() else {
+        return Err(usage.to_string());
+    };
+    let (name, x1, n1) = parse_sample(a)?;
+    let (name_b, x2, n2) = parse_sample(b)?;
+    if name != name_b {
+        println!("Note: player1 is {} in A and {} in B", name, name_b);
+    }
+
+    let test = two_proportion_z_test(x1, n1, x2, n2);
+    println!("{} win probability: A {:.2}% ({}/{}), B {:.2}% ({}/{})", name, test.p1 * 100.0, x1, n1, test.p2 * 100.0, x2, n2);
+    println!("Difference: {:+.2} points, 95% CI [{:+.2}, {:+.2}]", (test.p2 - test.p1) * 100.0, test.confidence_interval.0 * 100.0, test.confidence_interval.1 * 100.0);
+    println!("Two-proportion z-test: z = {:.3}, p = {} (two-sided)", test.z, format_p_value(test.p_value));
+    println!(
+        "Effect size: Cohen's h = {:+.3} ({}), relative change {:+.2}%",
+        test.cohens_h, describe_effect(test.cohens_h), (test.p2 - test.p1) / test.p1.max(f64::EPSILON) * 100.0,
+    );
+
+    if let Some(resamples) = flag_value(args, "--bootstrap") {
+        let resamples: usize = resamples.parse().map_err(|e| format!("invalid --bootstrap: {}", e))?;
+        if resamples == 0 {
+            return Err("--bootstrap needs at least one resample".to_string());
+        }
+        let mut rng = match flag_value(args, "--seed") {
+            Some(seed) => StdRng::seed_from_u64(seed.parse().map_err(|e| format!("invalid --seed: {}", e))?),
+            None => StdRng::from_entropy(),
+        };
+        let (bootstrap_p, (low, high)) = bootstrap(x1, n1, x2, n2, resamples, &mut rng);
+        println!("Bootstrap ({} resamples): p = {}, 95% CI [{:+.2}, {:+.2}]", resamples, format_p_value(bootstrap_p), low * 100.0, high * 100.0);
+    }
+
+    if test.p_value < 0.05 {
+        println!("The difference is significant at the 5% level");
+    } else {
+        println!("The difference is not significant at the 5% level; it is consistent with sampling noise");
+    }
+    Ok(())
+}
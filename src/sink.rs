@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+#[cfg(feature = "duckdb")]
+use crate::duckdb_sink::DuckDbSink;
+#[cfg(feature = "postgres")]
+use crate::postgres_sink::PostgresSink;
+#[cfg(feature = "redis")]
+use crate::redis_sink::RedisSink;
+use crate::{BatchResult, SimulationConfig, SimulationReport};
+
+// Receives results while a parallel simulation runs. `on_batch` is called from the worker
+// threads as each batch finishes, in completion order; `on_complete` once, with the final report.
+pub trait ResultSink: Sync {
+    fn on_batch(&self, batch: &BatchResult);
+    fn on_complete(&self, report: &SimulationReport);
+
+    // Called from the worker threads after each match with a JSON summary of it and its point log
+    fn on_match(&self, _summary: &serde_json::Value, _points: &[HashMap<String, serde_json::Value>]) {}
+
+    // Whether `on_match` does anything; when no sink does, the summary and the price series are not built
+    fn wants_matches(&self) -> bool {
+        true
+    }
+
+    // Called for every logged point of a match played by the `live` subcommand, as it is revealed
+    fn on_point(&self, _match_id: &str, _point: &HashMap<String, serde_json::Value>) {}
+    fn on_match_complete(&self, _match_id: &str, _winner: &str) {}
+}
+
+// Logs running match win percentages roughly every tenth of the run, or after every batch at -vv
+pub struct ProgressSink {
+    total_batches: usize,
+    batches_done: AtomicUsize,
+    match_wins: Mutex<HashMap<String, i32>>,
+}
+
+impl ProgressSink {
+    pub fn new(total_batches: usize) -> Self {
+        ProgressSink {
+            total_batches,
+            batches_done: AtomicUsize::new(0),
+            match_wins: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ResultSink for ProgressSink {
+    fn on_batch(&self, batch: &BatchResult) {
+        let mut match_wins = self.match_wins.lock().unwrap();
+        for (player, wins) in &batch.match_wins {
+            *match_wins.entry(player.clone()).or_insert(0) += wins;
+        }
+
+        let done = self.batches_done.fetch_add(1, Ordering::SeqCst) + 1;
+        let step = if log::log_enabled!(log::Level::Debug) { 1 } else { (self.total_batches / 10).max(1) };
+        if done.is_multiple_of(step) || done == self.total_batches {
+            let matches: i32 = match_wins.values().sum();
+            let mut players: Vec<_> = match_wins.iter().collect();
+            players.sort();
+            let standings: Vec<String> = players.iter()
+                .map(|(player, wins)| format!("{} {:.2}%", player, **wins as f64 / matches.max(1) as f64 * 100.0))
+                .collect();
+            log::info!("Progress: {}/{} batches, {} matches: {}", done, self.total_batches, matches, standings.join(", "));
+        }
+    }
+
+    fn on_complete(&self, report: &SimulationReport) {
+        log::info!("Progress: finished in {} milliseconds", report.execution_time);
+    }
+
+    fn wants_matches(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum StreamMode {
+    Points,
+    Matches,
+}
+
+// Writes newline-delimited JSON to stdout: one object per point, tagged with its match number,
+// or one summary object per match
+pub struct StreamSink {
+    mode: StreamMode,
+}
+
+impl StreamSink {
+    pub fn new(mode: StreamMode) -> Self {
+        StreamSink { mode }
+    }
+}
+
+impl ResultSink for StreamSink {
+    fn on_batch(&self, _batch: &BatchResult) {}
+
+    fn on_complete(&self, _report: &SimulationReport) {}
+
+    fn on_match(&self, summary: &serde_json::Value, points: &[HashMap<String, serde_json::Value>]) {
+        // Holding the lock keeps the lines of one match together when batches finish concurrently
+        let mut out = std::io::stdout().lock();
+        let written = match self.mode {
+            StreamMode::Points => points.iter().try_for_each(|point| {
+                let mut line = point.clone();
+                line.insert("match".to_string(), summary["match"].clone());
+                writeln!(out, "{}", serde_json::json!(line))
+            }),
+            StreamMode::Matches => writeln!(out, "{}", summary),
+        };
+        // The reader went away (e.g. `head`), so there is nobody left to simulate for
+        if written.is_err() {
+            std::process::exit(0);
+        }
+    }
+}
+
+// Connects the Redis pub/sub sink, which is only available when built with the `redis` feature
+pub fn redis_sink(url: &str) -> Result<Box<dyn ResultSink>, String> {
+    #[cfg(feature = "redis")]
+    return Ok(Box::new(RedisSink::connect(url)?));
+    #[cfg(not(feature = "redis"))]
+    Err(format!("publishing to {} requires building with --features redis", url))
+}
+
+// Opens (or creates) a DuckDB database for run `run_id`, which is only available when built with the `duckdb` feature
+#[cfg_attr(not(feature = "duckdb"), allow(unused_variables))]
+pub fn duckdb_sink(path: &str, config: &SimulationConfig, run_id: &str, resuming: bool) -> Result<Box<dyn ResultSink>, String> {
+    #[cfg(feature = "duckdb")]
+    return Ok(Box::new(DuckDbSink::open(path, config, run_id, resuming)?));
+    #[cfg(not(feature = "duckdb"))]
+    Err(format!("writing to {} requires building with --features duckdb", path))
+}
+
+// Connects the Postgres sink, which is only available when built with the `postgres` feature
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub fn postgres_sink(url: &str, config: &SimulationConfig, sample_every: usize) -> Result<Box<dyn ResultSink>, String> {
+    #[cfg(feature = "postgres")]
+    return Ok(Box::new(PostgresSink::connect(url, config, sample_every)?));
+    #[cfg(not(feature = "postgres"))]
+    Err(format!("writing to {} requires building with --features postgres", url))
+}
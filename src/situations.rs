@@ -0,0 +1,76 @@
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{csv_field, RunMetadata};
+
+// The situations a serve point is counted in, as (CSV key, label), all points first as the baseline the others
+// are read against. A point can be in several: a break point in the deciding set counts in both.
+pub const SITUATIONS: [(&str, &str); 5] = [
+    ("all", "All points"),
+    ("break_point_down", "Break point down"),
+    ("game_point_up", "Game point up"),
+    ("tiebreak", "Tiebreak"),
+    ("deciding_set", "Deciding set"),
+];
+
+// Serve points played, won and aced by each server in each of SITUATIONS, to check that the pressure and
+// momentum models move the serve where they are meant to
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ServeSituations {
+    // [server (0 = player1)][situation]: points served, points won and aces; empty until a point is counted
+    counts: Vec<[u64; 3]>,
+}
+
+impl ServeSituations {
+    // Counts a point served by `server` in each situation `in_situation` is true for, beside all points
+    pub fn add(&mut self, server: usize, in_situation: [bool; 4], won: bool, ace: bool) {
+        if self.counts.is_empty() {
+            self.counts.resize(2 * SITUATIONS.len(), [0; 3]);
+        }
+        let row = server * SITUATIONS.len();
+        for (situation, _) in std::iter::once(true).chain(in_situation).enumerate().filter(|(_, counted)| *counted) {
+            let counts = &mut self.counts[row + situation];
+            counts[0] += 1;
+            counts[1] += won as u64;
+            counts[2] += ace as u64;
+        }
+    }
+
+    pub fn merge(&mut self, other: &ServeSituations) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), [0; 3]);
+        }
+        for (total, counts) in self.counts.iter_mut().zip(&other.counts) {
+            for (total, count) in total.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+    }
+
+    // Back to no points, keeping the counts' allocation
+    pub fn clear(&mut self) {
+        self.counts.fill([0; 3]);
+    }
+
+    // Points `server` served in SITUATIONS[situation], with the share of them won and aced
+    pub fn rates(&self, server: usize, situation: usize) -> (u64, f64, f64) {
+        let [points, won, aces] = self.counts.get(server * SITUATIONS.len() + situation).copied().unwrap_or_default();
+        (points, won as f64 / points.max(1) as f64, aces as f64 / points.max(1) as f64)
+    }
+
+    // A row per server and situation
+    pub fn write_csv(&self, path: &Path, metadata: &RunMetadata, players: [&str; 2]) -> std::io::Result<()> {
+        let mut csv = std::fs::File::create(path)?;
+        writeln!(csv, "{}", metadata.csv_comment())?;
+        writeln!(csv, "server,situation,points,serve_win_rate,ace_rate")?;
+        for (server, player) in players.iter().enumerate() {
+            for (situation, (key, _)) in SITUATIONS.iter().enumerate() {
+                let (points, won, aces) = self.rates(server, situation);
+                writeln!(csv, "{},{},{},{:.6},{:.6}", csv_field(player), key, points, won, aces)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+// How much of the match a stat is counted over: the game in progress (a tiebreak counts as one game), the set
+// in progress, or the match so far
+#[derive(Clone, Copy)]
+pub enum Scope {
+    Game,
+    Set,
+    Match,
+}
+
+// Each player's stats (see SET_STAT_KEYS) of a match in play, by scope. Events are counted in the game in
+// progress, and `end_game` and `end_set` roll a finished scope into the one above it, so nothing is ever reset
+// under a consumer. A scope's count includes the narrower scopes still in progress: the match count is always
+// the match so far.
+#[derive(Clone, Default)]
+pub struct StatsTracker {
+    // [scope][player (0 = player1)]: the game in progress, the set in progress before it and the match before that
+    scopes: [[HashMap<&'static str, i32>; 2]; 3],
+}
+
+impl StatsTracker {
+    pub fn add(&mut self, player: usize, key: &'static str, count: i32) {
+        *self.scopes[Scope::Game as usize][player].entry(key).or_insert(0) += count;
+    }
+
+    pub fn get(&self, scope: Scope, player: usize, key: &str) -> i32 {
+        // The stored scopes run from the narrowest, so a scope is itself plus every narrower one
+        self.scopes[..=scope as usize].iter().map(|counts| counts[player].get(key).copied().unwrap_or(0)).sum()
+    }
+
+    // Moves the counts of `scope` into the scope above it
+    fn roll(&mut self, scope: Scope) {
+        let (narrow, wide) = self.scopes.split_at_mut(scope as usize + 1);
+        for (from, into) in narrow[scope as usize].iter_mut().zip(wide[0].iter_mut()) {
+            for (key, count) in from.drain() {
+                *into.entry(key).or_insert(0) += count;
+            }
+        }
+    }
+
+    pub fn end_game(&mut self) {
+        self.roll(Scope::Game);
+    }
+
+    // Also ends the game, for anything counted after the set's last point
+    pub fn end_set(&mut self) {
+        self.roll(Scope::Game);
+        self.roll(Scope::Set);
+    }
+
+    // Back to no stats, keeping the maps' capacity
+    pub fn clear(&mut self) {
+        self.scopes.iter_mut().flatten().for_each(HashMap::clear);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_stat_counted_in_a_game_rolls_up_into_the_set_and_match() {
+        let mut stats = StatsTracker::default();
+        stats.add(0, "aces", 2);
+        stats.add(1, "double_faults", 1);
+        for scope in [Scope::Game, Scope::Set, Scope::Match] {
+            assert_eq!(stats.get(scope, 0, "aces"), 2);
+            assert_eq!(stats.get(scope, 1, "double_faults"), 1);
+            assert_eq!(stats.get(scope, 1, "aces"), 0);
+        }
+
+        stats.end_game();
+        assert_eq!(stats.get(Scope::Game, 0, "aces"), 0);
+        assert_eq!(stats.get(Scope::Set, 0, "aces"), 2);
+        assert_eq!(stats.get(Scope::Match, 0, "aces"), 2);
+
+        stats.add(0, "aces", 1);
+        assert_eq!(stats.get(Scope::Game, 0, "aces"), 1);
+        assert_eq!(stats.get(Scope::Set, 0, "aces"), 3);
+
+        stats.end_set();
+        assert_eq!(stats.get(Scope::Game, 0, "aces"), 0);
+        assert_eq!(stats.get(Scope::Set, 0, "aces"), 0);
+        assert_eq!(stats.get(Scope::Match, 0, "aces"), 3);
+        assert_eq!(stats.get(Scope::Match, 1, "double_faults"), 1);
+
+        stats.clear();
+        assert_eq!(stats.get(Scope::Match, 0, "aces"), 0);
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Player;
+
+// Playing conditions a matchup is simulated under, applied on top of both players' parameters
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Surface {
+    pub name: String,
+    // Added to both players' serve_win_prob, e.g. -0.03 on clay or 0.02 on grass
+    #[serde(default)]
+    pub serve_win_modifier: f64,
+    // Multiplies both players' ace_prob
+    #[serde(default = "default_ace_factor")]
+    pub ace_factor: f64,
+    // Extra serve_win_prob modifier by player name, for players who suit or dislike the surface
+    #[serde(default)]
+    pub player_modifiers: HashMap<String, f64>,
+}
+
+fn default_ace_factor() -> f64 {
+    1.0
+}
+
+impl Surface {
+    pub fn apply(&self, player: &Player) -> Player {
+        let serve_shift = self.serve_win_modifier + self.player_modifiers.get(&player.name).copied().unwrap_or(0.0);
+        let mut adjusted = player.clone();
+        adjusted.serve_win_prob = (player.serve_win_prob + serve_shift).clamp(0.0, 1.0);
+        adjusted.ace_prob = (player.ace_prob * self.ace_factor).clamp(0.0, 1.0);
+        if let Some(placement) = &mut adjusted.serve_placement {
+            for profile in [&mut placement.wide, &mut placement.body, &mut placement.t] {
+                profile.serve_win_prob = (profile.serve_win_prob + serve_shift).clamp(0.0, 1.0);
+                profile.ace_prob = (profile.ace_prob * self.ace_factor).clamp(0.0, 1.0);
+            }
+        }
+        adjusted
+    }
+}
@@ -0,0 +1,13 @@
+use comfy_table::{presets, CellAlignment, Table};
+
+// A report table with every column after the first right-aligned: drawn with box characters, or with `plain`
+// as columns of plain text lined up with spaces, for piping into other tools
+pub fn new(header: &[&str], plain: bool) -> Table {
+    let mut table = Table::new();
+    table.load_preset(if plain { presets::NOTHING } else { presets::UTF8_FULL_CONDENSED });
+    table.set_header(header.to_vec());
+    for column in table.column_iter_mut().skip(1) {
+        column.set_cell_alignment(CellAlignment::Right);
+    }
+    table
+}
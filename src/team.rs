@@ -0,0 +1,859 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use rayon::prelude::*;
+use serde::Deserialize;
+
+use crate::scoring::ScoringRules;
+use crate::{find_player, flag_value, match_seed, FirstServe, ModelConfig, PlacementProfile, Player, ReturnProfile, ServePlacement, TennisMatch};
+
+// A team event: two rosters, and either days of rubbers where each match won scores that day's points for
+// the winner's team, as in the Laver Cup, a tie of rubbers with lineups chosen from the rosters, as in the
+// Billie Jean King Cup or United Cup, a college dual match, or a World TeamTennis match
+#[derive(Deserialize)]
+struct TeamEventFile {
+    teams: [TeamRoster; 2],
+    #[serde(default)]
+    days: Vec<Day>,
+    #[serde(default)]
+    tie: Option<Tie>,
+    #[serde(default)]
+    dual: Option<Dual>,
+    #[serde(default)]
+    wtt: Option<Wtt>,
+    // Points that win the event; more than half of the points on offer unless set
+    #[serde(default)]
+    target: Option<u32>,
+    // Played when the teams finish level on points, like the Laver Cup's deciding doubles
+    #[serde(default)]
+    decider: Option<Rubber>,
+    #[serde(default = "default_best_of")]
+    best_of: i32,
+    #[serde(default)]
+    grand_slam: bool,
+    #[serde(default = "default_simulations")]
+    num_simulations: usize,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    model: ModelConfig,
+}
+
+#[derive(Deserialize)]
+struct TeamRoster {
+    name: String,
+    players: Vec<Player>,
+    // Named groups of players that tie rubbers are filled from, e.g. "men" and "women"
+    #[serde(default)]
+    pools: HashMap<String, Vec<String>>,
+    // The team's players for each tie rubber, by rubber name
+    #[serde(default)]
+    lineup: Option<HashMap<String, Vec<String>>>,
+    // Chemistry of doubles pairs keyed "A/B" with the players' exact names; see `Team`
+    #[serde(default)]
+    chemistry: HashMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+struct Day {
+    points: u32,
+    rubbers: Vec<Rubber>,
+}
+
+// One match of the event, naming the first team's side and then the second team's
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Rubber {
+    Singles([String; 2]),
+    Doubles([[String; 2]; 2]),
+}
+
+// A tie: rubbers worth one point each, won by the team winning most of them
+#[derive(Deserialize)]
+struct Tie {
+    // In order of play
+    rubbers: Vec<Slot>,
+}
+
+// A tie rubber, with the pool each of a team's players in it comes from: one pool for singles, two for
+// doubles, e.g. ["men", "women"] for mixed doubles. The pool "any" is the whole roster.
+#[derive(Deserialize)]
+struct Slot {
+    name: String,
+    pools: Vec<String>,
+}
+
+// An NCAA dual match: the doubles matches decide one point between them, then each singles match is a
+// point, and the first team to a majority of the points wins
+#[derive(Deserialize)]
+struct Dual {
+    // One set each, usually on three courts
+    doubles: Vec<[[String; 2]; 2]>,
+    // In court order, usually six
+    singles: Vec<[String; 2]>,
+}
+
+// A World TeamTennis match: one set of each discipline, each first to five games with no-ad games and a
+// nine-point tiebreak at 4-4, won by the team with more games in total
+#[derive(Deserialize)]
+struct Wtt {
+    // In order of play
+    sets: Vec<WttSet>,
+}
+
+#[derive(Deserialize)]
+struct WttSet {
+    name: String,
+    #[serde(flatten)]
+    rubber: Rubber,
+}
+
+fn default_best_of() -> i32 {
+    3
+}
+
+fn default_simulations() -> usize {
+    2000
+}
+
+// A rubber with its players resolved from the rosters
+struct Matchup {
+    day: usize,
+    points: u32,
+    sides: [Player; 2],
+}
+
+// A doubles team made from two singles players, played by the match engine as one player with team-level
+// parameters. The engine has no doubles rules, so serve rotation between the partners isn't modelled.
+pub struct Team {
+    pub players: [Player; 2],
+    // Added to the team's serve_win_prob and taken off its return error_prob: positive for a settled
+    // pairing, negative for partners who rarely play together
+    pub chemistry: f64,
+}
+
+impl Team {
+    pub fn from_players(p1: &Player, p2: &Player) -> Team {
+        Team { players: [p1.clone(), p2.clone()], chemistry: 0.0 }
+    }
+
+    pub fn with_chemistry(mut self, chemistry: f64) -> Team {
+        self.chemistry = chemistry;
+        self
+    }
+
+    // The partners serve alternate service games and share the returns, so the team's serve and return
+    // parameters average theirs. Serve placement and return profiles are only kept when both partners have one.
+    pub fn profile(&self) -> Player {
+        let [a, b] = &self.players;
+        let average = |value: fn(&Player) -> f64| (value(a) + value(b)) / 2.0;
+        let serve_placement = match (&a.serve_placement, &b.serve_placement) {
+            (Some(first), Some(second)) => {
+                let direction = |first: &PlacementProfile, second: &PlacementProfile| PlacementProfile {
+                    share: (first.share + second.share) / 2.0,
+                    ace_prob: (first.ace_prob + second.ace_prob) / 2.0,
+                    serve_win_prob: ((first.serve_win_prob + second.serve_win_prob) / 2.0 + self.chemistry).clamp(0.0, 1.0),
+                };
+                Some(ServePlacement { wide: direction(&first.wide, &second.wide), body: direction(&first.body, &second.body), t: direction(&first.t, &second.t) })
+            }
+            _ => None,
+        };
+        let return_profile = match (&a.return_profile, &b.return_profile) {
+            (Some(first), Some(second)) => Some(ReturnProfile {
+                error_prob: ((first.error_prob + second.error_prob) / 2.0 - self.chemistry).clamp(0.0, 1.0),
+                deep_prob: (first.deep_prob + second.deep_prob) / 2.0,
+                aggressiveness: (first.aggressiveness + second.aggressiveness) / 2.0,
+            }),
+            _ => None,
+        };
+        let first_serve = match (&a.first_serve, &b.first_serve) {
+            (Some(first), Some(second)) => Some(FirstServe {
+                in_prob: (first.in_prob + second.in_prob) / 2.0,
+                second_serve_penalty: (first.second_serve_penalty + second.second_serve_penalty) / 2.0,
+            }),
+            _ => None,
+        };
+        Player {
+            name: format!("{}/{}", a.name, b.name),
+            serve_win_prob: (average(|player| player.serve_win_prob) + self.chemistry).clamp(0.0, 1.0),
+            ace_prob: average(|player| player.ace_prob),
+            double_fault_prob: average(|player| player.double_fault_prob),
+            tiebreak_serve_modifier: average(|player| player.tiebreak_serve_modifier),
+            deuce_court_modifier: average(|player| player.deuce_court_modifier),
+            ad_court_modifier: average(|player| player.ad_court_modifier),
+            serve_placement,
+            return_profile,
+            first_serve,
+            // The first partner calls the toss
+            toss_election: a.toss_election,
+            // Singles ratings say nothing about a pair
+            elo: None,
+        }
+    }
+}
+
+// The chemistry the roster gives a pair, 0 unless listed
+fn pair_chemistry(roster: &TeamRoster, a: &str, b: &str) -> f64 {
+    roster
+        .chemistry
+        .iter()
+        .find(|(pair, _)| pair.split_once('/').is_some_and(|(first, second)| [first, second] == [a, b] || [first, second] == [b, a]))
+        .map_or(0.0, |(_, chemistry)| *chemistry)
+}
+
+fn doubles_team(roster: &TeamRoster, a: &Player, b: &Player) -> Player {
+    Team::from_players(a, b).with_chemistry(pair_chemistry(roster, &a.name, &b.name)).profile()
+}
+
+fn resolve(teams: &[TeamRoster; 2], rubber: &Rubber) -> Result<[Player; 2], String> {
+    let sides = match rubber {
+        Rubber::Singles(names) => [0, 1].map(|team| find_player(&teams[team].players, &names[team]).cloned()),
+        Rubber::Doubles(pairs) => [0, 1].map(|team| {
+            let first = find_player(&teams[team].players, &pairs[team][0])?;
+            let second = find_player(&teams[team].players, &pairs[team][1])?;
+            if first.name == second.name {
+                return Err(format!("{} can't partner themselves", first.name));
+            }
+            Ok(doubles_team(&teams[team], first, second))
+        }),
+    };
+    let [first, second] = sides;
+    Ok([first?, second?])
+}
+
+// Outcome of one simulated event
+struct EventResult {
+    // Index of the winning team, or None when the teams finish level without a decider
+    winner: Option<usize>,
+    // Whether the first team won each rubber, in order, with the decider last
+    rubbers_won: Vec<bool>,
+    // Whether each rubber was played before the event was decided
+    rubbers_live: Vec<bool>,
+}
+
+// Whether the first side won, and the match's length in shots
+fn play(matchup: &Matchup, best_of: i32, grand_slam: bool, model: &ModelConfig, seed: u64) -> (bool, i32) {
+    let mut match_sim = TennisMatch::new(matchup.sides[0].clone(), matchup.sides[1].clone(), ScoringRules::standard(best_of, grand_slam), model.clone(), seed);
+    let won = match_sim.play_match().name == matchup.sides[0].name;
+    (won, match_sim.total_shots)
+}
+
+fn simulate_event(file: &TeamEventFile, matchups: &[Matchup], decider: Option<&Matchup>, target: u32, seed: u64, event: usize) -> EventResult {
+    let rubbers = matchups.len() + 1;
+    let mut points = [0, 0];
+    let mut winner = None;
+    let mut rubbers_won = Vec::with_capacity(rubbers);
+    let mut rubbers_live = Vec::with_capacity(rubbers);
+    // Every rubber is played, live or not, so each rubber's result is recorded in every event
+    for (index, matchup) in matchups.iter().enumerate() {
+        let (won, _) = play(matchup, file.best_of, file.grand_slam, &file.model, match_seed(seed, event * rubbers + index + 1));
+        rubbers_won.push(won);
+        rubbers_live.push(winner.is_none());
+        if winner.is_none() {
+            let team = if won { 0 } else { 1 };
+            points[team] += matchup.points;
+            if points[team] >= target {
+                winner = Some(team);
+            }
+        }
+    }
+    if let Some(decider) = decider {
+        let (won, _) = play(decider, file.best_of, file.grand_slam, &file.model, match_seed(seed, event * rubbers + rubbers));
+        rubbers_won.push(won);
+        rubbers_live.push(winner.is_none() && points[0] == points[1]);
+        if winner.is_none() && points[0] == points[1] {
+            winner = Some(if won { 0 } else { 1 });
+        }
+    }
+    if winner.is_none() && points[0] != points[1] {
+        winner = Some(if points[0] > points[1] { 0 } else { 1 });
+    }
+    EventResult { winner, rubbers_won, rubbers_live }
+}
+
+// team <event.json> [--seed <n>] [--optimize <team>]: simulates a team event and reports each team's chance of
+// winning it. Events played over days also report which rubbers swing them the most; ties can search for the
+// lineup that gives a team the best chance.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let path = args.first().filter(|arg| !arg.starts_with("--")).ok_or("usage: team <event file> [--seed <n>] [--optimize <team>]")?;
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    let file: TeamEventFile = serde_json::from_str(&contents).map_err(|e| format!("invalid team event file {}: {}", path, e))?;
+    if file.teams[0].name == file.teams[1].name {
+        return Err(format!("both teams are called {}", file.teams[0].name));
+    }
+    if let Some(player) = file.teams[0].players.iter().find(|player| file.teams[1].players.iter().any(|other| other.name == player.name)) {
+        return Err(format!("{} is on both teams", player.name));
+    }
+    for roster in &file.teams {
+        for pair in roster.chemistry.keys() {
+            let names = pair.split_once('/').map(|(first, second)| [first, second]);
+            if !names.is_some_and(|names| names.iter().all(|name| roster.players.iter().any(|player| player.name == *name))) {
+                return Err(format!("{}'s chemistry pair '{}' isn't two of its players' exact names joined by '/'", roster.name, pair));
+            }
+        }
+    }
+    let seed = match flag_value(args, "--seed") {
+        Some(seed) => seed.parse().map_err(|e| format!("invalid --seed: {}", e))?,
+        None => file.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+    };
+
+    if [!file.days.is_empty(), file.tie.is_some(), file.dual.is_some(), file.wtt.is_some()].iter().filter(|given| **given).count() > 1 {
+        return Err("a team event has one of days, a tie, a dual match or a WTT match".to_string());
+    }
+    if flag_value(args, "--optimize").is_some() && file.tie.is_none() {
+        return Err("--optimize only applies to ties".to_string());
+    }
+    match (&file.tie, &file.dual, &file.wtt) {
+        (Some(tie), _, _) => run_tie(&file, tie, seed, flag_value(args, "--optimize")),
+        (None, Some(dual), _) => run_dual(&file, dual, seed),
+        (None, None, Some(wtt)) => run_wtt(&file, wtt, seed),
+        (None, None, None) => run_days(&file, seed),
+    }
+}
+
+fn run_days(file: &TeamEventFile, seed: u64) -> Result<(), String> {
+    let mut matchups = Vec::new();
+    for (day, day_rubbers) in file.days.iter().enumerate() {
+        for rubber in &day_rubbers.rubbers {
+            matchups.push(Matchup { day: day + 1, points: day_rubbers.points, sides: resolve(&file.teams, rubber)? });
+        }
+    }
+    let decider = match &file.decider {
+        Some(rubber) => Some(Matchup { day: file.days.len(), points: 0, sides: resolve(&file.teams, rubber)? }),
+        None => None,
+    };
+    let total: u32 = matchups.iter().map(|matchup| matchup.points).sum();
+    let target = file.target.unwrap_or(total / 2 + 1);
+    if matchups.is_empty() || target == 0 || target > total {
+        return Err(format!("a target of {} points can't be reached with the {} points on offer", target, total));
+    }
+    let results: Vec<EventResult> = (0..file.num_simulations)
+        .into_par_iter()
+        .map(|event| simulate_event(file, &matchups, decider.as_ref(), target, seed, event))
+        .collect();
+
+    let events = results.len().max(1) as f64;
+    let teams = [&file.teams[0].name, &file.teams[1].name];
+    println!("{} vs {}: {} simulated events, {} points to win out of {} (seed {})", teams[0], teams[1], results.len(), target, total, seed);
+    for (index, team) in teams.iter().enumerate() {
+        let wins = results.iter().filter(|result| result.winner == Some(index)).count();
+        println!("  {:<20} {:>6.2}%", team, wins as f64 / events * 100.0);
+    }
+    let draws = results.iter().filter(|result| result.winner.is_none()).count();
+    if draws > 0 {
+        println!("  {:<20} {:>6.2}%", "level (no decider)", draws as f64 / events * 100.0);
+    }
+
+    // A rubber's swing is how much winning it rather than losing it moves the first team's chance of the event
+    let mut rows = Vec::new();
+    for (index, matchup) in matchups.iter().chain(decider.as_ref()).enumerate() {
+        let won: Vec<&EventResult> = results.iter().filter(|result| result.rubbers_won[index]).collect();
+        let lost: Vec<&EventResult> = results.iter().filter(|result| !result.rubbers_won[index]).collect();
+        let event_win_rate = |group: &[&EventResult]| group.iter().filter(|result| result.winner == Some(0)).count() as f64 / group.len().max(1) as f64;
+        let swing = event_win_rate(&won) - event_win_rate(&lost);
+        let live = results.iter().filter(|result| result.rubbers_live[index]).count() as f64 / events;
+        let label = if index == matchups.len() { "decider".to_string() } else { format!("day {} ({} pt)", matchup.day, matchup.points) };
+        rows.push((label, format!("{} vs {}", matchup.sides[0].name, matchup.sides[1].name), won.len() as f64 / events, live, swing));
+    }
+
+    let width = rows.iter().map(|(_, name, ..)| name.chars().count()).max().unwrap_or(0);
+    println!("\n{:<12} {:<width$} {:>8} {:>7} {:>7}", "rubber", "matchup", "p1 win", "live", "swing");
+    for (label, name, win, live, swing) in &rows {
+        println!("{:<12} {:<width$} {:>7.2}% {:>6.1}% {:>+6.1}pt", label, name, win * 100.0, live * 100.0, swing * 100.0);
+    }
+
+    rows.sort_by(|a, b| b.4.total_cmp(&a.4));
+    println!("\nMost decisive matchups:");
+    for (label, name, _, _, swing) in rows.iter().take(3) {
+        println!("  {} {}: {:+.1} points on {}'s chance of winning", label, name, swing * 100.0, teams[0]);
+    }
+    println!("p1 win is the first team's chance of winning the rubber; live is how often it is played before the event is decided");
+    Ok(())
+}
+
+// A team's players for each rubber of a tie, in order
+type Lineup = Vec<Vec<String>>;
+
+// The players of the roster that can be picked from `pool`
+fn pool_players<'a>(roster: &'a TeamRoster, pool: &str) -> Result<Vec<&'a Player>, String> {
+    if pool == "any" {
+        return Ok(roster.players.iter().collect());
+    }
+    let names = roster.pools.get(pool).ok_or_else(|| format!("{} has no pool '{}'", roster.name, pool))?;
+    names.iter().map(|name| find_player(&roster.players, name)).collect()
+}
+
+// Nobody plays two singles or two doubles rubbers of a tie
+fn check_lineup(lineup: &Lineup) -> Result<(), String> {
+    let mut rubbers_played: HashMap<(&str, usize), usize> = HashMap::new();
+    for players in lineup {
+        for player in players {
+            let count = rubbers_played.entry((player, players.len())).or_default();
+            *count += 1;
+            if *count > 1 {
+                return Err(format!("{} plays two {} rubbers", player, if players.len() == 1 { "singles" } else { "doubles" }));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Every lineup the roster can field for the tie
+fn lineups(roster: &TeamRoster, tie: &Tie) -> Result<Vec<Lineup>, String> {
+    let mut lineups: Vec<Lineup> = vec![Vec::new()];
+    for slot in &tie.rubbers {
+        let pools = slot.pools.iter().map(|pool| pool_players(roster, pool)).collect::<Result<Vec<_>, _>>()?;
+        let mut choices: Vec<Vec<String>> = match pools.as_slice() {
+            [pool] => pool.iter().map(|player| vec![player.name.clone()]).collect(),
+            [first, second] => first
+                .iter()
+                .flat_map(|a| second.iter().filter(|b| b.name != a.name).map(|b| {
+                    let mut pair = vec![a.name.clone(), b.name.clone()];
+                    pair.sort();
+                    pair
+                }))
+                .collect(),
+            _ => return Err(format!("rubber '{}' needs one pool for singles or two for doubles", slot.name)),
+        };
+        choices.sort();
+        choices.dedup();
+        lineups = lineups
+            .into_iter()
+            .flat_map(|lineup| choices.iter().map(move |choice| [lineup.clone(), vec![choice.clone()]].concat()))
+            .filter(|lineup| check_lineup(lineup).is_ok())
+            .collect();
+    }
+    Ok(lineups)
+}
+
+// The lineup given in the roster, if any, checked against the tie's rubbers and pools
+fn given_lineup(roster: &TeamRoster, tie: &Tie) -> Result<Option<Lineup>, String> {
+    let Some(given) = &roster.lineup else {
+        return Ok(None);
+    };
+    if let Some(name) = given.keys().find(|name| !tie.rubbers.iter().any(|slot| &slot.name == *name)) {
+        return Err(format!("{}'s lineup names rubber '{}', which isn't in the tie", roster.name, name));
+    }
+    let mut lineup = Vec::new();
+    for slot in &tie.rubbers {
+        let names = given.get(&slot.name).ok_or_else(|| format!("{}'s lineup has no players for {}", roster.name, slot.name))?;
+        if names.len() != slot.pools.len() {
+            return Err(format!("{} needs {} player(s) from {}, not {}", slot.name, slot.pools.len(), roster.name, names.len()));
+        }
+        let mut players = Vec::new();
+        for (name, pool) in names.iter().zip(&slot.pools) {
+            let player = find_player(&roster.players, name)?;
+            if !pool_players(roster, pool)?.iter().any(|candidate| candidate.name == player.name) {
+                return Err(format!("{} isn't in {}'s pool '{}' for {}", player.name, roster.name, pool, slot.name));
+            }
+            players.push(player.name.clone());
+        }
+        lineup.push(players);
+    }
+    check_lineup(&lineup).map_err(|e| format!("{}'s lineup: {}", roster.name, e))?;
+    Ok(Some(lineup))
+}
+
+fn describe_lineup(tie: &Tie, lineup: &Lineup) -> String {
+    tie.rubbers.iter().zip(lineup).map(|(slot, players)| format!("{}: {}", slot.name, players.join("/"))).collect::<Vec<_>>().join(", ")
+}
+
+// Rubber and tie win probabilities, with each rubber matchup simulated once however many lineups it appears in
+struct TieModel<'a> {
+    file: &'a TeamEventFile,
+    tie: &'a Tie,
+    seed: u64,
+    rubber_win_probs: HashMap<[Vec<String>; 2], f64>,
+}
+
+impl TieModel<'_> {
+    fn side(&self, team: usize, names: &[String]) -> Player {
+        let player = |name: &String| self.file.teams[team].players.iter().find(|player| &player.name == name).unwrap();
+        match names {
+            [single] => player(single).clone(),
+            [first, second] => doubles_team(&self.file.teams[team], player(first), player(second)),
+            _ => unreachable!("rubbers have one or two players a side"),
+        }
+    }
+
+    // The first team's chance of winning the rubber. Every matchup replays the same match seeds, so lineups
+    // are compared on common random numbers.
+    fn rubber_win_prob(&mut self, names: [&Vec<String>; 2]) -> f64 {
+        let key = [names[0].clone(), names[1].clone()];
+        if let Some(prob) = self.rubber_win_probs.get(&key) {
+            return *prob;
+        }
+        let matchup = Matchup { day: 1, points: 1, sides: [self.side(0, names[0]), self.side(1, names[1])] };
+        let (file, seed) = (self.file, self.seed);
+        let wins = (1..=file.num_simulations)
+            .into_par_iter()
+            .filter(|match_number| play(&matchup, file.best_of, file.grand_slam, &file.model, match_seed(seed, *match_number)).0)
+            .count();
+        let prob = wins as f64 / file.num_simulations.max(1) as f64;
+        self.rubber_win_probs.insert(key, prob);
+        prob
+    }
+
+    // Each team's chance of winning the tie, from the distribution of the number of rubbers the first team wins
+    fn tie_win_probs(&mut self, lineups: [&Lineup; 2]) -> [f64; 2] {
+        let rubbers = self.tie.rubbers.len();
+        let mut distribution = vec![1.0];
+        for (first, second) in lineups[0].iter().zip(lineups[1]) {
+            let prob = self.rubber_win_prob([first, second]);
+            let mut next = vec![0.0; distribution.len() + 1];
+            for (won, weight) in distribution.iter().enumerate() {
+                next[won] += weight * (1.0 - prob);
+                next[won + 1] += weight * prob;
+            }
+            distribution = next;
+        }
+        let target = rubbers / 2 + 1;
+        let first = distribution.iter().skip(target).sum();
+        let second = distribution.iter().take((rubbers + 1).saturating_sub(target)).sum();
+        [first, second]
+    }
+}
+
+fn run_tie(file: &TeamEventFile, tie: &Tie, seed: u64, optimize: Option<&str>) -> Result<(), String> {
+    if tie.rubbers.is_empty() {
+        return Err("a tie needs at least one rubber".to_string());
+    }
+    let given = [given_lineup(&file.teams[0], tie)?, given_lineup(&file.teams[1], tie)?];
+    let mut model = TieModel { file, tie, seed, rubber_win_probs: HashMap::new() };
+    let teams = [&file.teams[0].name, &file.teams[1].name];
+    println!(
+        "{} vs {}: tie of {} rubbers, {} to win ({} simulations per rubber, seed {})",
+        teams[0], teams[1], tie.rubbers.len(), tie.rubbers.len() / 2 + 1, file.num_simulations, seed,
+    );
+
+    let Some(optimize) = optimize else {
+        let [Some(first), Some(second)] = &given else {
+            return Err("both teams need a lineup to simulate the tie; use --optimize <team> to search for one".to_string());
+        };
+        let width = tie.rubbers.iter().map(|slot| slot.name.chars().count()).max().unwrap_or(0);
+        println!("\n{:<width$} {:<40} {:>8}", "rubber", "matchup", "p1 win");
+        for (index, slot) in tie.rubbers.iter().enumerate() {
+            let prob = model.rubber_win_prob([&first[index], &second[index]]);
+            println!("{:<width$} {:<40} {:>7.2}%", slot.name, format!("{} vs {}", first[index].join("/"), second[index].join("/")), prob * 100.0);
+        }
+        let probs = model.tie_win_probs([first, second]);
+        println!();
+        for (team, prob) in teams.iter().zip(probs) {
+            println!("  {:<20} {:>6.2}%", team, prob * 100.0);
+        }
+        return Ok(());
+    };
+
+    let team = teams.iter().position(|name| name.eq_ignore_ascii_case(optimize)).ok_or_else(|| format!("no team called {}", optimize))?;
+    let opponent = 1 - team;
+    let candidates = lineups(&file.teams[team], tie)?;
+    if candidates.is_empty() {
+        return Err(format!("{} can't field a lineup for the tie", teams[team]));
+    }
+    // The team's chance of winning with `lineup` against the opponent's `reply`
+    let mut win_prob = |lineup: &Lineup, reply: &Lineup| {
+        let mut sides = [lineup, reply];
+        if team == 1 {
+            sides.swap(0, 1);
+        }
+        model.tie_win_probs(sides)[team]
+    };
+
+    match &given[opponent] {
+        Some(reply) => {
+            println!("Best lineups for {} against {}'s lineup ({} candidates):", teams[team], teams[opponent], candidates.len());
+            let mut ranked: Vec<(f64, &Lineup)> = candidates.iter().map(|lineup| (win_prob(lineup, reply), lineup)).collect();
+            ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+            for (prob, lineup) in ranked.iter().take(5) {
+                println!("  {:>6.2}%  {}", prob * 100.0, describe_lineup(tie, lineup));
+            }
+            if let Some(lineup) = &given[team] {
+                println!("{}'s own lineup: {:.2}%", teams[team], win_prob(lineup, reply) * 100.0);
+            }
+        }
+        None => {
+            // Without the opponent's lineup, each candidate is judged by the opponent's best reply to it
+            let replies = lineups(&file.teams[opponent], tie)?;
+            if replies.is_empty() {
+                return Err(format!("{} can't field a lineup for the tie", teams[opponent]));
+            }
+            println!("Best lineups for {} against any of {}'s {} lineups ({} candidates):", teams[team], teams[opponent], replies.len(), candidates.len());
+            let mut ranked = Vec::new();
+            for lineup in &candidates {
+                let probs: Vec<(f64, &Lineup)> = replies.iter().map(|reply| (win_prob(lineup, reply), reply)).collect();
+                let (worst, best_reply) = probs.iter().copied().min_by(|a, b| a.0.total_cmp(&b.0)).unwrap();
+                let average = probs.iter().map(|(prob, _)| prob).sum::<f64>() / probs.len() as f64;
+                ranked.push((worst, average, lineup, best_reply));
+            }
+            ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.total_cmp(&a.1)));
+            println!("{:>10} {:>9}  lineup", "worst case", "average");
+            for (worst, average, lineup, _) in ranked.iter().take(5) {
+                println!("{:>9.2}% {:>8.2}%  {}", worst * 100.0, average * 100.0, describe_lineup(tie, lineup));
+            }
+            let (_, _, _, best_reply) = ranked[0];
+            println!("{}'s best reply to the top lineup: {}", teams[opponent], describe_lineup(tie, best_reply));
+        }
+    }
+    println!("Rubbers are simulated independently, so the order they are played in doesn't change the tie's outcome");
+    Ok(())
+}
+
+// Outcome of one simulated dual match
+struct DualResult {
+    winner: usize,
+    won_doubles_point: bool,
+    // Whether the first team won each singles court
+    courts_won: Vec<bool>,
+    // The singles court, numbered from 1, whose result gave the winner its majority
+    clinching_court: usize,
+}
+
+// The doubles are played first and the singles courts start together, so singles results come in the order
+// the matches finish, taking a match's length in shots as its duration. The clinching court is the one
+// finishing when a team reaches its majority; later matches are abandoned in a real dual match but are
+// still played here so every court has a result.
+fn simulate_dual(file: &TeamEventFile, doubles: &[Matchup], singles: &[Matchup], target: u32, seed: u64, event: usize) -> DualResult {
+    let courts = doubles.len() + singles.len();
+    let mut match_number = event * courts;
+    let mut doubles_won = 0;
+    for matchup in doubles {
+        match_number += 1;
+        if play(matchup, 1, false, &file.model, match_seed(seed, match_number)).0 {
+            doubles_won += 1;
+        }
+    }
+    let won_doubles_point = doubles_won * 2 > doubles.len();
+    let mut points = if won_doubles_point { [1, 0] } else { [0, 1] };
+
+    let mut finishes = Vec::new();
+    for (court, matchup) in singles.iter().enumerate() {
+        match_number += 1;
+        let (won, shots) = play(matchup, file.best_of, file.grand_slam, &file.model, match_seed(seed, match_number));
+        finishes.push((shots, court, won));
+    }
+    let courts_won = finishes.iter().map(|(_, _, won)| *won).collect();
+    finishes.sort();
+    let mut winner = None;
+    for (_, court, won) in finishes {
+        let team = if won { 0 } else { 1 };
+        points[team] += 1;
+        if points[team] >= target {
+            winner = Some((team, court + 1));
+            break;
+        }
+    }
+    let (winner, clinching_court) = winner.expect("a dual match has an odd number of points");
+    DualResult { winner, won_doubles_point, courts_won, clinching_court }
+}
+
+fn run_dual(file: &TeamEventFile, dual: &Dual, seed: u64) -> Result<(), String> {
+    if dual.doubles.len().is_multiple_of(2) || !dual.singles.len().is_multiple_of(2) {
+        return Err("a dual match needs an odd number of doubles courts and an even number of singles courts".to_string());
+    }
+    let resolve_all = |rubbers: Vec<Rubber>| -> Result<Vec<Matchup>, String> {
+        rubbers.iter().map(|rubber| Ok(Matchup { day: 1, points: 1, sides: resolve(&file.teams, rubber)? })).collect()
+    };
+    let doubles = resolve_all(dual.doubles.iter().cloned().map(Rubber::Doubles).collect())?;
+    let singles = resolve_all(dual.singles.iter().cloned().map(Rubber::Singles).collect())?;
+    for team in 0..2 {
+        let mut names: Vec<&String> = singles.iter().map(|matchup| &matchup.sides[team].name).collect();
+        names.sort();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("{} plays two singles courts", pair[0]));
+        }
+    }
+    let points = singles.len() as u32 + 1;
+    let target = points / 2 + 1;
+
+    let results: Vec<DualResult> = (0..file.num_simulations)
+        .into_par_iter()
+        .map(|event| simulate_dual(file, &doubles, &singles, target, seed, event))
+        .collect();
+
+    let events = results.len().max(1) as f64;
+    let teams = [&file.teams[0].name, &file.teams[1].name];
+    println!("{} vs {}: {} simulated dual matches, {} points to win (seed {})", teams[0], teams[1], results.len(), target, seed);
+    for (index, team) in teams.iter().enumerate() {
+        let wins = results.iter().filter(|result| result.winner == index).count();
+        println!("  {:<20} {:>6.2}%", team, wins as f64 / events * 100.0);
+    }
+
+    let with_point: Vec<&DualResult> = results.iter().filter(|result| result.won_doubles_point).collect();
+    let without_point: Vec<&DualResult> = results.iter().filter(|result| !result.won_doubles_point).collect();
+    let win_rate = |group: &[&DualResult]| group.iter().filter(|result| result.winner == 0).count() as f64 / group.len().max(1) as f64;
+    println!(
+        "\nDoubles point: {} win it {:.2}% of the time; they win the dual match {:.2}% of the time with it and {:.2}% without",
+        teams[0], with_point.len() as f64 / events * 100.0, win_rate(&with_point) * 100.0, win_rate(&without_point) * 100.0,
+    );
+    let width = singles.iter().map(|matchup| matchup.sides[0].name.chars().count() + matchup.sides[1].name.chars().count() + 4).max().unwrap_or(0);
+    println!("\n{:<6} {:<width$} {:>8} {:>9}", "court", "singles", "p1 win", "clinches");
+    for (court, matchup) in singles.iter().enumerate() {
+        let won = results.iter().filter(|result| result.courts_won[court]).count() as f64 / events;
+        let clinched = results.iter().filter(|result| result.clinching_court == court + 1).count() as f64 / events;
+        println!("{:<6} {:<width$} {:>7.2}% {:>8.2}%", court + 1, format!("{} vs {}", matchup.sides[0].name, matchup.sides[1].name), won * 100.0, clinched * 100.0);
+    }
+    let expected_court = results.iter().map(|result| result.clinching_court as f64).sum::<f64>() / events;
+    println!("\nExpected clinching court: {:.2}", expected_court);
+    Ok(())
+}
+
+// Plays WTT points between a set's two sides with the match engine, which only serves the points here: the
+// engine's games and sets are replaced by WTT's scoring
+struct WttPoints {
+    match_sim: TennisMatch,
+    sides: [Player; 2],
+    // Side serving the next game
+    server: usize,
+}
+
+impl WttPoints {
+    fn new(matchup: &Matchup, model: &ModelConfig, seed: u64) -> WttPoints {
+        let mut match_sim = TennisMatch::new(matchup.sides[0].clone(), matchup.sides[1].clone(), ScoringRules::standard(1, false), model.clone(), seed);
+        let server = if match_sim.coin_toss().name == matchup.sides[0].name { 0 } else { 1 };
+        WttPoints { match_sim, sides: matchup.sides.clone(), server }
+    }
+
+    // The engine reads the point score of the game in progress for its pressure model
+    fn reset_points(&mut self) {
+        self.match_sim.score.points = [0, 0];
+    }
+
+    // Index of the side winning a point served by `server`
+    fn point(&mut self, server: usize) -> usize {
+        self.match_sim.set_server(server);
+        if self.match_sim.play_point().name == self.sides[0].name { 0 } else { 1 }
+    }
+
+    // No-ad: the first side to four points wins, so 3-3 is a deciding point
+    fn game(&mut self) -> usize {
+        let server = self.server;
+        self.server = 1 - server;
+        self.reset_points();
+        let mut points = [0, 0];
+        loop {
+            let winner = self.point(server);
+            points[winner] += 1;
+            if points[winner] == 4 {
+                return winner;
+            }
+        }
+    }
+
+    // First to `target` points and `margin` ahead, with the serve changing after the first point and then
+    // every two points
+    fn tiebreak(&mut self, target: i32, margin: i32) -> usize {
+        let first_server = self.server;
+        self.server = 1 - first_server;
+        self.reset_points();
+        let mut points = [0, 0];
+        for played in 0.. {
+            let server = if (played + 1) / 2 % 2 == 0 { first_server } else { 1 - first_server };
+            let winner = self.point(server);
+            points[winner] += 1;
+            if points[winner] >= target && points[winner] - points[1 - winner] >= margin {
+                return winner;
+            }
+        }
+        unreachable!()
+    }
+
+    // Games won by each side: first to five, with a nine-point tiebreak (first to five points) at 4-4
+    fn set(&mut self) -> [u32; 2] {
+        let mut games = [0, 0];
+        while games[0] < 5 && games[1] < 5 {
+            let winner = if games == [4, 4] { self.tiebreak(5, 1) } else { self.game() };
+            games[winner] += 1;
+        }
+        games
+    }
+}
+
+// Outcome of one simulated WTT match
+struct WttResult {
+    winner: usize,
+    // Games won by each team in each set, and in overtime
+    set_games: Vec<[u32; 2]>,
+    overtime_games: [u32; 2],
+    overtime: bool,
+    supertiebreak: bool,
+}
+
+// Sets are played in order and their games added up. When the team trailing on games wins the last set, play
+// goes to overtime: the last set goes on until the leading team wins a game or the trailing team draws level.
+// Teams level on games play a supertiebreak (first to seven, by two) with the last set's players.
+fn simulate_wtt(file: &TeamEventFile, sets: &[Matchup], seed: u64, event: usize) -> WttResult {
+    let mut totals = [0, 0];
+    let mut set_games = Vec::new();
+    let mut last = None;
+    for (index, matchup) in sets.iter().enumerate() {
+        let mut points = WttPoints::new(matchup, &file.model, match_seed(seed, event * sets.len() + index + 1));
+        let games = points.set();
+        totals[0] += games[0];
+        totals[1] += games[1];
+        set_games.push(games);
+        last = Some(points);
+    }
+    let mut points = last.expect("a WTT match has at least one set");
+    let last_games = set_games[set_games.len() - 1];
+
+    let set_winner = if last_games[0] > last_games[1] { 0 } else { 1 };
+    let mut overtime_games = [0, 0];
+    let mut overtime = false;
+    if totals[0] != totals[1] && totals[set_winner] < totals[1 - set_winner] {
+        overtime = true;
+        while totals[0] != totals[1] {
+            let winner = points.game();
+            totals[winner] += 1;
+            overtime_games[winner] += 1;
+            if winner != set_winner {
+                break;
+            }
+        }
+    }
+
+    let supertiebreak = totals[0] == totals[1];
+    let winner = if supertiebreak {
+        points.tiebreak(7, 2)
+    } else if totals[0] > totals[1] {
+        0
+    } else {
+        1
+    };
+    WttResult { winner, set_games, overtime_games, overtime, supertiebreak }
+}
+
+fn run_wtt(file: &TeamEventFile, wtt: &Wtt, seed: u64) -> Result<(), String> {
+    if wtt.sets.is_empty() {
+        return Err("a WTT match needs at least one set".to_string());
+    }
+    let sets = wtt.sets.iter().map(|set| Ok(Matchup { day: 1, points: 1, sides: resolve(&file.teams, &set.rubber)? })).collect::<Result<Vec<_>, String>>()?;
+
+    let results: Vec<WttResult> = (0..file.num_simulations)
+        .into_par_iter()
+        .map(|event| simulate_wtt(file, &sets, seed, event))
+        .collect();
+
+    let events = results.len().max(1) as f64;
+    let teams = [&file.teams[0].name, &file.teams[1].name];
+    println!("{} vs {}: {} simulated WTT matches of {} sets (seed {})", teams[0], teams[1], results.len(), sets.len(), seed);
+    for (index, team) in teams.iter().enumerate() {
+        let wins = results.iter().filter(|result| result.winner == index).count();
+        println!("  {:<20} {:>6.2}%", team, wins as f64 / events * 100.0);
+    }
+
+    let width = wtt.sets.iter().map(|set| set.name.chars().count()).max().unwrap_or(0);
+    let matchup_width = sets.iter().map(|matchup| matchup.sides[0].name.chars().count() + matchup.sides[1].name.chars().count() + 4).max().unwrap_or(0);
+    println!("\n{:<width$} {:<matchup_width$} {:>8} {:>13}", "set", "matchup", "p1 set", "games p1/p2");
+    for (index, (set, matchup)) in wtt.sets.iter().zip(&sets).enumerate() {
+        let won = results.iter().filter(|result| result.set_games[index][0] > result.set_games[index][1]).count() as f64 / events;
+        let games = [0, 1].map(|team| results.iter().map(|result| result.set_games[index][team] as f64).sum::<f64>() / events);
+        let label = format!("{} vs {}", matchup.sides[0].name, matchup.sides[1].name);
+        println!("{:<width$} {:<matchup_width$} {:>7.2}% {:>13}", set.name, label, won * 100.0, format!("{:.2}/{:.2}", games[0], games[1]));
+    }
+
+    let total_games = results.iter().map(|result| result.set_games.iter().chain([&result.overtime_games]).map(|games| (games[0] + games[1]) as f64).sum::<f64>()).sum::<f64>() / events;
+    let overtime = results.iter().filter(|result| result.overtime).count() as f64 / events;
+    let supertiebreak = results.iter().filter(|result| result.supertiebreak).count() as f64 / events;
+    println!("\nGames per match, overtime included: {:.2}; overtime in {:.2}% of matches, supertiebreak in {:.2}%", total_games, overtime * 100.0, supertiebreak * 100.0);
+    Ok(())
+}
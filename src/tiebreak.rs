@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use rayon::prelude::*;
+
+use crate::scoring::ScoringRules;
+use crate::what_if::MatchState;
+use crate::{config_from_args, find_player, flag_value, match_seed, SimulationConfig, TennisMatch};
+
+// Winners and final scores of a set of standalone tiebreaks, in player order
+#[derive(Default)]
+pub struct TiebreakResults {
+    pub wins: [usize; 2],
+    pub scores: BTreeMap<[i32; 2], usize>,
+}
+
+impl TiebreakResults {
+    fn add(mut self, score: [i32; 2]) -> Self {
+        self.wins[if score[0] > score[1] { 0 } else { 1 }] += 1;
+        *self.scores.entry(score).or_insert(0) += 1;
+        self
+    }
+
+    fn merge(mut self, other: TiebreakResults) -> Self {
+        for player in 0..2 {
+            self.wins[player] += other.wins[player];
+        }
+        for (score, count) in other.scores {
+            *self.scores.entry(score).or_insert(0) += count;
+        }
+        self
+    }
+}
+
+// Plays `simulations` tiebreaks to `points_to_win` (7, or 10 for a match tiebreak) with the configured players
+// and models. `first_server` is 0 or 1 for player1 or player2, or None for a coin toss before each tiebreak.
+// Tiebreak n draws from the per-match seed of n, as match n of a run would.
+pub fn simulate(config: &SimulationConfig, points_to_win: i32, first_server: Option<usize>, simulations: usize) -> TiebreakResults {
+    (1..=simulations)
+        .into_par_iter()
+        .map(|n| {
+            // A one-set match at 6-6 plays its tiebreak to 7, or to 10 as the final set of a grand slam
+            let mut match_sim = TennisMatch::new(config.player1.clone(), config.player2.clone(), ScoringRules::standard(1, points_to_win == 10), config.model.clone(), match_seed(config.seed, n));
+            let server = match first_server {
+                Some(server) => server,
+                None if match_sim.coin_toss().name == config.player1.name => 0,
+                None => 1,
+            };
+            match_sim.resume_from(&MatchState { sets: [0, 0], games: [6, 6], points: [0, 0], server });
+            match_sim.play_game();
+            match_sim.score.points
+        })
+        .fold(TiebreakResults::default, TiebreakResults::add)
+        .reduce(TiebreakResults::default, TiebreakResults::merge)
+}
+
+// tiebreak [--config <config.json>] [--points 7|10] [--server <player>] [--simulations <n>] [--seed <n>]
+// Simulates standalone tiebreaks and prints each player's chance of winning one and the distribution of final scores
+pub fn run(args: &[String]) -> Result<(), String> {
+    let config = config_from_args(args)?;
+    let points_to_win = match flag_value(args, "--points") {
+        Some("7") | None => 7,
+        Some("10") => 10,
+        Some(other) => return Err(format!("invalid --points '{}'; tiebreaks are played to 7 or 10", other)),
+    };
+    let players = [config.player1.clone(), config.player2.clone()];
+    let first_server = match flag_value(args, "--server") {
+        Some(name) => Some(if find_player(&players, name)?.name == players[0].name { 0 } else { 1 }),
+        None => None,
+    };
+    let simulations = match flag_value(args, "--simulations") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --simulations: {}", e))?,
+        None => config.num_simulations,
+    };
+
+    let results = simulate(&config, points_to_win, first_server, simulations);
+    let serving = first_server.map_or("first server by coin toss".to_string(), |server| format!("{} serving first", players[server].name));
+    println!("{}-point tiebreaks, {} vs {} ({}), {} tiebreaks (seed {}):", points_to_win, players[0].name, players[1].name, serving, simulations, config.seed);
+    for (player, wins) in players.iter().zip(results.wins) {
+        let prob = wins as f64 / simulations.max(1) as f64;
+        let margin = 1.96 * (prob * (1.0 - prob) / simulations.max(1) as f64).sqrt();
+        println!(" {}: {:.2}% (+/- {:.2})", player.name, prob * 100.0, margin * 100.0);
+    }
+
+    let total_points: usize = results.scores.iter().map(|(score, count)| (score[0] + score[1]) as usize * count).sum();
+    println!("Average points played: {:.2}", total_points as f64 / simulations.max(1) as f64);
+    println!("Final scores ({} first):", players[0].name);
+    for winner in 0..2 {
+        for (score, count) in results.scores.iter().filter(|(score, _)| (score[0] > score[1]) == (winner == 0)) {
+            println!(" {}-{}: {:.2}%", score[0], score[1], *count as f64 / simulations.max(1) as f64 * 100.0);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::TennisMatch;
+
+// Something that happened on a point, for the player it is credited to
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventKind {
+    Ace,
+    DoubleFault,
+    ReturnError,
+    Let,
+    // A conduct entry of the point, e.g. "time_violation:warning"
+    Conduct(String),
+    PenaltyPoint,
+    // The last point of a game, for its winner
+    Hold,
+    Break,
+    Tiebreak,
+    Set,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    // Numbered from 1 over the match
+    pub point_number: usize,
+    // Index into (player1, player2)
+    pub player: usize,
+    pub kind: EventKind,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventKind::Ace => write!(f, "ace"),
+            EventKind::DoubleFault => write!(f, "double fault"),
+            EventKind::ReturnError => write!(f, "return error"),
+            EventKind::Let => write!(f, "let"),
+            EventKind::Conduct(conduct) => write!(f, "{}", conduct.replace([':', '_'], " ")),
+            EventKind::PenaltyPoint => write!(f, "penalty point"),
+            EventKind::Hold => write!(f, "hold"),
+            EventKind::Break => write!(f, "break"),
+            EventKind::Tiebreak => write!(f, "tiebreak won"),
+            EventKind::Set => write!(f, "set won"),
+        }
+    }
+}
+
+// The score of a match after a point, in player order; `points` is the game score as called, server first
+#[derive(Clone, Debug, PartialEq)]
+pub struct Score {
+    pub sets: [i32; 2],
+    pub games: [i32; 2],
+    pub points: String,
+}
+
+// One point of the match, placed by set, game within the set and point within the game, all from 1
+#[derive(Clone, Debug)]
+pub struct TimelinePoint {
+    pub set: usize,
+    pub game: usize,
+    pub point: usize,
+    pub server: usize,
+    pub winner: usize,
+    pub score: Score,
+    // Player1's match win probability after the point
+    pub match_win_prob: f64,
+    pub events: Vec<Event>,
+}
+
+// A finished match point by point, to look up the score at any moment, what happened in a game and how the
+// win probability moved, without parsing the flat point log
+pub struct MatchTimeline {
+    pub players: [String; 2],
+    pub points: Vec<TimelinePoint>,
+}
+
+impl MatchTimeline {
+    pub fn from_match(match_sim: &TennisMatch) -> Self {
+        let players = [match_sim.player1.name.clone(), match_sim.player2.name.clone()];
+        let index = |name: Option<&str>| if name == Some(players[0].as_str()) { 0 } else { 1 };
+        let win_prob_key = format!("{}_match_win_prob", players[0]);
+        let mut log = match_sim.point_log.iter();
+        let mut points = Vec::new();
+        let mut set_game = (usize::MAX, 0);
+        for game in &match_sim.games_played {
+            set_game = if set_game.0 == game.set { (game.set, set_game.1 + 1) } else { (game.set, 1) };
+            let game_points: Vec<&HashMap<String, serde_json::Value>> = log.by_ref().take(game.points).collect();
+            for (i, entry) in game_points.iter().enumerate() {
+                let text = |key: &str| entry.get(key).and_then(|value| value.as_str()).unwrap_or("");
+                let (server, winner) = (index(Some(text("server"))), index(Some(text("winner"))));
+                // The log's scores are server first
+                let pair = |score: &str| {
+                    let (a, b) = score.split_once('-').unwrap_or(("0", "0"));
+                    let pair = [a.parse().unwrap_or(0), b.parse().unwrap_or(0)];
+                    if server == 0 { pair } else { [pair[1], pair[0]] }
+                };
+                let point_number = entry["point_number"].as_u64().unwrap_or(0) as usize;
+                let event = |player: usize, kind: EventKind| Event { point_number, player, kind };
+
+                let shots = entry["shots"].as_array().cloned().unwrap_or_default();
+                let outcome = |shot: &str| shots.iter().filter(|entry| entry["shot"] == shot).map(|entry| entry["outcome"].as_str().unwrap_or("").to_string()).collect::<Vec<_>>();
+                let mut events: Vec<Event> = outcome("let").iter().map(|_| event(server, EventKind::Let)).collect();
+                match outcome("serve").first().map(String::as_str) {
+                    Some("ace") => events.push(event(server, EventKind::Ace)),
+                    Some("double_fault") => events.push(event(server, EventKind::DoubleFault)),
+                    Some(_) => {}
+                    None => events.push(event(winner, EventKind::PenaltyPoint)),
+                }
+                if outcome("return").first().is_some_and(|outcome| outcome == "error") {
+                    events.push(event(1 - server, EventKind::ReturnError));
+                }
+                for conduct in text("conduct").split(';').filter(|conduct| !conduct.is_empty()) {
+                    let (offender, what) = conduct.split_once(':').unwrap_or((conduct, ""));
+                    events.push(event(index(Some(offender)), EventKind::Conduct(what.to_string())));
+                }
+                let score = Score { sets: pair(text("set_score")), games: pair(text("game_score")), points: text("point_score").to_string() };
+                if i + 1 == game_points.len() {
+                    events.push(event(game.winner, if game.tiebreak { EventKind::Tiebreak } else if game.winner == game.server { EventKind::Hold } else { EventKind::Break }));
+                    let sets_before = points.last().map_or([0, 0], |point: &TimelinePoint| point.score.sets);
+                    if score.sets != sets_before {
+                        events.push(event(game.winner, EventKind::Set));
+                    }
+                }
+                points.push(TimelinePoint {
+                    set: game.set + 1,
+                    game: set_game.1,
+                    point: i + 1,
+                    server,
+                    winner,
+                    score,
+                    match_win_prob: entry[&win_prob_key].as_f64().unwrap_or(0.5),
+                    events,
+                });
+            }
+        }
+        MatchTimeline { players, points }
+    }
+
+    // The score after point `point` of game `game` of set `set`, all counted from 1
+    pub fn score_after(&self, set: usize, game: usize, point: usize) -> Option<&Score> {
+        self.points.iter().find(|p| (p.set, p.game, p.point) == (set, game, point)).map(|p| &p.score)
+    }
+
+    // The points of game `game` of set `set`, counted from 1
+    pub fn game(&self, set: usize, game: usize) -> Vec<&TimelinePoint> {
+        self.points.iter().filter(|p| p.set == set && p.game == game).collect()
+    }
+
+    // What happened in game `game` of set `set`, point by point
+    pub fn events_in_game(&self, set: usize, game: usize) -> Vec<&Event> {
+        self.game(set, game).into_iter().flat_map(|point| &point.events).collect()
+    }
+
+    // Player1's match win probability after each point
+    pub fn win_prob_series(&self) -> Vec<f64> {
+        self.points.iter().map(|point| point.match_win_prob).collect()
+    }
+}
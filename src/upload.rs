@@ -0,0 +1,32 @@
+use object_store::path::{Path, PathPart};
+use object_store::{parse_url_opts, ObjectStoreExt, PutPayload};
+use url::Url;
+
+use crate::run_dir::{RunDirectory, MANIFEST_FILE};
+
+// Copies a run directory, manifest last, to `<prefix>/<run id>/` under an object store prefix such as
+// s3://bucket/runs/nightly, gs://bucket/runs or az://container/runs, and returns the number of files
+// uploaded. Credentials and regions come from the usual environment variables (AWS_ACCESS_KEY_ID,
+// GOOGLE_SERVICE_ACCOUNT, ...).
+pub fn upload_run(url: &str, run_dir: &RunDirectory) -> Result<usize, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("invalid upload url {}: {}", url, e))?;
+    let (store, prefix) = parse_url_opts(&parsed, std::env::vars()).map_err(|e| format!("unsupported upload url {}: {}", url, e))?;
+    let location = |name: &str| {
+        let parts = std::iter::once(run_dir.run_id.as_str()).chain(name.split('/')).map(PathPart::from);
+        Path::from_iter(prefix.parts().chain(parts))
+    };
+
+    let mut files: Vec<String> = run_dir.artifacts().into_iter().map(|(path, _)| path).collect();
+    files.push(MANIFEST_FILE.to_string());
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        for file in &files {
+            let path = run_dir.path.join(file);
+            let contents = std::fs::read(&path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+            store.put(&location(file), PutPayload::from(contents)).await
+                .map_err(|e| format!("could not upload {} to {}: {}", file, url, e))?;
+        }
+        Ok(files.len())
+    })
+}
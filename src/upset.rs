@@ -0,0 +1,68 @@
+use std::io::Write;
+
+use rayon::prelude::*;
+
+use crate::scoring::ScoringRules;
+use crate::{config_from_args, flag_value, match_seed, surface, Player, SimulationConfig, TennisMatch};
+
+// Default gaps in serve_win_prob between the favourite and the underdog
+const DEFAULT_GAPS: [f64; 11] = [0.0, 0.01, 0.02, 0.03, 0.04, 0.05, 0.06, 0.07, 0.08, 0.09, 0.10];
+
+// Two players alike in everything but serve_win_prob, `gap` apart around the configured players' average. The
+// placement and return profiles are dropped, since they carry serve probabilities of their own.
+fn synthetic_players(config: &SimulationConfig, gap: f64) -> [Player; 2] {
+    let base = (config.player1.serve_win_prob + config.player2.serve_win_prob) / 2.0;
+    let template = Player { serve_placement: None, return_profile: None, ..config.player1.clone() };
+    let player = |name: &str, serve_win_prob: f64| Player { name: name.to_string(), serve_win_prob: serve_win_prob.clamp(0.0, 1.0), ..template.clone() };
+    [player("Favourite", base + gap / 2.0), player("Underdog", base - gap / 2.0)]
+}
+
+// The underdog's share of `matches` matches, match n drawing from the per-match seed of n
+fn upset_rate(config: &SimulationConfig, players: &[Player; 2], best_of: i32, matches: usize) -> f64 {
+    // The configured scoring, or the usual one, played over the given number of sets
+    let rules = ScoringRules { sets_to_win: best_of / 2 + 1, ..config.scoring_rules() };
+    let upsets = (1..=matches)
+        .into_par_iter()
+        .filter(|n| {
+            let mut match_sim = TennisMatch::new(players[0].clone(), players[1].clone(), rules.clone(), config.model.clone(), match_seed(config.seed, *n));
+            match_sim.play_match().name == players[1].name
+        })
+        .count();
+    upsets as f64 / matches.max(1) as f64
+}
+
+// upset-curve [--config <config.json>] [--gaps 0,0.02,0.05] [--matches <n>] [--csv <path>] [--seed <n>]
+// Sweeps the gap in serve_win_prob between two otherwise identical players and prints the underdog's win
+// probability at each gap, best of 3 and best of 5, on every configured surface (or none)
+pub fn run(args: &[String]) -> Result<(), String> {
+    let config = config_from_args(args)?;
+    let gaps: Vec<f64> = match flag_value(args, "--gaps") {
+        Some(gaps) => gaps.split(',').map(|gap| gap.trim().parse::<f64>().map_err(|e| format!("invalid gap '{}': {}", gap, e))).collect::<Result<_, _>>()?,
+        None => DEFAULT_GAPS.to_vec(),
+    };
+    let matches = match flag_value(args, "--matches") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --matches: {}", e))?,
+        None => config.num_simulations,
+    };
+    let surfaces: Vec<Option<&surface::Surface>> = if config.surfaces.is_empty() { vec![None] } else { config.surfaces.iter().map(Some).collect() };
+
+    let mut rows = Vec::new();
+    println!("Underdog win probability by serve_win_prob gap, {} matches per cell (seed {}):", matches, config.seed);
+    println!("{:<12} {:>6} {:>10} {:>10}", "surface", "gap", "best of 3", "best of 5");
+    for surface in &surfaces {
+        let name = surface.map_or("none", |surface| surface.name.as_str());
+        for gap in &gaps {
+            let players = synthetic_players(&config, *gap).map(|player| surface.map_or(player.clone(), |surface| surface.apply(&player)));
+            let rates = [3, 5].map(|best_of| upset_rate(&config, &players, best_of, matches));
+            println!("{:<12} {:>6.3} {:>9.2}% {:>9.2}%", name, gap, rates[0] * 100.0, rates[1] * 100.0);
+            rows.push(format!("{},{},{},{}", name, gap, rates[0], rates[1]));
+        }
+    }
+
+    if let Some(path) = flag_value(args, "--csv") {
+        let mut file = std::fs::File::create(path).map_err(|e| format!("could not create {}: {}", path, e))?;
+        writeln!(file, "surface,gap,upset_prob_best_of_3,upset_prob_best_of_5").and_then(|_| writeln!(file, "{}", rows.join("\n"))).map_err(|e| format!("could not write {}: {}", path, e))?;
+        println!("Upset curve written to '{}'", path);
+    }
+    Ok(())
+}
@@ -0,0 +1,272 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::scoring::{GameState, ScoringRules};
+use crate::{find_player, flag_value, match_seed, SimulationConfig, TennisMatch};
+
+// A score to play a match out from, in player order (player1 first)
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MatchState {
+    pub sets: [i32; 2],
+    pub games: [i32; 2],
+    // Points of the current game, counted 0, 1, 2, 3 for 0, 15, 30, 40 and on past 40 at deuce, or of the tiebreak
+    pub points: [i32; 2],
+    // 0 if player1 is serving the current point, 1 if player2 is
+    pub server: usize,
+}
+
+impl MatchState {
+    pub fn is_tiebreak(&self, rules: &ScoringRules) -> bool {
+        rules.tiebreak_at(self.sets, self.games).is_some()
+    }
+
+    // Parses the set scores and current games ("6-4 3-6 4-4", with "7-6" for a set won on a tiebreak) and the
+    // points of the current game ("30-40", "40-A", or "5-4" in a tiebreak), all in player order
+    pub fn parse(score: &str, points: Option<&str>, server: usize, rules: &ScoringRules) -> Result<Self, String> {
+        let mut pairs = Vec::new();
+        for token in score.split_whitespace() {
+            let (a, b) = token.split_once('-').ok_or_else(|| format!("invalid score '{}'; expected games like 6-4", token))?;
+            let games = |value: &str| value.parse::<i32>().map_err(|_| format!("invalid games in '{}'", token));
+            pairs.push([games(a)?, games(b)?]);
+        }
+        let games = pairs.pop().unwrap_or([0, 0]);
+        let mut sets = [0, 0];
+        for set in &pairs {
+            let (winner, loser) = if set[0] > set[1] { (0, 1) } else { (1, 0) };
+            if !rules.is_finished_set(sets, [set[winner], set[loser]]) {
+                return Err(format!("{}-{} is not a finished set", set[0], set[1]));
+            }
+            sets[winner] += 1;
+        }
+        let points = match points {
+            Some(points) => parse_points(points, rules.tiebreak_at(sets, games).is_some())?,
+            None => [0, 0],
+        };
+        Ok(MatchState { sets, games, points, server })
+    }
+
+    // Rejects scores that can't occur in a match still in play under the given format
+    pub fn validate(&self, rules: &ScoringRules) -> Result<(), String> {
+        if self.sets.iter().any(|sets| *sets < 0 || *sets >= rules.sets_to_win) {
+            return Err(format!("sets {}-{}: the match is over or the score is invalid for best of {}", self.sets[0], self.sets[1], rules.best_of()));
+        }
+        let [a, b] = self.games;
+        if !rules.is_set_in_progress(self.sets, self.games) {
+            return Err(format!("games {}-{} are not a set in progress", a, b));
+        }
+        let [a, b] = self.points;
+        let over = match rules.tiebreak_at(self.sets, self.games) {
+            Some(target) => a.max(b) >= target && (a - b).abs() >= 2,
+            None => matches!(rules.game_state(self.points), GameState::Won(_)),
+        };
+        if a < 0 || b < 0 || over {
+            return Err(format!("points {}-{} are not a game in progress", a, b));
+        }
+        if self.server > 1 {
+            return Err(format!("server {} must be 0 (player1) or 1 (player2)", self.server));
+        }
+        Ok(())
+    }
+
+    // The score after `winner` (0 = player1) wins the next point, and the match winner if that point ends it.
+    // Servers alternate each game, and in a tiebreak after the first point and then every two points; the
+    // player who received first in a tiebreak serves first in the next set.
+    pub fn after_point(&self, winner: usize, rules: &ScoringRules) -> (MatchState, Option<usize>) {
+        let mut next = self.clone();
+        next.points[winner] += 1;
+        let [won, lost] = [next.points[winner], next.points[1 - winner]];
+        if let Some(target) = rules.tiebreak_at(self.sets, self.games) {
+            let played = self.points.iter().sum::<i32>();
+            if won >= target && won - lost >= 2 {
+                // Point `played + 1` was served by the first server if (played + 1) / 2 is even
+                let first_server = if ((played + 1) / 2) % 2 == 0 { self.server } else { 1 - self.server };
+                next.server = 1 - first_server;
+                return next.win_set(winner, rules);
+            }
+            if played % 2 == 0 {
+                next.server = 1 - self.server;
+            }
+            return (next, None);
+        }
+        if rules.game_state(next.points) != GameState::Won(winner) {
+            return (next, None);
+        }
+        next.points = [0, 0];
+        next.games[winner] += 1;
+        next.server = 1 - self.server;
+        let [won, lost] = [next.games[winner], next.games[1 - winner]];
+        if rules.set_won(won, lost) {
+            return next.win_set(winner, rules);
+        }
+        (next, None)
+    }
+
+    fn win_set(mut self, winner: usize, rules: &ScoringRules) -> (MatchState, Option<usize>) {
+        self.points = [0, 0];
+        self.games = [0, 0];
+        self.sets[winner] += 1;
+        let match_winner = if self.sets[winner] >= rules.sets_to_win { Some(winner) } else { None };
+        (self, match_winner)
+    }
+
+    // The score as it would be called, e.g. "sets 1-0, games 4-4, 30-40"
+    pub fn describe(&self, rules: &ScoringRules) -> String {
+        let points = if self.is_tiebreak(rules) {
+            format!("tiebreak {}-{}", self.points[0], self.points[1])
+        } else {
+            let [a, b] = self.points;
+            let call = |points: i32| match points {
+                0 => "0".to_string(),
+                1 => "15".to_string(),
+                2 => "30".to_string(),
+                _ => "40".to_string(),
+            };
+            if a >= 3 && b >= 3 {
+                match a - b {
+                    0 => "deuce".to_string(),
+                    1 => "A-40".to_string(),
+                    _ => "40-A".to_string(),
+                }
+            } else {
+                format!("{}-{}", call(a), call(b))
+            }
+        };
+        format!("sets {}-{}, games {}-{}, {}", self.sets[0], self.sets[1], self.games[0], self.games[1], points)
+    }
+}
+
+// Points of the current game in tennis notation ("0", "15", "30", "40", "A" or "AD"), or tiebreak points
+pub fn parse_points(points: &str, tiebreak: bool) -> Result<[i32; 2], String> {
+    let (a, b) = points.split_once('-').ok_or_else(|| format!("invalid points '{}'; expected e.g. 30-40", points))?;
+    if tiebreak {
+        let value = |value: &str| value.parse::<i32>().map_err(|_| format!("invalid tiebreak points '{}'", points));
+        return Ok([value(a)?, value(b)?]);
+    }
+    let value = |value: &str| match value.to_ascii_uppercase().as_str() {
+        "0" | "L" | "LOVE" => Ok(0),
+        "15" => Ok(1),
+        "30" => Ok(2),
+        "40" => Ok(3),
+        "A" | "AD" => Ok(4),
+        _ => Err(format!("invalid points '{}'; use 0, 15, 30, 40 or A", value)),
+    };
+    let (a, b) = (value(a)?, value(b)?);
+    if a == 4 && b != 3 || b == 4 && a != 3 {
+        return Err(format!("invalid points '{}'; advantage is only against 40", points));
+    }
+    Ok([a, b])
+}
+
+// How playouts from a state went, for player1 where there is a winner: the match, the game in play (the next
+// one at 0-0), the set in play and the set after it
+#[derive(Default)]
+pub struct Playouts {
+    pub simulations: usize,
+    pub matches_won: usize,
+    pub games_won: usize,
+    pub games_to_deuce: usize,
+    pub sets_won: usize,
+    pub set_tiebreaks: usize,
+    // Playouts that went on to another set, and those in which it had a tiebreak
+    pub next_sets: usize,
+    pub next_set_tiebreaks: usize,
+}
+
+impl Playouts {
+    fn merge(mut self, other: Playouts) -> Playouts {
+        self.simulations += other.simulations;
+        self.matches_won += other.matches_won;
+        self.games_won += other.games_won;
+        self.games_to_deuce += other.games_to_deuce;
+        self.sets_won += other.sets_won;
+        self.set_tiebreaks += other.set_tiebreaks;
+        self.next_sets += other.next_sets;
+        self.next_set_tiebreaks += other.next_set_tiebreaks;
+        self
+    }
+
+    pub fn share(&self, count: usize, of: usize) -> f64 {
+        count as f64 / of.max(1) as f64
+    }
+}
+
+// Plays the configured match out from `state` `simulations` times. Each playout draws from the per-match seed
+// of its number, so re-running from a new score uses the same random numbers.
+pub fn play_out(config: &SimulationConfig, state: &MatchState, simulations: usize) -> Playouts {
+    let rules = config.scoring_rules();
+    let set = (state.sets[0] + state.sets[1]) as usize;
+    (1..=simulations)
+        .into_par_iter()
+        .map(|n| {
+            let mut match_sim = TennisMatch::new(config.player1.clone(), config.player2.clone(), rules.clone(), config.model.clone(), match_seed(config.seed, n));
+            match_sim.resume_from(state);
+            let winner = match_sim.play_on();
+            let games = &match_sim.games_played;
+            let game = &games[0];
+            // Points before the state count too; a game reaches deuce at 3-3, the seventh point
+            let game_points = state.points[0] + state.points[1] + game.points as i32;
+            let set_winner = games.iter().rfind(|game| game.set == set).map_or(0, |game| game.winner);
+            let next_set: Vec<_> = games.iter().filter(|game| game.set == set + 1).collect();
+            Playouts {
+                simulations: 1,
+                matches_won: (winner.name == config.player1.name) as usize,
+                games_won: (game.winner == 0) as usize,
+                games_to_deuce: (!game.tiebreak && game_points >= 7) as usize,
+                sets_won: (set_winner == 0) as usize,
+                set_tiebreaks: games.iter().any(|game| game.set == set && game.tiebreak) as usize,
+                next_sets: !next_set.is_empty() as usize,
+                next_set_tiebreaks: next_set.iter().any(|game| game.tiebreak) as usize,
+            }
+        })
+        .reduce(Playouts::default, Playouts::merge)
+}
+
+// Player1's chance of winning the match from `state`, over `simulations` playouts
+pub fn win_probability(config: &SimulationConfig, state: &MatchState, simulations: usize) -> f64 {
+    let playouts = play_out(config, state, simulations);
+    playouts.share(playouts.matches_won, playouts.simulations)
+}
+
+// what-if [--config <config.json>] --score <sets and games> [--points <points>] --server <player> [--simulations <n>] [--seed <n>]
+// Plays the configured match out from a given score and prints each player's chance of winning it, and of
+// the near-term markets: the game and set in play, whether the game goes to deuce and whether this set and the
+// next have a tiebreak
+pub fn run(args: &[String]) -> Result<(), String> {
+    let usage = "usage: what-if [--config <config.json>] --score \"6-4 3-6 4-4\" [--points 30-40] --server <player> [--simulations <n>]";
+    let config = crate::config_from_args(args)?;
+    let score = flag_value(args, "--score").ok_or(usage)?;
+    let server = flag_value(args, "--server").ok_or(usage)?;
+    let players = [config.player1.clone(), config.player2.clone()];
+    let server = if find_player(&players, server)?.name == players[0].name { 0 } else { 1 };
+    let simulations = match flag_value(args, "--simulations") {
+        Some(value) => value.parse::<usize>().map_err(|e| format!("invalid --simulations: {}", e))?,
+        None => 10000,
+    };
+
+    let rules = config.scoring_rules();
+    let state = MatchState::parse(score, flag_value(args, "--points"), server, &rules)?;
+    state.validate(&rules)?;
+    let playouts = play_out(&config, &state, simulations);
+    let prob = playouts.share(playouts.matches_won, simulations);
+    // 95% interval of the Monte Carlo estimate
+    let margin = 1.96 * (prob * (1.0 - prob) / simulations.max(1) as f64).sqrt();
+    println!("{} vs {} at {} ({} serving), best of {}:", players[0].name, players[1].name, state.describe(&rules), players[server].name, config.best_of);
+    println!("{}: {:.2}% (+/- {:.2})", players[0].name, prob * 100.0, margin * 100.0);
+    println!("{}: {:.2}% (+/- {:.2})", players[1].name, (1.0 - prob) * 100.0, margin * 100.0);
+
+    let percent = |count: usize, of: usize| format!("{:.2}%", playouts.share(count, of) * 100.0);
+    let both = |won: usize| format!("{} {}, {} {}", players[0].name, percent(won, simulations), players[1].name, percent(simulations - won, simulations));
+    let game = if state.is_tiebreak(&rules) {
+        format!("Tiebreak: {}", both(playouts.games_won))
+    } else {
+        let label = if state.points == [0, 0] { "Next game" } else { "Current game" };
+        format!("{}: {}; deuce {}", label, both(playouts.games_won), percent(playouts.games_to_deuce, simulations))
+    };
+    println!("{}", game);
+    println!("Current set: {}; tiebreak {}", both(playouts.sets_won), percent(playouts.set_tiebreaks, simulations));
+    if playouts.next_sets > 0 {
+        println!("Next set: played {}; tiebreak {} of those", percent(playouts.next_sets, simulations), percent(playouts.next_set_tiebreaks, playouts.next_sets));
+    }
+    println!("({} playouts, seed {})", simulations, config.seed);
+    Ok(())
+}